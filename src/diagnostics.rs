@@ -0,0 +1,105 @@
+//! Diagnostics for recipe files skipped while scanning a directory.
+//!
+//! Searching and tree building keep going when individual files cannot be
+//! read or parsed, e.g. files still being synced or saved with a broken
+//! encoding. The `*_with_diagnostics` variants return the partial result
+//! together with every file that was left out and why.
+
+use crate::model::RecipeEntryError;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::path::Path;
+
+/// A file that could not be read or parsed and was left out of a result.
+#[derive(Debug)]
+pub struct SkippedFile {
+    /// Path of the skipped file or directory
+    pub path: Utf8PathBuf,
+    /// Why the file was skipped
+    pub error: RecipeEntryError,
+}
+
+impl SkippedFile {
+    pub(crate) fn new(path: impl AsRef<Utf8Path>, error: impl Into<RecipeEntryError>) -> Self {
+        SkippedFile {
+            path: path.as_ref().to_path_buf(),
+            error: error.into(),
+        }
+    }
+
+    /// Creates an entry for a directory entry that could not be listed or
+    /// an ignore file that could not be parsed.
+    pub(crate) fn from_walk_error(error: ignore::Error) -> Self {
+        let path = walk_error_path(&error).map(lossy_path).unwrap_or_default();
+        let message = error.to_string();
+        let error = error
+            .into_io_error()
+            .unwrap_or_else(|| std::io::Error::other(message));
+        SkippedFile::new(path, error)
+    }
+}
+
+/// Returns the path a walk error is about, if it has one.
+fn walk_error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        ignore::Error::Partial(errors) => errors.iter().find_map(walk_error_path),
+        _ => None,
+    }
+}
+
+/// A result that may be incomplete because some files were skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::{search_with_diagnostics, SearchOptions};
+/// use camino::Utf8Path;
+///
+/// let report = search_with_diagnostics(Utf8Path::new("./recipes"), "soup", &SearchOptions::default())?;
+/// for skipped in &report.skipped {
+///     eprintln!("Skipped {}: {}", skipped.path, skipped.error);
+/// }
+/// let recipes = report.value;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Partial<T> {
+    /// The result built from all files that could be read
+    pub value: T,
+    /// Files left out of the result, sorted by path
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl<T> Partial<T> {
+    pub(crate) fn new(value: T, mut skipped: Vec<SkippedFile>) -> Self {
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        Partial { value, skipped }
+    }
+
+    /// Returns true if no file was skipped.
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    /// Returns the result, discarding the diagnostics.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Transforms the result, keeping the diagnostics.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Partial<U> {
+        Partial {
+            value: f(self.value),
+            skipped: self.skipped,
+        }
+    }
+}
+
+/// Converts a path to UTF-8, replacing invalid sequences.
+pub(crate) fn lossy_path(path: &Path) -> Utf8PathBuf {
+    Utf8PathBuf::from(path.to_string_lossy().into_owned())
+}
@@ -5,7 +5,9 @@
 //! automatically handles both .cook and .menu file extensions.
 
 use crate::model::{RecipeEntry, RecipeEntryError};
-use camino::{Utf8Path, Utf8PathBuf};
+use crate::normalize::fold;
+use crate::walk::{is_ignored, WalkOptions};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use thiserror::Error;
 
 /// Errors that can occur when fetching recipes.
@@ -28,6 +30,13 @@ pub enum FetchError {
 /// - Direct file paths with extensions (e.g., "recipe.cook", "menu.menu")
 /// - Names without extensions (automatically tries .cook and .menu)
 ///
+/// If no file matches the name exactly, names are compared ignoring case,
+/// Unicode normalization form and diacritics, so "creme brulee" finds
+/// "Crème Brûlée.cook". The returned entry keeps the original file name.
+///
+/// Files excluded by `.cookignore` files (see [`crate::walk`]) are not
+/// found, like in [`search`](crate::search()) and [`build_tree`](crate::build_tree).
+///
 /// # Arguments
 ///
 /// * `base_dirs` - An iterator of directory paths to search in order
@@ -52,31 +61,107 @@ pub enum FetchError {
 pub fn get_recipe<P: AsRef<Utf8Path>>(
     base_dirs: impl IntoIterator<Item = P>,
     name: P,
+) -> Result<RecipeEntry, FetchError> {
+    get_recipe_with_options(base_dirs, name, &WalkOptions::default())
+}
+
+/// Loads a recipe by name like [`get_recipe`], also leaving out the files
+/// excluded by `options`.
+pub fn get_recipe_with_options<P: AsRef<Utf8Path>>(
+    base_dirs: impl IntoIterator<Item = P>,
+    name: P,
+    options: &WalkOptions,
 ) -> Result<RecipeEntry, FetchError> {
     let name = name.as_ref();
+    let base_dirs: Vec<P> = base_dirs.into_iter().collect();
+    let visible = |base_dir: &Utf8Path, path: Utf8PathBuf| {
+        (!is_ignored(base_dir, &path, options)).then_some(path)
+    };
+
+    // Exact matches in any directory take precedence over folded ones
+    let path = base_dirs
+        .iter()
+        .find_map(|base_dir| {
+            let base_dir = base_dir.as_ref();
+            visible(base_dir, exact_recipe_path(base_dir, name)?)
+        })
+        .or_else(|| {
+            base_dirs.iter().find_map(|base_dir| {
+                let base_dir = base_dir.as_ref();
+                visible(base_dir, folded_recipe_path(base_dir, name)?)
+            })
+        });
+
+    match path {
+        Some(path) => RecipeEntry::from_path(path).map_err(FetchError::RecipeEntryError),
+        None => Err(FetchError::InvalidPath(name.to_path_buf())),
+    }
+}
 
-    for base_dir in base_dirs {
-        if name.extension().is_some() {
-            // If the name already has an extension, use it as-is
-            let recipe_path = base_dir.as_ref().join(name);
-            if recipe_path.exists() {
-                return RecipeEntry::from_path(recipe_path).map_err(FetchError::RecipeEntryError);
-            }
-        } else {
-            // Try both .cook and .menu extensions
-            let cook_path = base_dir.as_ref().join(format!("{name}.cook"));
-            if cook_path.exists() {
-                return RecipeEntry::from_path(cook_path).map_err(FetchError::RecipeEntryError);
-            }
-
-            let menu_path = base_dir.as_ref().join(format!("{name}.menu"));
-            if menu_path.exists() {
-                return RecipeEntry::from_path(menu_path).map_err(FetchError::RecipeEntryError);
-            }
+/// Finds a recipe file whose path matches the name exactly.
+fn exact_recipe_path(base_dir: &Utf8Path, name: &Utf8Path) -> Option<Utf8PathBuf> {
+    if name.extension().is_some() {
+        // If the name already has an extension, use it as-is
+        let recipe_path = base_dir.join(name);
+        return recipe_path.exists().then_some(recipe_path);
+    }
+
+    // Try both .cook and .menu extensions
+    ["cook", "menu"]
+        .into_iter()
+        .map(|extension| base_dir.join(format!("{name}.{extension}")))
+        .find(|path| path.exists())
+}
+
+/// Finds a recipe file comparing every path component by its folded form.
+fn folded_recipe_path(base_dir: &Utf8Path, name: &Utf8Path) -> Option<Utf8PathBuf> {
+    let mut components = Vec::new();
+    for component in name.components() {
+        match component {
+            Utf8Component::Normal(component) => components.push(fold(component)),
+            Utf8Component::CurDir => {}
+            _ => return None,
         }
     }
+    let file_name = components.pop()?;
+
+    let mut dir = base_dir.to_path_buf();
+    for component in components {
+        dir = sorted_entries(&dir)
+            .into_iter()
+            .find(|path| path.is_dir() && path.file_name().map(fold) == Some(component.clone()))?;
+    }
 
-    Err(FetchError::InvalidPath(name.to_path_buf()))
+    let entries = sorted_entries(&dir);
+    if name.extension().is_some() {
+        return entries
+            .into_iter()
+            .find(|path| path.is_file() && path.file_name().map(fold) == Some(file_name.clone()));
+    }
+    ["cook", "menu"]
+        .into_iter()
+        .find_map(|extension| {
+            entries.iter().find(|path| {
+                path.is_file()
+                    && path.extension() == Some(extension)
+                    && path.file_stem().map(fold) == Some(file_name.clone())
+            })
+        })
+        .cloned()
+}
+
+/// Lists the entries of a directory sorted by name, so that lookups
+/// with several matching spellings are deterministic.
+fn sorted_entries(dir: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let Ok(entries) = dir.read_dir_utf8() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<Utf8PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .collect();
+    paths.sort();
+    paths
 }
 
 /// Convenience function to search for recipes using string paths.
@@ -296,4 +381,75 @@ mod tests {
         let result = get_recipe([&temp_dir_path], &Utf8PathBuf::from("weekly.menu")).unwrap();
         assert_eq!(result.path(), Some(&menu_path));
     }
+
+    #[test]
+    fn test_get_recipe_ignores_accents_and_normalization() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let sub_dir = temp_dir_path.join("Pâtisserie");
+        fs::create_dir_all(&sub_dir).unwrap();
+
+        // Decomposed (NFD) file name, as saved on macOS
+        let decomposed = "Cre\u{300}me Bru\u{302}le\u{301}e";
+        create_test_recipe(&sub_dir, decomposed, "Caramelize @sugar{}");
+
+        for name in [
+            "patisserie/creme brulee",
+            "Pâtisserie/Crème Brûlée",
+            "PATISSERIE/CRÈME BRÛLÉE.cook",
+        ] {
+            let result = get_recipe([&temp_dir_path], &Utf8PathBuf::from(name)).unwrap();
+            // The original spelling is preserved
+            assert_eq!(result.name().as_deref(), Some(decomposed));
+        }
+
+        let result = get_recipe([&temp_dir_path], &Utf8PathBuf::from("patisserie/flan"));
+        assert!(matches!(result, Err(FetchError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_get_recipe_prefers_exact_match() {
+        let temp_dir1 = TempDir::new().unwrap();
+        let temp_dir2 = TempDir::new().unwrap();
+        let dir1 = Utf8PathBuf::from_path_buf(temp_dir1.path().to_path_buf()).unwrap();
+        let dir2 = Utf8PathBuf::from_path_buf(temp_dir2.path().to_path_buf()).unwrap();
+
+        create_test_recipe(&dir1, "Crêpes", "Crêpes with @jam{}");
+        let exact = create_test_recipe(&dir2, "crepes", "Crepes with @sugar{}");
+
+        // An exact match in a later directory wins over a folded one
+        let result = get_recipe([&dir1, &dir2], &Utf8PathBuf::from("crepes")).unwrap();
+        assert_eq!(result.path(), Some(&exact));
+    }
+
+    #[test]
+    fn test_get_recipe_honors_cookignore() {
+        let temp_dir1 = TempDir::new().unwrap();
+        let temp_dir2 = TempDir::new().unwrap();
+        let dir1 = Utf8PathBuf::from_path_buf(temp_dir1.path().to_path_buf()).unwrap();
+        let dir2 = Utf8PathBuf::from_path_buf(temp_dir2.path().to_path_buf()).unwrap();
+        let archive = dir1.join("archive");
+        fs::create_dir_all(&archive).unwrap();
+        fs::write(dir1.join(".cookignore"), "archive/\nsoup.cook\n").unwrap();
+
+        create_test_recipe(&archive, "stew", "Old @beef{} stew");
+        create_test_recipe(&dir1, "soup", "Old @leek{} soup");
+        create_test_recipe(&dir1, "salad", "Toss @lettuce{}");
+        let current = create_test_recipe(&dir2, "soup", "New @leek{} soup");
+
+        let result = get_recipe([&dir1], &Utf8PathBuf::from("archive/stew"));
+        assert!(matches!(result, Err(FetchError::InvalidPath(_))));
+
+        // Ignored files fall through to later directories
+        let result = get_recipe([&dir1, &dir2], &Utf8PathBuf::from("Soup")).unwrap();
+        assert_eq!(result.path(), Some(&current));
+
+        let options = WalkOptions {
+            exclude: vec!["salad.*".to_string()],
+            ..Default::default()
+        };
+        assert!(get_recipe([&dir1], &Utf8PathBuf::from("salad")).is_ok());
+        let result = get_recipe_with_options([&dir1], &Utf8PathBuf::from("salad"), &options);
+        assert!(matches!(result, Err(FetchError::InvalidPath(_))));
+    }
 }
@@ -3,12 +3,24 @@
 //! This module provides FFI-safe types and functions for use with UniFFI.
 //! Complex types are converted to simpler representations suitable for FFI.
 
-use crate::fetcher::{get_recipe_str, FetchError};
+use crate::diagnostics::{Partial, SkippedFile};
+use crate::fetcher::{get_recipe_str, get_recipe_with_options, FetchError};
 use crate::model::{Metadata, RecipeEntry, RecipeEntryError, StepImageCollection};
-use crate::search::{search as search_internal, SearchError};
-use crate::tree::{build_tree as build_tree_internal, RecipeTree, TreeError};
-use camino::Utf8Path;
-use std::sync::Arc;
+use crate::search::{
+    search as search_internal, search_detailed_with_options, search_iter,
+    search_roots_with_options, search_with_diagnostics, search_with_options, FieldWeights,
+    Fuzziness, Language, MatchField, Query, QueryError, SearchError, SearchHit, SearchOptions,
+    SearchResults, Stemmer, Synonyms,
+};
+use crate::tree::{
+    build_tree as build_tree_internal, build_tree_with_diagnostics, diff_trees, refresh_tree,
+    Asset, AssetKind, NameCollision, RecipeFilter, RecipeTree, TreeDiff, TreeError, TreeOptions,
+    TreeSort,
+};
+use crate::walk::{SymlinkPolicy, WalkOptions};
+use crate::watch::WatchEvent;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::sync::{Arc, Mutex};
 
 /// FFI-safe error type that wraps all possible errors.
 #[derive(Debug, Clone, uniffi::Error)]
@@ -25,6 +37,8 @@ pub enum CooklangError {
     SearchError { reason: String },
     /// Tree operation failed
     TreeError { reason: String },
+    /// Search query could not be parsed
+    InvalidQuery { reason: String },
 }
 
 impl std::fmt::Display for CooklangError {
@@ -36,6 +50,7 @@ impl std::fmt::Display for CooklangError {
             CooklangError::InvalidPath { reason } => write!(f, "Invalid path: {}", reason),
             CooklangError::SearchError { reason } => write!(f, "Search error: {}", reason),
             CooklangError::TreeError { reason } => write!(f, "Tree error: {}", reason),
+            CooklangError::InvalidQuery { reason } => write!(f, "Invalid query: {}", reason),
         }
     }
 }
@@ -73,7 +88,18 @@ impl From<RecipeEntryError> for CooklangError {
 
 impl From<SearchError> for CooklangError {
     fn from(e: SearchError) -> Self {
-        CooklangError::SearchError {
+        match e {
+            SearchError::QueryError(e) => e.into(),
+            e => CooklangError::SearchError {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<QueryError> for CooklangError {
+    fn from(e: QueryError) -> Self {
+        CooklangError::InvalidQuery {
             reason: e.to_string(),
         }
     }
@@ -218,6 +244,12 @@ impl FfiRecipeEntry {
         FfiMetadata::from(self.inner.metadata())
     }
 
+    /// Returns the recipe's metadata, or why the recipe file of a lazy
+    /// tree could not be read.
+    pub fn try_metadata(&self) -> Result<FfiMetadata, CooklangError> {
+        Ok(FfiMetadata::from(self.inner.try_metadata()?))
+    }
+
     /// Returns the recipe's tags.
     pub fn tags(&self) -> Vec<String> {
         self.inner.tags()
@@ -276,6 +308,478 @@ impl FfiRecipeEntry {
     }
 }
 
+/// A parsed search query.
+///
+/// Parsing a query once and reusing it avoids re-parsing the same
+/// query string and surfaces syntax errors before searching.
+#[derive(uniffi::Object)]
+pub struct FfiQuery {
+    inner: Query,
+}
+
+#[uniffi::export]
+impl FfiQuery {
+    /// Parses a query string such as `tag:vegan -mushroom "sheet pan"`.
+    #[uniffi::constructor]
+    pub fn parse(query: String) -> Result<Arc<Self>, CooklangError> {
+        let inner = Query::parse(&query)?;
+        Ok(Arc::new(FfiQuery { inner }))
+    }
+
+    /// Returns the free text terms and phrases used for ranking.
+    pub fn terms(&self) -> Vec<String> {
+        self.inner.terms()
+    }
+
+    /// Returns true if the query contains metadata field, ingredient,
+    /// cookware or timer filters.
+    pub fn has_filters(&self) -> bool {
+        self.inner.has_filters()
+    }
+
+    /// Returns the ingredients the query asks for (`@name` clauses).
+    pub fn ingredients(&self) -> Vec<String> {
+        self.inner.ingredients()
+    }
+
+    /// Returns the cookware the query asks for (`#name` clauses).
+    pub fn cookware(&self) -> Vec<String> {
+        self.inner.cookware()
+    }
+
+    /// Returns the normalized query string.
+    pub fn normalized(&self) -> String {
+        self.inner.to_string()
+    }
+}
+
+/// FFI-safe search options.
+#[derive(Debug, Clone, Default, uniffi::Record)]
+pub struct FfiSearchOptions {
+    /// Enables typo-tolerant matching
+    #[uniffi(default = false)]
+    pub fuzzy: bool,
+    /// Maximum edits per term when fuzzy; `None` picks a tolerance based on term length
+    #[uniffi(default = None)]
+    pub max_edits: Option<u32>,
+    /// Ingredients on hand; recipes using more of them rank higher
+    #[uniffi(default = [])]
+    pub on_hand: Vec<String>,
+    /// Number of ranked results to skip
+    #[uniffi(default = 0)]
+    pub offset: u32,
+    /// Maximum number of results to return; `None` returns all of them
+    #[uniffi(default = None)]
+    pub limit: Option<u32>,
+    /// Relevance weights per recipe field; `None` uses the default weights
+    #[uniffi(default = None)]
+    pub weights: Option<FfiFieldWeights>,
+    /// Language whose stemmer lets terms match other forms of the same word;
+    /// `None` disables stemming
+    #[uniffi(default = None)]
+    pub language: Option<FfiLanguage>,
+    /// Groups of words that mean the same; `None` loads `synonyms.yaml`
+    /// from the recipe directory, if present
+    #[uniffi(default = None)]
+    pub synonyms: Option<Vec<Vec<String>>>,
+    /// Files left out in addition to `.cookignore` files; `None` only
+    /// honors `.cookignore` files
+    #[uniffi(default = None)]
+    pub walk: Option<FfiWalkOptions>,
+}
+
+/// FFI-safe options controlling which files directory walks leave out.
+#[derive(Debug, Clone, Default, uniffi::Record)]
+pub struct FfiWalkOptions {
+    /// Gitignore-style patterns relative to the base directory, e.g. `archive/`
+    #[uniffi(default = [])]
+    pub exclude: Vec<String>,
+    /// Also honor `.gitignore` files
+    #[uniffi(default = false)]
+    pub gitignore: bool,
+    /// How symbolic links are treated; `None` follows them
+    #[uniffi(default = None)]
+    pub symlinks: Option<FfiSymlinkPolicy>,
+}
+
+impl From<FfiWalkOptions> for WalkOptions {
+    fn from(options: FfiWalkOptions) -> Self {
+        WalkOptions {
+            exclude: options.exclude,
+            gitignore: options.gitignore,
+            symlinks: options.symlinks.map(Into::into).unwrap_or_default(),
+        }
+    }
+}
+
+/// How directory walks treat symbolic links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiSymlinkPolicy {
+    /// Follow links to files and directories
+    Follow,
+    /// Leave out links to files and directories
+    Skip,
+    /// Follow links, but list every file only once
+    FollowOnce,
+}
+
+impl From<FfiSymlinkPolicy> for SymlinkPolicy {
+    fn from(policy: FfiSymlinkPolicy) -> Self {
+        match policy {
+            FfiSymlinkPolicy::Follow => SymlinkPolicy::Follow,
+            FfiSymlinkPolicy::Skip => SymlinkPolicy::Skip,
+            FfiSymlinkPolicy::FollowOnce => SymlinkPolicy::FollowOnce,
+        }
+    }
+}
+
+/// FFI-safe options for building recipe trees.
+#[derive(Debug, Clone, Default, uniffi::Record)]
+pub struct FfiTreeOptions {
+    /// Files left out in addition to `.cookignore` files; `None` only
+    /// honors `.cookignore` files
+    #[uniffi(default = None)]
+    pub walk: Option<FfiWalkOptions>,
+    /// How the children of every node are ordered; `None` sorts by name
+    #[uniffi(default = None)]
+    pub sort: Option<FfiTreeSort>,
+    /// List directories before recipes
+    #[uniffi(default = false)]
+    pub dirs_first: bool,
+    /// Also list files other than recipes in the `assets` of their
+    /// directory's node, and include directories without recipes
+    #[uniffi(default = false)]
+    pub assets: bool,
+    /// How many levels of subdirectories are listed; deeper directories
+    /// are included as unexpanded nodes to list with
+    /// `FfiRecipeTree::expand`. `None` lists all of them
+    #[uniffi(default = None)]
+    pub max_depth: Option<u32>,
+    /// Only read recipe files when their metadata is first accessed;
+    /// recipe nodes are then named after their files
+    #[uniffi(default = false)]
+    pub lazy: bool,
+}
+
+impl From<FfiTreeOptions> for TreeOptions {
+    fn from(options: FfiTreeOptions) -> Self {
+        TreeOptions {
+            walk: options.walk.map(Into::into).unwrap_or_default(),
+            sort: options.sort.map(Into::into).unwrap_or_default(),
+            dirs_first: options.dirs_first,
+            assets: options.assets,
+            max_depth: options.max_depth.map(|depth| depth as usize),
+            lazy: options.lazy,
+        }
+    }
+}
+
+/// How the children of tree nodes are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiTreeSort {
+    /// Alphabetically by file name
+    Name,
+    /// By file name, comparing numbers by their value
+    Natural,
+    /// By displayed name, i.e. recipe title where there is one
+    Title,
+    /// Most recently modified first
+    Modified,
+    /// In the order listed in each directory's `_dir.yaml`
+    Custom,
+}
+
+impl From<FfiTreeSort> for TreeSort {
+    fn from(sort: FfiTreeSort) -> Self {
+        match sort {
+            FfiTreeSort::Name => TreeSort::Name,
+            FfiTreeSort::Natural => TreeSort::Natural,
+            FfiTreeSort::Title => TreeSort::Title,
+            FfiTreeSort::Modified => TreeSort::Modified,
+            FfiTreeSort::Custom => TreeSort::Custom,
+        }
+    }
+}
+
+/// Languages with a built-in stemmer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiLanguage {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl From<FfiLanguage> for Language {
+    fn from(language: FfiLanguage) -> Self {
+        match language {
+            FfiLanguage::Arabic => Language::Arabic,
+            FfiLanguage::Danish => Language::Danish,
+            FfiLanguage::Dutch => Language::Dutch,
+            FfiLanguage::English => Language::English,
+            FfiLanguage::Finnish => Language::Finnish,
+            FfiLanguage::French => Language::French,
+            FfiLanguage::German => Language::German,
+            FfiLanguage::Greek => Language::Greek,
+            FfiLanguage::Hungarian => Language::Hungarian,
+            FfiLanguage::Italian => Language::Italian,
+            FfiLanguage::Norwegian => Language::Norwegian,
+            FfiLanguage::Portuguese => Language::Portuguese,
+            FfiLanguage::Romanian => Language::Romanian,
+            FfiLanguage::Russian => Language::Russian,
+            FfiLanguage::Spanish => Language::Spanish,
+            FfiLanguage::Swedish => Language::Swedish,
+            FfiLanguage::Tamil => Language::Tamil,
+            FfiLanguage::Turkish => Language::Turkish,
+        }
+    }
+}
+
+/// FFI-safe relevance weights of the parts of a recipe.
+#[derive(Debug, Clone, Copy, uniffi::Record)]
+pub struct FfiFieldWeights {
+    /// The recipe's file name without extension
+    pub file_stem: f64,
+    /// The `title` metadata field
+    pub title: f64,
+    /// The recipe's tags
+    pub tags: f64,
+    /// All other frontmatter fields
+    pub metadata: f64,
+    /// The recipe body below the frontmatter
+    pub body: f64,
+}
+
+impl From<FfiFieldWeights> for FieldWeights {
+    fn from(weights: FfiFieldWeights) -> Self {
+        FieldWeights {
+            file_stem: weights.file_stem,
+            title: weights.title,
+            tags: weights.tags,
+            metadata: weights.metadata,
+            body: weights.body,
+        }
+    }
+}
+
+impl From<FfiSearchOptions> for SearchOptions {
+    fn from(options: FfiSearchOptions) -> Self {
+        let fuzziness = match (options.fuzzy, options.max_edits) {
+            (false, _) => Fuzziness::Off,
+            (true, None) => Fuzziness::Auto,
+            (true, Some(max)) => Fuzziness::MaxEdits(max as usize),
+        };
+        SearchOptions {
+            fuzziness,
+            weights: options.weights.map(Into::into).unwrap_or_default(),
+            stemmer: options
+                .language
+                .map(|language| Arc::new(Language::from(language)) as Arc<dyn Stemmer>),
+            synonyms: options.synonyms.map(|groups| {
+                let mut synonyms = Synonyms::new();
+                for group in groups {
+                    synonyms.add_group(group);
+                }
+                synonyms
+            }),
+            on_hand: options.on_hand,
+            offset: options.offset as usize,
+            limit: options.limit.map(|limit| limit as usize),
+            walk: options.walk.map(Into::into).unwrap_or_default(),
+        }
+    }
+}
+
+/// The part of a recipe in which a query term was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiMatchField {
+    /// The recipe's file name without extension
+    FileStem,
+    /// The `title` metadata field
+    Title,
+    /// One of the recipe's tags
+    Tag,
+    /// A frontmatter field other than `title` and the tags
+    Metadata,
+    /// The recipe body below the frontmatter
+    Body,
+    /// One of the recipe's Cooklang ingredients
+    Ingredient,
+    /// One of the recipe's Cooklang cookware items
+    Cookware,
+}
+
+impl From<MatchField> for FfiMatchField {
+    fn from(field: MatchField) -> Self {
+        match field {
+            MatchField::FileStem => FfiMatchField::FileStem,
+            MatchField::Title => FfiMatchField::Title,
+            MatchField::Tag => FfiMatchField::Tag,
+            MatchField::Metadata => FfiMatchField::Metadata,
+            MatchField::Body => FfiMatchField::Body,
+            MatchField::Ingredient => FfiMatchField::Ingredient,
+            MatchField::Cookware => FfiMatchField::Cookware,
+        }
+    }
+}
+
+/// A query term found in a specific part of a recipe.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct FfiFieldMatch {
+    /// Where the term was found
+    pub field: FfiMatchField,
+    /// The query term or phrase, lowercase and without diacritics
+    pub term: String,
+    /// True if only a misspelled variant of the term was found
+    pub fuzzy: bool,
+}
+
+/// A range of text to highlight.
+///
+/// Offsets are in UTF-16 code units, matching `NSRange` on iOS and
+/// `String` indices on Android.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiHighlight {
+    /// Start offset (inclusive)
+    pub start: u32,
+    /// End offset (exclusive)
+    pub end: u32,
+}
+
+/// A line of a recipe body containing query terms.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct FfiSnippet {
+    /// One-indexed line number in the recipe file
+    pub line: u32,
+    /// Text of the line, shortened around the first match for long lines
+    pub text: String,
+    /// Ranges within `text` to highlight
+    pub highlights: Vec<FfiHighlight>,
+}
+
+/// A search result with its score and an explanation of why it matched.
+#[derive(uniffi::Record)]
+pub struct FfiSearchHit {
+    /// The matching recipe
+    pub recipe: Arc<FfiRecipeEntry>,
+    /// The base directory the recipe was found in
+    pub root: String,
+    /// Relevance score; higher is better
+    pub score: f64,
+    /// Query terms found per recipe field
+    pub matches: Vec<FfiFieldMatch>,
+    /// Body lines containing query terms
+    pub snippets: Vec<FfiSnippet>,
+}
+
+impl From<SearchHit> for FfiSearchHit {
+    fn from(hit: SearchHit) -> Self {
+        let snippets = hit
+            .snippets
+            .into_iter()
+            .map(|snippet| {
+                let highlights = snippet
+                    .highlights
+                    .iter()
+                    .map(|range| FfiHighlight {
+                        start: utf16_offset(&snippet.text, range.start),
+                        end: utf16_offset(&snippet.text, range.end),
+                    })
+                    .collect();
+                FfiSnippet {
+                    line: snippet.line as u32,
+                    text: snippet.text,
+                    highlights,
+                }
+            })
+            .collect();
+
+        FfiSearchHit {
+            recipe: Arc::new(FfiRecipeEntry::new(hit.recipe)),
+            root: hit.root.into_string(),
+            score: hit.score,
+            matches: hit
+                .matches
+                .into_iter()
+                .map(|m| FfiFieldMatch {
+                    field: m.field.into(),
+                    term: m.term,
+                    fuzzy: m.fuzzy,
+                })
+                .collect(),
+            snippets,
+        }
+    }
+}
+
+/// Converts a UTF-8 byte offset into a UTF-16 code unit offset.
+fn utf16_offset(text: &str, byte_offset: usize) -> u32 {
+    text[..byte_offset].encode_utf16().count() as u32
+}
+
+/// A file that could not be read and was left out of a result.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct FfiSkippedFile {
+    /// Path of the skipped file or directory
+    pub path: String,
+    /// Why the file was skipped
+    pub error: String,
+}
+
+impl From<SkippedFile> for FfiSkippedFile {
+    fn from(skipped: SkippedFile) -> Self {
+        FfiSkippedFile {
+            path: skipped.path.into_string(),
+            error: skipped.error.to_string(),
+        }
+    }
+}
+
+/// Search results together with the files that were skipped.
+#[derive(uniffi::Record)]
+pub struct FfiSearchReport {
+    /// Matching recipes sorted by relevance
+    pub recipes: Vec<Arc<FfiRecipeEntry>>,
+    /// Recipe files that could not be read, sorted by path
+    pub skipped: Vec<FfiSkippedFile>,
+}
+
+impl From<Partial<Vec<RecipeEntry>>> for FfiSearchReport {
+    fn from(report: Partial<Vec<RecipeEntry>>) -> Self {
+        FfiSearchReport {
+            recipes: report
+                .value
+                .into_iter()
+                .map(|r| Arc::new(FfiRecipeEntry::new(r)))
+                .collect(),
+            skipped: report.skipped.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A recipe tree together with the files that were skipped.
+#[derive(uniffi::Record)]
+pub struct FfiTreeReport {
+    /// The tree built from all readable recipes
+    pub tree: Arc<FfiRecipeTree>,
+    /// Recipe files that could not be read, sorted by path
+    pub skipped: Vec<FfiSkippedFile>,
+}
+
 /// FFI-safe representation of a tree node.
 #[derive(Debug, Clone, uniffi::Record)]
 pub struct FfiTreeNode {
@@ -285,60 +789,582 @@ pub struct FfiTreeNode {
     pub path: String,
     /// True if this node has a recipe
     pub has_recipe: bool,
-    /// Names of child nodes
+    /// Keys of child nodes, in sorted order
     pub children: Vec<String>,
+    /// True if this node was reached through a symbolic link
+    pub linked: bool,
+    /// Settings of a directory from its `_dir.yaml` file, if it has one
+    pub config: Option<FfiDirConfig>,
+    /// Files in a directory other than recipes, if the tree was built with
+    /// `assets` set
+    pub assets: Vec<FfiAsset>,
+    /// True if this is a directory below the `max_depth` of the tree whose
+    /// content has not been listed yet
+    pub unexpanded: bool,
+}
+
+/// FFI-safe representation of a file in a recipe directory that is not a
+/// recipe.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiAsset {
+    /// File name of the asset
+    pub name: String,
+    /// Full path to the file
+    pub path: String,
+    /// What kind of file it is
+    pub kind: FfiAssetKind,
+}
+
+impl From<&Asset> for FfiAsset {
+    fn from(asset: &Asset) -> Self {
+        FfiAsset {
+            name: asset.file_name().to_string(),
+            path: asset.path.to_string(),
+            kind: asset.kind.into(),
+        }
+    }
+}
+
+/// The kind of a file listed as an asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiAssetKind {
+    /// A photo or picture
+    Image,
+    /// A document such as a PDF
+    Document,
+    /// A plain text or markup note
+    Note,
+    /// Any other file
+    Unknown,
+}
+
+impl From<AssetKind> for FfiAssetKind {
+    fn from(kind: AssetKind) -> Self {
+        match kind {
+            AssetKind::Image => FfiAssetKind::Image,
+            AssetKind::Document => FfiAssetKind::Document,
+            AssetKind::Note => FfiAssetKind::Note,
+            AssetKind::Unknown => FfiAssetKind::Unknown,
+        }
+    }
+}
+
+/// FFI-safe representation of the settings of a recipe directory.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct FfiDirConfig {
+    /// Displayed name of the directory
+    pub title: Option<String>,
+    /// Icon of the directory, e.g. an emoji
+    pub icon: Option<String>,
+    /// Description of the directory
+    pub description: Option<String>,
+    /// Path or URL of the cover image
+    pub cover_image: Option<String>,
+    /// Tags and metadata fields the recipes in the directory inherit
+    pub metadata: FfiMetadata,
+}
+
+/// FFI-safe representation of siblings in a recipe tree that share a name.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiNameCollision {
+    /// Path of the directory holding the clashing nodes
+    pub parent: String,
+    /// The shared name
+    pub name: String,
+    /// Keys of the clashing nodes among their parent's children
+    pub keys: Vec<String>,
+    /// Paths of the clashing nodes, in the order of `keys`
+    pub paths: Vec<String>,
+}
+
+impl From<NameCollision> for FfiNameCollision {
+    fn from(collision: NameCollision) -> Self {
+        FfiNameCollision {
+            parent: collision.parent.into_string(),
+            name: collision.name,
+            keys: collision.keys,
+            paths: collision
+                .paths
+                .into_iter()
+                .map(Utf8PathBuf::into_string)
+                .collect(),
+        }
+    }
+}
+
+/// FFI-safe criteria for filtering the recipes of a tree; a recipe is kept
+/// if it meets all of them.
+#[derive(Debug, Clone, Default, uniffi::Record)]
+pub struct FfiRecipeFilter {
+    /// Tags the recipe must all have, ignoring case and diacritics
+    #[uniffi(default = [])]
+    pub tags: Vec<String>,
+    /// Metadata keys the recipe must all have
+    #[uniffi(default = [])]
+    pub metadata_keys: Vec<String>,
+    /// Keep only menus if true, or only recipes if false; `None` keeps both
+    #[uniffi(default = None)]
+    pub is_menu: Option<bool>,
+    /// Keep only recipes with a title image if true, or only those without
+    /// one if false; `None` keeps both
+    #[uniffi(default = None)]
+    pub has_title_image: Option<bool>,
+}
+
+impl From<FfiRecipeFilter> for RecipeFilter {
+    fn from(filter: FfiRecipeFilter) -> Self {
+        RecipeFilter {
+            tags: filter.tags,
+            metadata_keys: filter.metadata_keys,
+            is_menu: filter.is_menu,
+            has_title_image: filter.has_title_image,
+        }
+    }
 }
 
 /// FFI-safe representation of a recipe tree.
 #[derive(uniffi::Object)]
 pub struct FfiRecipeTree {
-    inner: RecipeTree,
+    inner: Arc<Mutex<RecipeTree>>,
+}
+
+impl FfiRecipeTree {
+    fn new(tree: RecipeTree) -> Self {
+        FfiRecipeTree {
+            inner: Arc::new(Mutex::new(tree)),
+        }
+    }
 }
 
 #[uniffi::export]
 impl FfiRecipeTree {
     /// Returns the root node information.
     pub fn root(&self) -> FfiTreeNode {
-        tree_to_node(&self.inner)
+        tree_to_node(&self.inner.lock().unwrap())
     }
 
     /// Returns all nodes in the tree as a flat list.
     pub fn all_nodes(&self) -> Vec<FfiTreeNode> {
         let mut nodes = Vec::new();
-        collect_nodes(&self.inner, &mut nodes);
+        collect_nodes(&self.inner.lock().unwrap(), &mut nodes);
         nodes
     }
 
     /// Returns all recipes in the tree.
     pub fn all_recipes(&self) -> Vec<Arc<FfiRecipeEntry>> {
         let mut recipes = Vec::new();
-        collect_recipes(&self.inner, &mut recipes);
+        collect_recipes(&self.inner.lock().unwrap(), &mut recipes);
         recipes
     }
 
+    /// Returns the groups of siblings anywhere in the tree that share a name.
+    pub fn collisions(&self) -> Vec<FfiNameCollision> {
+        self.inner
+            .lock()
+            .unwrap()
+            .collisions()
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Serializes the tree to JSON, e.g. to keep it between app launches
+    /// and bring it up to date with `refresh_tree`.
+    pub fn snapshot(&self) -> Result<String, CooklangError> {
+        serde_json::to_string(&*self.inner.lock().unwrap()).map_err(|e| CooklangError::ParseError {
+            reason: e.to_string(),
+        })
+    }
+
+    /// Returns a copy of the tree with only the recipes that meet the
+    /// criteria of `filter`, leaving out directories without any.
+    pub fn filter(&self, filter: FfiRecipeFilter) -> Arc<FfiRecipeTree> {
+        let tree = self.inner.lock().unwrap().filter_recipes(&filter.into());
+        Arc::new(FfiRecipeTree::new(tree))
+    }
+
+    /// Returns a copy of the tree with only the recipes matching a search
+    /// query, leaving out directories without any.
+    ///
+    /// `offset` and `limit` of the options are ignored.
+    pub fn filter_query(
+        &self,
+        query: String,
+        options: FfiSearchOptions,
+    ) -> Result<Arc<FfiRecipeTree>, CooklangError> {
+        let tree = self
+            .inner
+            .lock()
+            .unwrap()
+            .filter_query_with_options(&query, &options.into())?;
+        Ok(Arc::new(FfiRecipeTree::new(tree)))
+    }
+
+    /// Regroups the recipes into a virtual tree keyed by metadata fields,
+    /// e.g. `["course", "cuisine"]`, instead of directories.
+    ///
+    /// Recipes without a field are listed next to the groups of that field.
+    pub fn group_by(&self, fields: Vec<String>) -> Arc<FfiRecipeTree> {
+        let tree = self.inner.lock().unwrap().group_by(&fields);
+        Arc::new(FfiRecipeTree::new(tree))
+    }
+
+    /// Returns a handle to the root node, to navigate the tree node by node.
+    pub fn root_node(&self) -> Arc<FfiTreeNodeHandle> {
+        let tree = self.inner.lock().unwrap();
+        FfiTreeNodeHandle::new(&self.inner, Vec::new(), &tree)
+    }
+
+    /// Returns a handle to the node at the given path components (e.g.,
+    /// ["breakfast", "pancakes"]), directory or recipe, matched like
+    /// `get_child`.
+    pub fn node_at_path(&self, path: Vec<String>) -> Option<Arc<FfiTreeNodeHandle>> {
+        self.root_node().find(path)
+    }
+
     /// Gets a child node by name from the root.
     pub fn get_child(&self, name: String) -> Option<FfiTreeNode> {
-        self.inner.children.get(&name).map(tree_to_node)
+        self.inner.lock().unwrap().child(&name).map(tree_to_node)
     }
 
     /// Gets the recipe at the root level if present.
     pub fn recipe(&self) -> Option<Arc<FfiRecipeEntry>> {
         self.inner
+            .lock()
+            .unwrap()
             .recipe
             .as_ref()
-            .map(|r| Arc::new(FfiRecipeEntry::new(r.clone())))
+            .map(|r| Arc::new(FfiRecipeEntry::new(r.clone_cached())))
     }
 
     /// Gets a recipe by path components (e.g., ["breakfast", "pancakes"]).
     pub fn get_recipe_at_path(&self, path: Vec<String>) -> Option<Arc<FfiRecipeEntry>> {
-        let mut current = &self.inner;
+        let tree = self.inner.lock().unwrap();
+        let mut current = &*tree;
         for component in &path {
-            current = current.children.get(component)?;
+            current = current.child(component)?;
         }
         current
             .recipe
             .as_ref()
-            .map(|r| Arc::new(FfiRecipeEntry::new(r.clone())))
+            .map(|r| Arc::new(FfiRecipeEntry::new(r.clone_cached())))
+    }
+
+    /// Lists the content of the directory at the given path components
+    /// (e.g., ["breakfast"], or [] for the root), such as one left
+    /// unexpanded by `max_depth`, and returns its updated node.
+    ///
+    /// The directory is listed as deep as `options.max_depth` allows and
+    /// replaces its previous children.
+    pub fn expand(
+        &self,
+        path: Vec<String>,
+        options: FfiTreeOptions,
+    ) -> Result<FfiTreeNode, CooklangError> {
+        let mut tree = self.inner.lock().unwrap();
+        let dir = node_at(&tree, &path)?.path.clone();
+        tree.expand(&dir, &options.into())?;
+        Ok(tree_to_node(node_at(&tree, &path)?))
+    }
+}
+
+/// A handle to a node of a recipe tree, directory or recipe, for
+/// navigating the tree node by node.
+///
+/// Handles refer to their node by the keys leading to it from the root and
+/// look it up whenever they are called, so they see directories expanded
+/// in the meantime. A handle whose node is no longer in the tree, e.g.
+/// after its directory was expanded again, has no children and no recipe.
+#[derive(uniffi::Object)]
+pub struct FfiTreeNodeHandle {
+    tree: Arc<Mutex<RecipeTree>>,
+    keys: Vec<String>,
+    name: String,
+    path: String,
+    is_dir: bool,
+}
+
+impl FfiTreeNodeHandle {
+    fn new(tree: &Arc<Mutex<RecipeTree>>, keys: Vec<String>, node: &RecipeTree) -> Arc<Self> {
+        Arc::new(FfiTreeNodeHandle {
+            tree: tree.clone(),
+            keys,
+            name: node.name.clone(),
+            path: node.path.to_string(),
+            is_dir: node.recipe.is_none(),
+        })
+    }
+
+    /// Calls `f` with the node of this handle, if it is still in the tree.
+    fn with_node<T>(&self, f: impl FnOnce(&RecipeTree) -> T) -> Option<T> {
+        let tree = self.tree.lock().unwrap();
+        let mut current = &*tree;
+        for key in &self.keys {
+            current = current.children.get(key)?;
+        }
+        Some(f(current))
+    }
+
+    /// Returns a handle to a child of this node by its key.
+    fn child_handle(&self, key: &str, node: &RecipeTree) -> Arc<FfiTreeNodeHandle> {
+        let mut keys = self.keys.clone();
+        keys.push(key.to_string());
+        FfiTreeNodeHandle::new(&self.tree, keys, node)
+    }
+}
+
+#[uniffi::export]
+impl FfiTreeNodeHandle {
+    /// Returns the displayed name of the node.
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Returns the full path of the node.
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    /// Returns true if the node is a directory rather than a recipe.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns the keys leading from the root to this node, empty for the
+    /// root.
+    pub fn keys(&self) -> Vec<String> {
+        self.keys.clone()
+    }
+
+    /// Returns the details of the node, or `None` if it is no longer in
+    /// the tree.
+    pub fn node(&self) -> Option<FfiTreeNode> {
+        self.with_node(tree_to_node)
+    }
+
+    /// Returns the recipe of the node if it is a recipe.
+    pub fn recipe(&self) -> Option<Arc<FfiRecipeEntry>> {
+        self.with_node(|node| {
+            node.recipe
+                .as_ref()
+                .map(|r| Arc::new(FfiRecipeEntry::new(r.clone_cached())))
+        })
+        .flatten()
+    }
+
+    /// Returns a handle to the parent of the node, or `None` for the root.
+    pub fn parent(&self) -> Option<Arc<FfiTreeNodeHandle>> {
+        let (_, parent_keys) = self.keys.split_last()?;
+        let tree = self.tree.lock().unwrap();
+        let mut current = &*tree;
+        for key in parent_keys {
+            current = current.children.get(key)?;
+        }
+        Some(FfiTreeNodeHandle::new(
+            &self.tree,
+            parent_keys.to_vec(),
+            current,
+        ))
+    }
+
+    /// Returns the children of the node in order, with what is needed to
+    /// list them.
+    ///
+    /// The titles, title images and tags of recipes in lazy trees are read
+    /// from their files if they weren't already.
+    pub fn children(&self) -> Vec<FfiTreeChild> {
+        self.with_node(|node| {
+            node.children
+                .iter()
+                .map(|(key, child)| tree_to_child(key, child))
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Returns handles to the children of the node, in order.
+    pub fn child_nodes(&self) -> Vec<Arc<FfiTreeNodeHandle>> {
+        self.with_node(|node| {
+            node.children
+                .iter()
+                .map(|(key, child)| self.child_handle(key, child))
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Returns a handle to the child with the given name, matched like
+    /// `FfiRecipeTree::get_child`: by key first, then ignoring case and
+    /// diacritics, then by displayed name.
+    pub fn child(&self, name: String) -> Option<Arc<FfiTreeNodeHandle>> {
+        self.with_node(|node| {
+            node.child_entry(&name)
+                .map(|(key, child)| self.child_handle(key, child))
+        })
+        .flatten()
+    }
+
+    /// Returns a handle to the node at the given path components below
+    /// this one, each matched like `child`; an empty path returns this
+    /// node.
+    pub fn find(&self, path: Vec<String>) -> Option<Arc<FfiTreeNodeHandle>> {
+        let tree = self.tree.lock().unwrap();
+        let mut current = &*tree;
+        for key in &self.keys {
+            current = current.children.get(key)?;
+        }
+        let mut keys = self.keys.clone();
+        for component in &path {
+            let (key, child) = current.child_entry(component)?;
+            keys.push(key.clone());
+            current = child;
+        }
+        Some(FfiTreeNodeHandle::new(&self.tree, keys, current))
+    }
+}
+
+/// FFI-safe summary of a child node, for listing the children of a
+/// directory without navigating to each of them.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiTreeChild {
+    /// Key of the child among its parent's children
+    pub key: String,
+    /// Displayed name of the child
+    pub name: String,
+    /// Full path to the child
+    pub path: String,
+    /// True if the child is a directory rather than a recipe
+    pub is_dir: bool,
+    /// Title of a recipe from its metadata, or of a directory from its
+    /// `_dir.yaml` file
+    pub title: Option<String>,
+    /// Title image of a recipe, or cover image of a directory
+    pub title_image: Option<String>,
+    /// Tags of a recipe, or those a directory adds to its recipes
+    pub tags: Vec<String>,
+    /// True if the child is a directory whose content has not been listed
+    /// yet
+    pub unexpanded: bool,
+}
+
+/// FFI-safe representation of a change to the recipes or images of a
+/// directory.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiWatchEvent {
+    /// A recipe file was created
+    RecipeAdded { path: String },
+    /// A recipe file was deleted
+    RecipeRemoved { path: String },
+    /// A recipe file was moved or renamed
+    RecipeRenamed { from: String, to: String },
+    /// The content of a recipe file changed
+    RecipeModified { path: String },
+    /// An image file was created
+    ImageAdded { path: String },
+    /// An image file was deleted
+    ImageRemoved { path: String },
+}
+
+impl From<WatchEvent> for FfiWatchEvent {
+    fn from(event: WatchEvent) -> Self {
+        match event {
+            WatchEvent::RecipeAdded(path) => FfiWatchEvent::RecipeAdded {
+                path: path.into_string(),
+            },
+            WatchEvent::RecipeRemoved(path) => FfiWatchEvent::RecipeRemoved {
+                path: path.into_string(),
+            },
+            WatchEvent::RecipeRenamed { from, to } => FfiWatchEvent::RecipeRenamed {
+                from: from.into_string(),
+                to: to.into_string(),
+            },
+            WatchEvent::RecipeModified(path) => FfiWatchEvent::RecipeModified {
+                path: path.into_string(),
+            },
+            WatchEvent::ImageAdded(path) => FfiWatchEvent::ImageAdded {
+                path: path.into_string(),
+            },
+            WatchEvent::ImageRemoved(path) => FfiWatchEvent::ImageRemoved {
+                path: path.into_string(),
+            },
+        }
+    }
+}
+
+/// A refreshed recipe tree, the changes since the snapshot it was
+/// refreshed from, and the files that were skipped.
+#[derive(uniffi::Record)]
+pub struct FfiTreeRefresh {
+    /// The tree as it is now
+    pub tree: Arc<FfiRecipeTree>,
+    /// Recipes added, removed, renamed and modified since the snapshot
+    pub changes: Vec<FfiWatchEvent>,
+    /// Recipe files that could not be read, sorted by path
+    pub skipped: Vec<FfiSkippedFile>,
+}
+
+/// FFI-safe representation of a recipe moved or renamed between two trees.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiMovedRecipe {
+    /// Path in the old tree, relative to its root
+    pub from: String,
+    /// Path in the new tree, relative to its root
+    pub to: String,
+}
+
+/// FFI-safe representation of a recipe whose content changed between two
+/// trees.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiModifiedRecipe {
+    /// Path relative to the roots of the trees
+    pub path: String,
+    /// Metadata keys that were added, removed or changed
+    pub metadata_keys: Vec<String>,
+}
+
+/// FFI-safe representation of the differences between two recipe trees.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiTreeDiff {
+    /// Recipes only in the new tree
+    pub added: Vec<String>,
+    /// Recipes only in the old tree
+    pub removed: Vec<String>,
+    /// Recipes found at another path in the new tree
+    pub moved: Vec<FfiMovedRecipe>,
+    /// Recipes whose content changed
+    pub modified: Vec<FfiModifiedRecipe>,
+}
+
+impl From<TreeDiff> for FfiTreeDiff {
+    fn from(diff: TreeDiff) -> Self {
+        FfiTreeDiff {
+            added: diff
+                .added
+                .into_iter()
+                .map(Utf8PathBuf::into_string)
+                .collect(),
+            removed: diff
+                .removed
+                .into_iter()
+                .map(Utf8PathBuf::into_string)
+                .collect(),
+            moved: diff
+                .moved
+                .into_iter()
+                .map(|moved| FfiMovedRecipe {
+                    from: moved.from.into_string(),
+                    to: moved.to.into_string(),
+                })
+                .collect(),
+            modified: diff
+                .modified
+                .into_iter()
+                .map(|modified| FfiModifiedRecipe {
+                    path: modified.path.into_string(),
+                    metadata_keys: modified.metadata_keys,
+                })
+                .collect(),
+        }
     }
 }
 
@@ -348,7 +1374,56 @@ fn tree_to_node(tree: &RecipeTree) -> FfiTreeNode {
         path: tree.path.to_string(),
         has_recipe: tree.recipe.is_some(),
         children: tree.children.keys().cloned().collect(),
+        linked: tree.linked,
+        config: tree.config.as_ref().map(|config| FfiDirConfig {
+            title: config.title.clone(),
+            icon: config.icon.clone(),
+            description: config.description.clone(),
+            cover_image: tree.cover_image(),
+            metadata: (&Metadata::from_fields(config.metadata.clone(), &config.tags)).into(),
+        }),
+        assets: tree.assets.iter().map(Into::into).collect(),
+        unexpanded: tree.unexpanded,
+    }
+}
+
+fn tree_to_child(key: &str, node: &RecipeTree) -> FfiTreeChild {
+    let config = node.config.as_ref();
+    let (title, title_image, tags) = match &node.recipe {
+        Some(recipe) => (
+            recipe.metadata().title().map(str::to_string),
+            recipe.title_image().clone(),
+            recipe.tags(),
+        ),
+        None => (
+            config.and_then(|config| config.title.clone()),
+            node.cover_image(),
+            config.map(|config| config.tags.clone()).unwrap_or_default(),
+        ),
+    };
+    FfiTreeChild {
+        key: key.to_string(),
+        name: node.name.clone(),
+        path: node.path.to_string(),
+        is_dir: node.recipe.is_none(),
+        title,
+        title_image,
+        tags,
+        unexpanded: node.unexpanded,
+    }
+}
+
+/// Returns the node at the given path components below a tree.
+fn node_at<'a>(tree: &'a RecipeTree, path: &[String]) -> Result<&'a RecipeTree, CooklangError> {
+    let mut current = tree;
+    for component in path {
+        current = current
+            .child(component)
+            .ok_or_else(|| CooklangError::NotFound {
+                reason: path.join("/"),
+            })?;
     }
+    Ok(current)
 }
 
 fn collect_nodes(tree: &RecipeTree, nodes: &mut Vec<FfiTreeNode>) {
@@ -360,7 +1435,7 @@ fn collect_nodes(tree: &RecipeTree, nodes: &mut Vec<FfiTreeNode>) {
 
 fn collect_recipes(tree: &RecipeTree, recipes: &mut Vec<Arc<FfiRecipeEntry>>) {
     if let Some(recipe) = &tree.recipe {
-        recipes.push(Arc::new(FfiRecipeEntry::new(recipe.clone())));
+        recipes.push(Arc::new(FfiRecipeEntry::new(recipe.clone_cached())));
     }
     for child in tree.children.values() {
         collect_recipes(child, recipes);
@@ -391,9 +1466,22 @@ pub fn get_recipe(
     Ok(Arc::new(FfiRecipeEntry::new(entry)))
 }
 
-/// Creates a recipe from file content.
-///
-/// Useful for creating recipes from sources other than files,
+/// Loads a recipe by name like `get_recipe`, also leaving out the files
+/// excluded by `options`.
+#[uniffi::export(name = "get_recipe_with_options")]
+pub fn ffi_get_recipe_with_options(
+    base_dirs: Vec<String>,
+    name: String,
+    options: FfiWalkOptions,
+) -> Result<Arc<FfiRecipeEntry>, CooklangError> {
+    let base_dirs: Vec<Utf8PathBuf> = base_dirs.into_iter().map(Utf8PathBuf::from).collect();
+    let entry = get_recipe_with_options(base_dirs, Utf8PathBuf::from(name), &options.into())?;
+    Ok(Arc::new(FfiRecipeEntry::new(entry)))
+}
+
+/// Creates a recipe from file content.
+///
+/// Useful for creating recipes from sources other than files,
 /// such as network responses or programmatically generated content.
 ///
 /// # Arguments
@@ -431,7 +1519,7 @@ pub fn recipe_from_path(path: String) -> Result<Arc<FfiRecipeEntry>, CooklangErr
 ///
 /// # Arguments
 /// * `base_dir` - Root directory to search in
-/// * `query` - Search query (can contain multiple space-separated terms)
+/// * `query` - Search query (terms, `"phrases"`, `key:value` filters, `-negations`, `OR`)
 ///
 /// # Returns
 /// List of matching recipes sorted by relevance.
@@ -444,6 +1532,167 @@ pub fn search(base_dir: String, query: String) -> Result<Vec<Arc<FfiRecipeEntry>
         .collect())
 }
 
+/// Searches for recipes matching a parsed query.
+///
+/// # Arguments
+/// * `base_dir` - Root directory to search in
+/// * `query` - Query parsed with `FfiQuery::parse`
+///
+/// # Returns
+/// List of matching recipes sorted by relevance.
+#[uniffi::export]
+pub fn search_query(
+    base_dir: String,
+    query: Arc<FfiQuery>,
+) -> Result<Vec<Arc<FfiRecipeEntry>>, CooklangError> {
+    let results = search_internal(Utf8Path::new(&base_dir), &query.inner)?;
+    Ok(results
+        .into_iter()
+        .map(|r| Arc::new(FfiRecipeEntry::new(r)))
+        .collect())
+}
+
+/// Searches for recipes with options controlling how terms match.
+///
+/// # Arguments
+/// * `base_dir` - Root directory to search in
+/// * `query` - Search query string
+/// * `options` - Search options, e.g. typo tolerance
+///
+/// # Returns
+/// List of matching recipes sorted by relevance.
+#[uniffi::export(name = "search_with_options")]
+pub fn ffi_search_with_options(
+    base_dir: String,
+    query: String,
+    options: FfiSearchOptions,
+) -> Result<Vec<Arc<FfiRecipeEntry>>, CooklangError> {
+    let results = search_with_options(Utf8Path::new(&base_dir), &query, &options.into())?;
+    Ok(results
+        .into_iter()
+        .map(|r| Arc::new(FfiRecipeEntry::new(r)))
+        .collect())
+}
+
+/// Searches for recipes and reports the files that could not be read.
+///
+/// Unreadable files never fail the search; they are left out of the
+/// results and listed in the report instead.
+///
+/// # Arguments
+/// * `base_dir` - Root directory to search in
+/// * `query` - Search query string
+/// * `options` - Search options, e.g. typo tolerance
+///
+/// # Returns
+/// The matching recipes sorted by relevance, and the skipped files.
+#[uniffi::export(name = "search_with_diagnostics")]
+pub fn ffi_search_with_diagnostics(
+    base_dir: String,
+    query: String,
+    options: FfiSearchOptions,
+) -> Result<FfiSearchReport, CooklangError> {
+    let report = search_with_diagnostics(Utf8Path::new(&base_dir), &query, &options.into())?;
+    Ok(report.into())
+}
+
+/// Ranked search results that are loaded page by page.
+///
+/// Created by `search_cursor`. Recipes are only loaded when a page
+/// containing them is requested.
+#[derive(uniffi::Object)]
+pub struct FfiSearchCursor {
+    inner: Mutex<SearchResults>,
+}
+
+#[uniffi::export]
+impl FfiSearchCursor {
+    /// Returns the number of matching recipes, ignoring offset and limit.
+    pub fn total(&self) -> u32 {
+        self.inner.lock().unwrap().total() as u32
+    }
+
+    /// Returns the number of results not loaded yet.
+    pub fn remaining(&self) -> u32 {
+        self.inner.lock().unwrap().len() as u32
+    }
+
+    /// Loads the next `count` results; returns an empty list once all
+    /// results have been loaded.
+    pub fn next_page(&self, count: u32) -> Result<Vec<Arc<FfiRecipeEntry>>, CooklangError> {
+        let mut results = self.inner.lock().unwrap();
+        results
+            .by_ref()
+            .take(count as usize)
+            .map(|recipe| Ok(Arc::new(FfiRecipeEntry::new(recipe?))))
+            .collect()
+    }
+}
+
+/// Searches for recipes, returning a cursor that loads results lazily.
+///
+/// # Arguments
+/// * `base_dir` - Root directory to search in
+/// * `query` - Search query string
+/// * `options` - Search options, e.g. typo tolerance or offset
+///
+/// # Returns
+/// A cursor over the results sorted by relevance.
+#[uniffi::export]
+pub fn search_cursor(
+    base_dir: String,
+    query: String,
+    options: FfiSearchOptions,
+) -> Result<Arc<FfiSearchCursor>, CooklangError> {
+    let results = search_iter(Utf8Path::new(&base_dir), &query, &options.into())?;
+    Ok(Arc::new(FfiSearchCursor {
+        inner: Mutex::new(results),
+    }))
+}
+
+/// Searches for recipes and explains why each one matched.
+///
+/// # Arguments
+/// * `base_dir` - Root directory to search in
+/// * `query` - Search query string
+/// * `options` - Search options, e.g. typo tolerance
+///
+/// # Returns
+/// List of hits sorted by relevance, each with its score, matched
+/// fields and highlighted body snippets.
+#[uniffi::export]
+pub fn search_detailed(
+    base_dir: String,
+    query: String,
+    options: FfiSearchOptions,
+) -> Result<Vec<FfiSearchHit>, CooklangError> {
+    let hits = search_detailed_with_options(Utf8Path::new(&base_dir), &query, &options.into())?;
+    Ok(hits.into_iter().map(FfiSearchHit::from).collect())
+}
+
+/// Searches several base directories and merges their results.
+///
+/// Directories are given in order of precedence: a recipe file found at
+/// the same relative path in more than one directory is only taken from
+/// the first one, as with `get_recipe`.
+///
+/// # Arguments
+/// * `base_dirs` - Root directories to search in, in order of precedence
+/// * `query` - Search query string
+/// * `options` - Search options, e.g. typo tolerance
+///
+/// # Returns
+/// List of hits sorted by relevance, each reporting the directory it came from.
+#[uniffi::export(name = "search_roots")]
+pub fn ffi_search_roots(
+    base_dirs: Vec<String>,
+    query: String,
+    options: FfiSearchOptions,
+) -> Result<Vec<FfiSearchHit>, CooklangError> {
+    let hits = search_roots_with_options(&base_dirs, &query, &options.into())?;
+    Ok(hits.into_iter().map(FfiSearchHit::from).collect())
+}
+
 /// Builds a hierarchical tree of all recipes in a directory.
 ///
 /// Recursively scans the directory for .cook and .menu files,
@@ -457,7 +1706,89 @@ pub fn search(base_dir: String, query: String) -> Result<Vec<Arc<FfiRecipeEntry>
 #[uniffi::export]
 pub fn build_tree(base_dir: String) -> Result<Arc<FfiRecipeTree>, CooklangError> {
     let tree = build_tree_internal(&base_dir)?;
-    Ok(Arc::new(FfiRecipeTree { inner: tree }))
+    Ok(Arc::new(FfiRecipeTree::new(tree)))
+}
+
+/// Builds a recipe tree and reports the files that could not be read.
+///
+/// # Arguments
+/// * `base_dir` - Root directory to build the tree from
+/// * `options` - Which files to leave out of the tree
+///
+/// # Returns
+/// The tree of all readable recipes, and the skipped files.
+#[uniffi::export(name = "build_tree_with_diagnostics")]
+pub fn ffi_build_tree_with_diagnostics(
+    base_dir: String,
+    options: FfiTreeOptions,
+) -> Result<FfiTreeReport, CooklangError> {
+    let report = build_tree_with_diagnostics(&base_dir, &options.into())?;
+    Ok(FfiTreeReport {
+        tree: Arc::new(FfiRecipeTree::new(report.value)),
+        skipped: report.skipped.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// Restores a recipe tree serialized with `FfiRecipeTree::snapshot`.
+///
+/// The tree is returned as it was when the snapshot was taken; call
+/// `refresh_tree` to bring it up to date.
+#[uniffi::export]
+pub fn tree_from_snapshot(snapshot: String) -> Result<Arc<FfiRecipeTree>, CooklangError> {
+    let tree = serde_json::from_str(&snapshot).map_err(|e| CooklangError::ParseError {
+        reason: e.to_string(),
+    })?;
+    Ok(Arc::new(FfiRecipeTree::new(tree)))
+}
+
+/// Brings a recipe tree up to date with its directory, only reading the
+/// recipe files that changed since the tree was built.
+///
+/// # Arguments
+/// * `tree` - A tree built earlier or restored with `tree_from_snapshot`
+/// * `options` - Which files to leave out of the tree and how to sort it
+///
+/// # Returns
+/// The updated tree, the changes since `tree`, and the skipped files.
+#[uniffi::export(name = "refresh_tree")]
+pub fn ffi_refresh_tree(
+    tree: Arc<FfiRecipeTree>,
+    options: FfiTreeOptions,
+) -> Result<FfiTreeRefresh, CooklangError> {
+    let report = refresh_tree(&tree.inner.lock().unwrap(), &options.into())?;
+    Ok(FfiTreeRefresh {
+        tree: Arc::new(FfiRecipeTree::new(report.value.tree)),
+        changes: report.value.changes.into_iter().map(Into::into).collect(),
+        skipped: report.skipped.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// Compares the recipes of two trees, e.g. a snapshot and a freshly built
+/// tree, or the same collection on two devices.
+///
+/// # Arguments
+/// * `old` - The earlier tree
+/// * `new` - The later tree
+///
+/// # Returns
+/// The recipes added, removed, moved and modified, with paths relative to
+/// the roots of the trees.
+#[uniffi::export(name = "diff_trees")]
+pub fn ffi_diff_trees(old: Arc<FfiRecipeTree>, new: Arc<FfiRecipeTree>) -> FfiTreeDiff {
+    if Arc::ptr_eq(&old.inner, &new.inner) {
+        let tree = old.inner.lock().unwrap();
+        return diff_trees(&tree, &tree).into();
+    }
+    // Lock the trees in the order of their addresses, so that a diff in the
+    // other direction cannot hold one lock while waiting for the other
+    let (old_guard, new_guard) = if Arc::as_ptr(&old.inner) < Arc::as_ptr(&new.inner) {
+        let old_guard = old.inner.lock().unwrap();
+        (old_guard, new.inner.lock().unwrap())
+    } else {
+        let new_guard = new.inner.lock().unwrap();
+        (old.inner.lock().unwrap(), new_guard)
+    };
+    diff_trees(&old_guard, &new_guard).into()
 }
 
 /// Returns the library version.
@@ -528,6 +1859,579 @@ mod tests {
         assert_eq!(results[0].name(), Some("Fluffy Pancakes".to_string()));
     }
 
+    #[test]
+    fn test_search_with_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+
+        create_test_recipe(
+            temp_path,
+            "pancakes",
+            indoc! {r#"
+            ---
+            tags: [breakfast]
+            ---
+
+            Mix and cook"#},
+        );
+        create_test_recipe(temp_path, "soup", "Simmer and cook");
+
+        let query = FfiQuery::parse("tag:breakfast cook".to_string()).unwrap();
+        assert_eq!(query.terms(), vec!["cook"]);
+        assert!(query.has_filters());
+
+        let results = search_query(temp_path.to_string(), query).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name(), Some("pancakes".to_string()));
+
+        let error = search(temp_path.to_string(), "(cook".to_string()).err();
+        assert!(matches!(error, Some(CooklangError::InvalidQuery { .. })));
+    }
+
+    #[test]
+    fn test_search_detailed_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+
+        create_test_recipe(temp_path, "crepes", "Fill the crêpes with @jam{}");
+
+        let hits = search_detailed(
+            temp_path.to_string(),
+            "jam".to_string(),
+            FfiSearchOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].score > 0.0);
+        assert_eq!(hits[0].matches[0].field, FfiMatchField::Body);
+
+        // "ê" is two UTF-8 bytes but a single UTF-16 code unit
+        let snippet = &hits[0].snippets[0];
+        assert_eq!(snippet.line, 1);
+        assert_eq!(
+            snippet.highlights,
+            vec![FfiHighlight { start: 22, end: 25 }]
+        );
+
+        let options = FfiSearchOptions {
+            fuzzy: true,
+            max_edits: None,
+            ..Default::default()
+        };
+        let results =
+            ffi_search_with_options(temp_path.to_string(), "crepe".to_string(), options).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_search_roots_ffi() {
+        let personal_dir = TempDir::new().unwrap();
+        let shared_dir = TempDir::new().unwrap();
+        let personal = personal_dir.path().to_str().unwrap();
+        let shared = shared_dir.path().to_str().unwrap();
+
+        create_test_recipe(personal, "pancakes", "Mix @flour{} and @milk{}");
+        create_test_recipe(shared, "pancakes", "Mix @flour{} and @water{}");
+        create_test_recipe(shared, "crepes", "Thin batter of @flour{}");
+
+        let hits = ffi_search_roots(
+            vec![personal.to_string(), shared.to_string()],
+            "flour".to_string(),
+            FfiSearchOptions::default(),
+        )
+        .unwrap();
+        let mut found: Vec<_> = hits
+            .iter()
+            .map(|hit| (hit.recipe.name().unwrap(), hit.root.clone()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                ("crepes".to_string(), shared.to_string()),
+                ("pancakes".to_string(), personal.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_cursor() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        for name in ["a", "b", "c"] {
+            create_test_recipe(temp_path, name, "Simmer the soup");
+        }
+
+        let options = FfiSearchOptions {
+            offset: 1,
+            ..Default::default()
+        };
+        let cursor = search_cursor(temp_path.to_string(), "soup".to_string(), options).unwrap();
+        assert_eq!(cursor.total(), 3);
+        assert_eq!(cursor.remaining(), 2);
+
+        let page = cursor.next_page(1).unwrap();
+        assert_eq!(page[0].name(), Some("b".to_string()));
+        assert_eq!(cursor.next_page(5).unwrap().len(), 1);
+        assert!(cursor.next_page(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_language_and_synonyms_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "salsa", "Chop a @tomato{1}");
+        create_test_recipe(temp_path, "moussaka", "Slice the @eggplant{2}");
+
+        let options = FfiSearchOptions {
+            language: Some(FfiLanguage::English),
+            ..Default::default()
+        };
+        let results =
+            ffi_search_with_options(temp_path.to_string(), "tomatoes".to_string(), options)
+                .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let options = FfiSearchOptions {
+            synonyms: Some(vec![vec!["aubergine".to_string(), "eggplant".to_string()]]),
+            ..Default::default()
+        };
+        let results =
+            ffi_search_with_options(temp_path.to_string(), "aubergine".to_string(), options)
+                .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_tree_sort_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "10 soup", "A hearty soup");
+        create_test_recipe(temp_path, "2 soup", "A light soup");
+        fs::create_dir(temp_dir.path().join("stews")).unwrap();
+        create_test_recipe(
+            temp_dir.path().join("stews").to_str().unwrap(),
+            "goulash",
+            "A stew",
+        );
+
+        let options = FfiTreeOptions {
+            sort: Some(FfiTreeSort::Natural),
+            dirs_first: true,
+            ..Default::default()
+        };
+        let report = ffi_build_tree_with_diagnostics(temp_path.to_string(), options).unwrap();
+        assert_eq!(
+            report.tree.root().children,
+            vec!["stews", "2 soup", "10 soup"]
+        );
+    }
+
+    #[test]
+    fn test_tree_collisions_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "A hearty soup");
+        fs::write(temp_dir.path().join("soup.menu"), "A soup menu").unwrap();
+
+        let tree = build_tree(temp_path.to_string()).unwrap();
+        assert_eq!(tree.root().children, vec!["soup", "soup.menu"]);
+        let collisions = tree.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].name, "soup");
+        assert_eq!(collisions[0].keys, vec!["soup", "soup.menu"]);
+    }
+
+    #[test]
+    fn test_refresh_tree_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "A hearty soup");
+
+        let snapshot = build_tree(temp_path.to_string())
+            .unwrap()
+            .snapshot()
+            .unwrap();
+        let tart = create_test_recipe(temp_path, "tart", "Bake a tart");
+
+        let tree = tree_from_snapshot(snapshot).unwrap();
+        assert_eq!(tree.root().children, vec!["soup"]);
+        let refresh = ffi_refresh_tree(tree, FfiTreeOptions::default()).unwrap();
+        assert_eq!(refresh.tree.root().children, vec!["soup", "tart"]);
+        assert_eq!(
+            refresh.changes,
+            vec![FfiWatchEvent::RecipeAdded { path: tart }]
+        );
+        assert!(refresh.skipped.is_empty());
+        assert!(tree_from_snapshot("{".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_diff_trees_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "A hearty soup");
+        create_test_recipe(temp_path, "stew", "A rich stew");
+        let old = build_tree(temp_path.to_string()).unwrap();
+
+        fs::rename(
+            temp_dir.path().join("soup.cook"),
+            temp_dir.path().join("leek soup.cook"),
+        )
+        .unwrap();
+        create_test_recipe(temp_path, "stew", "---\ntitle: Stew\n---\nA rich stew");
+        let new = build_tree(temp_path.to_string()).unwrap();
+
+        let diff = ffi_diff_trees(old, new);
+        assert!(diff.added.is_empty());
+        assert_eq!(
+            diff.moved,
+            vec![FfiMovedRecipe {
+                from: "soup.cook".to_string(),
+                to: "leek soup.cook".to_string(),
+            }]
+        );
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].path, "stew.cook");
+        assert_eq!(diff.modified[0].metadata_keys, vec!["title"]);
+    }
+
+    #[test]
+    fn test_diff_tree_with_itself_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "A hearty soup");
+        let tree = build_tree(temp_path.to_string()).unwrap();
+
+        let diff = ffi_diff_trees(tree.clone(), tree.clone());
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.moved.is_empty());
+        assert!(diff.modified.is_empty());
+
+        // Opposite directions at once don't wait on each other
+        let other = build_tree(temp_path.to_string()).unwrap();
+        let handles: Vec<_> = [(tree.clone(), other.clone()), (other, tree)]
+            .into_iter()
+            .map(|(old, new)| {
+                std::thread::spawn(move || {
+                    (0..100).all(|_| ffi_diff_trees(old.clone(), new.clone()).added.is_empty())
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tree_assets_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "pie", "A sweet pie");
+        fs::write(temp_dir.path().join("pie card.pdf"), "%PDF").unwrap();
+
+        let options = FfiTreeOptions {
+            assets: true,
+            ..Default::default()
+        };
+        let report = ffi_build_tree_with_diagnostics(temp_path.to_string(), options).unwrap();
+        let root = report.tree.root();
+        assert_eq!(
+            root.assets,
+            vec![FfiAsset {
+                name: "pie card.pdf".to_string(),
+                path: temp_dir
+                    .path()
+                    .join("pie card.pdf")
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+                kind: FfiAssetKind::Document,
+            }]
+        );
+        assert!(build_tree(temp_path.to_string())
+            .unwrap()
+            .root()
+            .assets
+            .is_empty());
+    }
+
+    #[test]
+    fn test_expand_tree_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        let mains = temp_dir.path().join("mains/pasta");
+        fs::create_dir_all(&mains).unwrap();
+        create_test_recipe(
+            mains.to_str().unwrap(),
+            "carbonara",
+            "---\ntitle: Carbonara\n---\nCook @spaghetti",
+        );
+        create_test_recipe(temp_path, "salad", "A fresh salad");
+
+        let options = FfiTreeOptions {
+            max_depth: Some(0),
+            lazy: true,
+            ..Default::default()
+        };
+        let report =
+            ffi_build_tree_with_diagnostics(temp_path.to_string(), options.clone()).unwrap();
+        let tree = report.tree;
+        let mains_node = tree.get_child("mains".to_string()).unwrap();
+        assert!(mains_node.unexpanded);
+        assert!(mains_node.children.is_empty());
+        let handle = tree.node_at_path(vec!["mains".to_string()]).unwrap();
+        assert!(handle.children().is_empty());
+
+        let mains_node = tree
+            .expand(vec!["mains".to_string()], options.clone())
+            .unwrap();
+        assert!(!mains_node.unexpanded);
+        assert_eq!(mains_node.children, vec!["pasta"]);
+        // Handles see the expanded directory
+        assert!(handle.children()[0].unexpanded);
+        let pasta = tree
+            .expand(vec!["mains".to_string(), "pasta".to_string()], options)
+            .unwrap();
+        assert_eq!(pasta.children, vec!["carbonara"]);
+        let recipe = tree
+            .get_recipe_at_path(vec![
+                "mains".to_string(),
+                "pasta".to_string(),
+                "carbonara".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(recipe.name(), Some("Carbonara".to_string()));
+
+        assert!(matches!(
+            tree.expand(vec!["desserts".to_string()], FfiTreeOptions::default()),
+            Err(CooklangError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tree_node_handles_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        let breakfast = temp_dir.path().join("breakfast");
+        let eggs = breakfast.join("eggs");
+        fs::create_dir_all(&eggs).unwrap();
+        fs::write(
+            breakfast.join("_dir.yaml"),
+            "title: Breakfast\ncover: cover.jpg\ntags: [morning]\n",
+        )
+        .unwrap();
+        create_test_recipe(
+            breakfast.to_str().unwrap(),
+            "pancakes",
+            "---\ntitle: Fluffy Pancakes\ntags: [sweet]\n---\nMix @flour",
+        );
+        fs::write(breakfast.join("pancakes.jpg"), "dummy image content").unwrap();
+        create_test_recipe(eggs.to_str().unwrap(), "omelette", "Whisk @eggs");
+        create_test_recipe(temp_path, "soup", "Boil @water");
+
+        let options = FfiTreeOptions {
+            dirs_first: true,
+            ..Default::default()
+        };
+        let tree = ffi_build_tree_with_diagnostics(temp_path.to_string(), options)
+            .unwrap()
+            .tree;
+        let root = tree.root_node();
+        assert!(root.is_dir());
+        assert!(root.keys().is_empty());
+        assert!(root.parent().is_none());
+        assert!(root.recipe().is_none());
+
+        let children = root.children();
+        assert_eq!(
+            children.iter().map(|c| c.key.as_str()).collect::<Vec<_>>(),
+            vec!["breakfast", "soup"]
+        );
+        assert_eq!(
+            children[0],
+            FfiTreeChild {
+                key: "breakfast".to_string(),
+                name: "Breakfast".to_string(),
+                path: breakfast.to_str().unwrap().to_string(),
+                is_dir: true,
+                title: Some("Breakfast".to_string()),
+                title_image: Some(breakfast.join("cover.jpg").to_str().unwrap().to_string()),
+                tags: vec!["morning".to_string()],
+                unexpanded: false,
+            }
+        );
+        assert!(!children[1].is_dir);
+        assert_eq!(children[1].title, None);
+
+        // Nested nodes are reachable from handles and by path
+        let breakfast_node = root.child("BREAKFAST".to_string()).unwrap();
+        assert_eq!(breakfast_node.keys(), vec!["breakfast"]);
+        let pancakes = &breakfast_node.children()[1];
+        assert_eq!(pancakes.title.as_deref(), Some("Fluffy Pancakes"));
+        assert_eq!(pancakes.tags, vec!["sweet", "morning"]);
+        assert!(pancakes.title_image.is_some());
+
+        let omelette = tree
+            .node_at_path(vec![
+                "breakfast".to_string(),
+                "eggs".to_string(),
+                "omelette".to_string(),
+            ])
+            .unwrap();
+        assert!(!omelette.is_dir());
+        assert_eq!(omelette.name(), "omelette");
+        assert!(omelette.child_nodes().is_empty());
+        assert_eq!(
+            omelette.recipe().unwrap().name(),
+            Some("omelette".to_string())
+        );
+        let eggs_node = omelette.parent().unwrap();
+        assert_eq!(eggs_node.path(), eggs.to_str().unwrap());
+        assert_eq!(eggs_node.parent().unwrap().name(), "Breakfast");
+        assert_eq!(eggs_node.node().unwrap().children, vec!["omelette"]);
+        let handles = breakfast_node.child_nodes();
+        assert_eq!(
+            handles.iter().map(|h| h.name()).collect::<Vec<_>>(),
+            vec!["eggs", "Fluffy Pancakes"]
+        );
+        assert_eq!(
+            breakfast_node
+                .find(vec!["eggs".to_string(), "omelette".to_string()])
+                .unwrap()
+                .path(),
+            omelette.path()
+        );
+        assert!(tree.node_at_path(vec!["lunch".to_string()]).is_none());
+        assert_eq!(tree.node_at_path(Vec::new()).unwrap().path(), root.path());
+    }
+
+    #[test]
+    fn test_dir_config_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        let soups = temp_dir.path().join("soups");
+        fs::create_dir_all(&soups).unwrap();
+        fs::write(
+            soups.join("_dir.yaml"),
+            "title: Soups & Stews\nicon: 🍲\ncover: https://example.com/soup.jpg\ntags: [warm]\n",
+        )
+        .unwrap();
+        create_test_recipe(soups.to_str().unwrap(), "leek", "A hearty soup");
+        create_test_recipe(temp_path, "salad", "A fresh salad");
+
+        let tree = build_tree(temp_path.to_string()).unwrap();
+        let node = tree.get_child("soups".to_string()).unwrap();
+        assert_eq!(node.name, "Soups & Stews");
+        let config = node.config.unwrap();
+        assert_eq!(config.icon.as_deref(), Some("🍲"));
+        assert_eq!(
+            config.cover_image.as_deref(),
+            Some("https://example.com/soup.jpg")
+        );
+        assert_eq!(config.metadata.tags, vec!["warm"]);
+        assert!(tree
+            .get_child("salad".to_string())
+            .unwrap()
+            .config
+            .is_none());
+
+        let leek = tree
+            .get_recipe_at_path(vec!["soups".to_string(), "leek".to_string()])
+            .unwrap();
+        assert_eq!(leek.tags(), vec!["warm"]);
+    }
+
+    #[test]
+    fn test_filter_and_group_tree_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(
+            temp_path,
+            "soup",
+            "---\ncourse: starter\ntags: [vegan]\n---\n@leeks",
+        );
+        create_test_recipe(temp_path, "stew", "---\ncourse: main\n---\n@beef");
+        let tree = build_tree(temp_path.to_string()).unwrap();
+
+        let vegan = tree.filter(FfiRecipeFilter {
+            tags: vec!["vegan".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(vegan.root().children, vec!["soup"]);
+
+        let beef = tree
+            .filter_query("@beef".to_string(), FfiSearchOptions::default())
+            .unwrap();
+        assert_eq!(beef.root().children, vec!["stew"]);
+        assert!(tree
+            .filter_query("(beef".to_string(), FfiSearchOptions::default())
+            .is_err());
+
+        let grouped = tree.group_by(vec!["course".to_string()]);
+        assert_eq!(grouped.root().children, vec!["main", "starter"]);
+        assert!(grouped
+            .get_recipe_at_path(vec!["starter".to_string(), "soup".to_string()])
+            .is_some());
+    }
+
+    #[test]
+    fn test_walk_options_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "A hearty soup");
+        create_test_recipe(temp_path, "old soup", "A stale soup");
+
+        let walk = FfiWalkOptions {
+            exclude: vec!["old *".to_string()],
+            ..Default::default()
+        };
+        let options = FfiSearchOptions {
+            walk: Some(walk.clone()),
+            ..Default::default()
+        };
+        let results =
+            ffi_search_with_options(temp_path.to_string(), "soup".to_string(), options).unwrap();
+        assert_eq!(results.len(), 1);
+
+        let options = FfiTreeOptions {
+            walk: Some(walk.clone()),
+            ..Default::default()
+        };
+        let report = ffi_build_tree_with_diagnostics(temp_path.to_string(), options).unwrap();
+        assert_eq!(report.tree.root().children, vec!["soup".to_string()]);
+
+        let result =
+            ffi_get_recipe_with_options(vec![temp_path.to_string()], "old soup".to_string(), walk);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_diagnostics_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "A hearty soup");
+        fs::write(format!("{}/broken.cook", temp_path), b"Soup \xff").unwrap();
+
+        let report = ffi_search_with_diagnostics(
+            temp_path.to_string(),
+            "soup".to_string(),
+            FfiSearchOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.recipes.len(), 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.skipped[0].path.ends_with("broken.cook"));
+        assert!(!report.skipped[0].error.is_empty());
+
+        let report =
+            ffi_build_tree_with_diagnostics(temp_path.to_string(), FfiTreeOptions::default())
+                .unwrap();
+        assert_eq!(report.tree.root().children, vec!["soup".to_string()]);
+        assert_eq!(report.skipped.len(), 1);
+    }
+
     #[test]
     fn test_build_tree() {
         let temp_dir = TempDir::new().unwrap();
@@ -556,6 +2460,65 @@ mod tests {
         assert_eq!(recipes.len(), 1);
     }
 
+    #[test]
+    fn test_tree_recipes_keep_images_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        create_test_recipe(temp_path, "soup", "Boil @water");
+        let title_image = temp_dir.path().join("soup.jpg");
+        let step_image = temp_dir.path().join("soup.1.jpg");
+        fs::write(&title_image, "dummy image content").unwrap();
+        fs::write(&step_image, "dummy image content").unwrap();
+
+        let tree = build_tree(temp_path.to_string()).unwrap();
+
+        // The images resolved by the walk are handed out, not looked up again
+        fs::remove_file(&title_image).unwrap();
+        fs::remove_file(&step_image).unwrap();
+        let title_image = title_image.to_str().map(String::from);
+        let step_image = step_image.to_str().map(String::from);
+        let recipes = [
+            tree.all_recipes().remove(0),
+            tree.get_recipe_at_path(vec!["soup".to_string()]).unwrap(),
+            tree.root_node()
+                .child("soup".to_string())
+                .unwrap()
+                .recipe()
+                .unwrap(),
+        ];
+        for recipe in recipes {
+            assert_eq!(recipe.title_image(), title_image);
+            assert_eq!(recipe.get_step_image(0, 1), step_image);
+        }
+    }
+
+    #[test]
+    fn test_lazy_tree_reads_recipes_once_ffi() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        let soup = create_test_recipe(temp_path, "soup", "---\ntitle: Leek Soup\n---\nBoil");
+
+        let options = FfiTreeOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let tree = ffi_build_tree_with_diagnostics(temp_path.to_string(), options)
+            .unwrap()
+            .tree;
+        let path = vec!["soup".to_string()];
+        let first = tree.get_recipe_at_path(path.clone()).unwrap();
+        assert_eq!(first.name(), Some("Leek Soup".to_string()));
+
+        // Recipes handed out later share the metadata read by the first one
+        fs::write(&soup, "---\ntitle: Chicken Soup\n---\nBoil").unwrap();
+        let second = tree.get_recipe_at_path(path).unwrap();
+        assert_eq!(second.name(), Some("Leek Soup".to_string()));
+        assert_eq!(
+            tree.all_recipes()[0].metadata().title.as_deref(),
+            Some("Leek Soup")
+        );
+    }
+
     #[test]
     fn test_step_images_conversion() {
         use std::collections::HashMap;
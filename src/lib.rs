@@ -5,9 +5,10 @@
 //! This library provides utilities for working with .cook and .menu files,
 //! including:
 //! - Loading recipes from files or content
-//! - Searching recipes by name and content
+//! - Searching recipes by name, content and metadata filters
 //! - Building hierarchical directory trees of recipes
 //! - Extracting and working with recipe metadata
+//! - Watching recipe directories and keeping trees and indexes up to date
 //!
 //! ## Quick Start
 //!
@@ -45,13 +46,31 @@ pub mod ffi;
 /// Core data models for recipes and metadata.
 pub mod model;
 
+/// Unicode normalization and accent folding for name and text comparisons.
+pub(crate) mod normalize;
+
+/// Diagnostics for recipe files skipped while scanning.
+pub mod diagnostics;
+
 /// Recipe searching functionality.
 pub mod search;
 
 /// Recipe tree building for directory hierarchies.
 pub mod tree;
 
-pub use fetcher::{get_recipe, get_recipe_str};
+/// Directory walks honoring `.cookignore` files.
+pub mod walk;
+
+/// Watching recipe directories for changes.
+pub mod watch;
+
+pub use diagnostics::{Partial, SkippedFile};
+pub use fetcher::{get_recipe, get_recipe_str, get_recipe_with_options};
 pub use model::*;
-pub use search::search;
-pub use tree::{build_tree, RecipeTree};
+pub use search::{search, search_roots, Query};
+pub use tree::{
+    build_tree, build_tree_with_diagnostics, build_tree_with_options, diff_trees, refresh_tree,
+    Asset, AssetKind, RecipeFilter, RecipeTree, TreeDiff, TreeOptions, TreeRefresh, TreeSort,
+};
+pub use walk::{SymlinkPolicy, WalkOptions, COOKIGNORE_FILE};
+pub use watch::{RecipeWatcher, WatchEvent, WatchOptions};
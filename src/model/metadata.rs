@@ -1,3 +1,4 @@
+use crate::normalize::fold;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 use std::collections::HashMap;
@@ -54,6 +55,11 @@ impl Metadata {
         self.data.get(key)
     }
 
+    /// Returns all metadata fields, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.data.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
     /// Returns the number of servings from metadata.
     ///
     /// Returns `None` if no servings field is present or if it's not a number.
@@ -127,6 +133,49 @@ impl Metadata {
         }
         Vec::new()
     }
+
+    /// Returns true if there are no metadata fields.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Creates metadata from fields and tags, e.g. the defaults of a
+    /// directory.
+    pub(crate) fn from_fields(
+        fields: impl IntoIterator<Item = (String, Value)>,
+        tags: &[String],
+    ) -> Self {
+        let mut data: HashMap<String, Value> = fields.into_iter().collect();
+        if !tags.is_empty() {
+            data.remove("tag");
+            let tags = tags.iter().map(|tag| Value::String(tag.clone())).collect();
+            data.insert("tags".to_string(), Value::Sequence(tags));
+        }
+        Metadata { data }
+    }
+
+    /// Fills in fields missing from this metadata from `defaults`, and adds
+    /// the tags of `defaults` that aren't listed yet, ignoring case and
+    /// diacritics.
+    pub(crate) fn inherit(&mut self, defaults: &Metadata) {
+        let default_tags = defaults.tags();
+        if !default_tags.is_empty() {
+            let mut tags = self.tags();
+            for tag in default_tags {
+                if !tags.iter().any(|t| fold(t) == fold(&tag)) {
+                    tags.push(tag);
+                }
+            }
+            self.data.remove("tag");
+            let tags = tags.into_iter().map(Value::String).collect();
+            self.data.insert("tags".to_string(), Value::Sequence(tags));
+        }
+        for (key, value) in &defaults.data {
+            if key != "tags" && key != "tag" && !self.data.contains_key(key) {
+                self.data.insert(key.clone(), value.clone());
+            }
+        }
+    }
 }
 
 /// Parse YAML frontmatter from raw YAML content (without --- markers)
@@ -179,6 +228,12 @@ where
     Ok(None)
 }
 
+/// Extract and parse metadata from the frontmatter of recipe content
+pub(crate) fn parse_frontmatter(content: &str) -> Metadata {
+    extract_and_parse_metadata(content.lines().map(|line| Ok::<_, ()>(line.to_string())))
+        .unwrap_or_default()
+}
+
 /// Helper to extract and parse metadata from a Result iterator
 pub(super) fn extract_and_parse_metadata<I, E>(lines: I) -> Result<Metadata, E>
 where
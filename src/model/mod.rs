@@ -6,5 +6,7 @@
 mod metadata;
 mod recipe_entry;
 
+pub(crate) use metadata::parse_frontmatter;
 pub use metadata::Metadata;
+pub(crate) use recipe_entry::{is_image_file, IMAGE_EXTENSIONS};
 pub use recipe_entry::{RecipeEntry, RecipeEntryError, StepImageCollection};
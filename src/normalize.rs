@@ -0,0 +1,166 @@
+//! Unicode normalization and accent folding.
+//!
+//! Recipe names and content can arrive in different Unicode forms: files
+//! saved on macOS use decomposed (NFD) names while most other platforms
+//! produce composed (NFC) text. Comparisons go through [`fold`], which
+//! removes those differences along with case and diacritics, so that
+//! "Crème Brûlée", "CRE\u{300}ME BRU\u{302}LE\u{301}E" and "creme brulee"
+//! all compare equal.
+
+use std::ops::Range;
+use unicode_normalization::char::{decompose_compatible, is_combining_mark};
+use unicode_normalization::UnicodeNormalization;
+
+/// Returns the folded form of a text: compatibility-decomposed, lowercase
+/// and without diacritics.
+pub(crate) fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, &mut folded);
+    }
+    folded
+}
+
+/// Returns the text in composed (NFC) form.
+pub(crate) fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// Appends the folded form of a single character.
+fn fold_char(c: char, out: &mut String) {
+    if c.is_ascii() {
+        out.push(c.to_ascii_lowercase());
+        return;
+    }
+
+    decompose_compatible(c, |decomposed| {
+        for lower in decomposed.to_lowercase() {
+            if is_combining_mark(lower) {
+                continue;
+            }
+            // Letters without a decomposition that are commonly typed
+            // without their diacritic
+            match lower {
+                'ß' => out.push_str("ss"),
+                'æ' => out.push_str("ae"),
+                'œ' => out.push_str("oe"),
+                'ø' => out.push('o'),
+                'ł' => out.push('l'),
+                'đ' | 'ð' => out.push('d'),
+                'þ' => out.push_str("th"),
+                'ı' => out.push('i'),
+                _ => out.push(lower),
+            }
+        }
+    });
+}
+
+/// Folded text together with the original position of every byte.
+pub(crate) struct FoldedText {
+    /// Folded text
+    pub(crate) text: String,
+    /// For each byte of `text`, the byte range of the original characters it came from
+    origins: Vec<Range<usize>>,
+}
+
+impl FoldedText {
+    pub(crate) fn new(original: &str) -> Self {
+        let mut text = String::with_capacity(original.len());
+        let mut origins: Vec<Range<usize>> = Vec::with_capacity(original.len());
+        // Start in `origins` of the last character that produced output
+        let mut last = None;
+
+        for (start, c) in original.char_indices() {
+            let origin = start..start + c.len_utf8();
+            let before = text.len();
+            fold_char(c, &mut text);
+            if text.len() > before {
+                origins.extend(std::iter::repeat_n(origin, text.len() - before));
+                last = Some(before);
+            } else if let Some(last) = last {
+                // A dropped combining mark belongs to the preceding character
+                for range in &mut origins[last..] {
+                    range.end = origin.end;
+                }
+            }
+        }
+
+        FoldedText { text, origins }
+    }
+
+    /// Returns the byte range in the original text of a range of the folded text.
+    pub(crate) fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        self.origins[range.start].start..self.origins[range.end - 1].end
+    }
+
+    /// Returns the byte ranges in the original text where a folded term occurs.
+    pub(crate) fn find(&self, term: &str) -> Vec<Range<usize>> {
+        if term.is_empty() {
+            return Vec::new();
+        }
+        self.text
+            .match_indices(term)
+            .map(|(start, matched)| self.original_range(start..start + matched.len()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold() {
+        let composed = "Crème Brûlée";
+        let decomposed = "Cre\u{300}me Bru\u{302}le\u{301}e";
+        assert_eq!(fold(composed), "creme brulee");
+        assert_eq!(fold(decomposed), "creme brulee");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("Smørrebrød"), "smorrebrod");
+        // Compatibility forms are folded too
+        assert_eq!(fold("ﬁsh ½"), "fish 1⁄2");
+    }
+
+    #[test]
+    fn test_nfc() {
+        assert_eq!(nfc("Cre\u{300}me"), "Crème");
+        assert_eq!(nfc("Crème"), "Crème");
+    }
+
+    #[test]
+    fn test_find_maps_to_original_ranges() {
+        let text = FoldedText::new("Add SALT and more Salt");
+        assert_eq!(text.find("salt"), vec![4..8, 18..22]);
+        assert!(text.find("pepper").is_empty());
+    }
+
+    #[test]
+    fn test_find_with_multibyte_lowercase() {
+        // 'İ' lowercases to two characters ("i̇"), shifting byte offsets
+        let original = "İstanbul Soup";
+        let text = FoldedText::new(original);
+        let ranges = text.find("soup");
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(&original[ranges[0].clone()], "Soup");
+    }
+
+    #[test]
+    fn test_find_covers_combining_marks() {
+        let composed = "Fill the crêpes";
+        let decomposed = "Fill the cre\u{302}pes";
+        for original in [composed, decomposed] {
+            let text = FoldedText::new(original);
+            let ranges = text.find("crepe");
+            assert_eq!(ranges.len(), 1);
+            assert_eq!(
+                &original[ranges[0].clone()],
+                &original[9..original.len() - 1]
+            );
+        }
+
+        // A trailing combining mark stays within the highlighted range
+        let original = "cafe\u{301}";
+        let ranges = FoldedText::new(original).find("cafe");
+        assert_eq!(ranges, vec![0..original.len()]);
+    }
+}
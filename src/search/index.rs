@@ -0,0 +1,984 @@
+//! Persistent inverted index for recipe search.
+//!
+//! The index caches the content of every recipe below a base directory
+//! together with a trigram posting list, so queries only need to score
+//! candidate documents instead of re-reading the whole tree. It can be
+//! saved to and loaded from a cache file, and refreshed incrementally
+//! by comparing file modification times and sizes.
+
+use super::query::QueryTarget;
+use super::rank::FieldLengths;
+use super::{
+    sort_results, Document, IntoQuery, Scorer, SearchError, SearchHit, SearchOptions, SearchResult,
+    SearchResults,
+};
+use crate::diagnostics::{Partial, SkippedFile};
+use crate::model::{Metadata, RecipeEntry};
+use crate::normalize::fold;
+use crate::tree::InheritedMetadata;
+use crate::walk::{is_ignored, recipe_files, FileStamp, WalkOptions};
+use crate::watch::WatchEvent;
+use camino::{Utf8Path, Utf8PathBuf};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+
+/// Version of the on-disk index format.
+///
+/// Bumped whenever the serialized layout changes; caches with a different
+/// version are rejected by [`SearchIndex::load`].
+const INDEX_FORMAT_VERSION: u32 = 7;
+
+/// A single recipe file stored in the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedDocument {
+    path: Utf8PathBuf,
+    stamp: FileStamp,
+    /// File content, `None` if the file could not be read as UTF-8
+    content: Option<String>,
+    /// Frontmatter metadata with what the file inherits from directory
+    /// configs, used by query filters
+    metadata: Metadata,
+    /// Number of words in each ranking field
+    lengths: FieldLengths,
+}
+
+impl IndexedDocument {
+    fn read(path: Utf8PathBuf, inherited: &InheritedMetadata) -> io::Result<Self> {
+        let stamp = FileStamp::of(&path)?;
+        let content = fs::read_to_string(&path).ok();
+        let document =
+            Document::new(&path, content.as_deref()).inheriting(inherited.for_file(&path));
+        let metadata = document.metadata().clone();
+        let lengths = document.lengths();
+        Ok(IndexedDocument {
+            path,
+            stamp,
+            content,
+            metadata,
+            lengths,
+        })
+    }
+
+    /// Returns the trigrams of the folded document content and metadata.
+    ///
+    /// The metadata is included for the fields inherited from directory
+    /// configs, which are matched like the frontmatter but not part of the
+    /// content.
+    fn trigrams(&self) -> HashSet<String> {
+        let Some(content) = self.content.as_deref() else {
+            return HashSet::new();
+        };
+        let mut grams = trigrams(&fold(content));
+        if !self.metadata.is_empty() {
+            let metadata = serde_yaml::to_string(&self.metadata).unwrap_or_default();
+            grams.extend(trigrams(&fold(&metadata)));
+        }
+        grams
+    }
+}
+
+/// Summary of the changes applied by [`SearchIndex::refresh`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshSummary {
+    /// Number of new files added to the index
+    pub added: usize,
+    /// Number of files re-read because their modification time or size changed
+    pub updated: usize,
+    /// Number of files removed from the index because they no longer exist
+    /// or are now ignored
+    pub removed: usize,
+}
+
+impl RefreshSummary {
+    /// Returns true if the refresh did not change the index.
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.updated == 0 && self.removed == 0
+    }
+}
+
+/// An opt-in, persistent search index over a recipe directory.
+///
+/// The index is built from the same .cook and .menu files that [`search`](super::search)
+/// walks, and query results are scored identically. Use it when the same
+/// directory is queried repeatedly: build or load it once, call
+/// [`refresh`](SearchIndex::refresh) to pick up changed files, and run
+/// queries against the in-memory data.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::SearchIndex;
+/// use camino::Utf8Path;
+///
+/// let cache = Utf8Path::new("./recipes/.search-index.json");
+///
+/// // Load the cached index (or build a new one) and pick up changes
+/// let mut index = SearchIndex::open(Utf8Path::new("./recipes"), cache)?;
+/// let results = index.search("chocolate cake")?;
+///
+/// // Persist the index for the next start
+/// index.save(cache)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    base_dir: Utf8PathBuf,
+    /// Files and directories left out in addition to `.cookignore` files
+    walk: WalkOptions,
+    next_id: u32,
+    documents: BTreeMap<u32, IndexedDocument>,
+    /// Lowercase trigram -> ids of documents whose content contains it
+    postings: HashMap<String, BTreeSet<u32>>,
+}
+
+impl SearchIndex {
+    /// Builds a new index from all recipes in a directory tree.
+    ///
+    /// Files that cannot be listed or read are left out of the index.
+    pub fn build(base_dir: &Utf8Path) -> Result<Self, SearchError> {
+        Self::build_with_options(base_dir, &WalkOptions::default())
+    }
+
+    /// Builds a new index like [`build`](SearchIndex::build), leaving out
+    /// the files excluded by `walk`.
+    ///
+    /// The options are stored with the index and used by every
+    /// [`refresh`](SearchIndex::refresh). Files that cannot be listed or
+    /// read are left out; use
+    /// [`build_with_diagnostics`](SearchIndex::build_with_diagnostics) to
+    /// find out which.
+    pub fn build_with_options(
+        base_dir: &Utf8Path,
+        walk: &WalkOptions,
+    ) -> Result<Self, SearchError> {
+        Ok(Self::build_with_diagnostics(base_dir, walk)?.into_value())
+    }
+
+    /// Builds a new index like [`build_with_options`](SearchIndex::build_with_options)
+    /// and reports the files that were left out because they could not be
+    /// listed or read.
+    pub fn build_with_diagnostics(
+        base_dir: &Utf8Path,
+        walk: &WalkOptions,
+    ) -> Result<Partial<Self>, SearchError> {
+        let mut index = SearchIndex {
+            version: INDEX_FORMAT_VERSION,
+            base_dir: base_dir.to_path_buf(),
+            walk: walk.clone(),
+            next_id: 0,
+            documents: BTreeMap::new(),
+            postings: HashMap::new(),
+        };
+
+        let mut skipped = Vec::new();
+        let paths = recipe_files(base_dir, walk, &mut skipped);
+        for doc in read_documents(base_dir, paths, &mut skipped) {
+            index.insert(doc);
+        }
+
+        Ok(Partial::new(index, skipped))
+    }
+
+    /// Loads an index previously written with [`save`](SearchIndex::save).
+    ///
+    /// The loaded index reflects the state at the time it was saved; call
+    /// [`refresh`](SearchIndex::refresh) to bring it up to date.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SearchError` if the cache file cannot be read, is not a valid
+    /// index, or was written by an incompatible version of this library.
+    pub fn load(cache_path: &Utf8Path) -> Result<Self, SearchError> {
+        let data = fs::read(cache_path)?;
+        let index: SearchIndex = serde_json::from_slice(&data)?;
+        if index.version != INDEX_FORMAT_VERSION {
+            return Err(SearchError::IndexError(format!(
+                "unsupported index version {} (expected {})",
+                index.version, INDEX_FORMAT_VERSION
+            )));
+        }
+        Ok(index)
+    }
+
+    /// Loads a cached index for `base_dir` and refreshes it, or builds a new one.
+    ///
+    /// A new index is built if the cache file does not exist, cannot be
+    /// loaded, or belongs to a different base directory. A loaded index
+    /// keeps the walk options it was built with; use
+    /// [`open_with_options`](SearchIndex::open_with_options) to build it
+    /// with exclusions.
+    pub fn open(base_dir: &Utf8Path, cache_path: &Utf8Path) -> Result<Self, SearchError> {
+        match Self::load(cache_path) {
+            Ok(mut index) if index.base_dir == base_dir => {
+                index.refresh()?;
+                Ok(index)
+            }
+            _ => Self::build(base_dir),
+        }
+    }
+
+    /// Loads a cached index like [`open`](SearchIndex::open), leaving out
+    /// the files excluded by `walk`.
+    ///
+    /// A new index is also built if the cached one was built with other
+    /// walk options.
+    pub fn open_with_options(
+        base_dir: &Utf8Path,
+        cache_path: &Utf8Path,
+        walk: &WalkOptions,
+    ) -> Result<Self, SearchError> {
+        match Self::load(cache_path) {
+            Ok(mut index) if index.base_dir == base_dir && index.walk == *walk => {
+                index.refresh()?;
+                Ok(index)
+            }
+            _ => Self::build_with_options(base_dir, walk),
+        }
+    }
+
+    /// Writes the index to a cache file.
+    pub fn save(&self, cache_path: &Utf8Path) -> Result<(), SearchError> {
+        let data = serde_json::to_vec(self)?;
+        fs::write(cache_path, data)?;
+        Ok(())
+    }
+
+    /// Brings the index up to date with the filesystem.
+    ///
+    /// Only files whose modification time or size changed are re-read.
+    /// New files are added; deleted files and files that became ignored
+    /// are dropped. Metadata inherited from changed directory configs is
+    /// only picked up with the files that are re-read; build a new index to
+    /// apply it to all of them.
+    ///
+    /// Files that cannot be listed or read are left out of the index; use
+    /// [`refresh_with_diagnostics`](SearchIndex::refresh_with_diagnostics)
+    /// to find out which.
+    pub fn refresh(&mut self) -> Result<RefreshSummary, SearchError> {
+        Ok(self.refresh_with_diagnostics()?.into_value())
+    }
+
+    /// Brings the index up to date like [`refresh`](SearchIndex::refresh)
+    /// and reports the files that were left out because they could not be
+    /// listed or read.
+    pub fn refresh_with_diagnostics(&mut self) -> Result<Partial<RefreshSummary>, SearchError> {
+        let mut summary = RefreshSummary::default();
+        let mut skipped = Vec::new();
+        let mut by_path: HashMap<Utf8PathBuf, u32> = self
+            .documents
+            .iter()
+            .map(|(id, doc)| (doc.path.clone(), *id))
+            .collect();
+
+        let mut changed = Vec::new();
+        let mut known = HashSet::new();
+        for path in recipe_files(&self.base_dir, &self.walk, &mut skipped) {
+            let Some(id) = by_path.remove(&path) else {
+                changed.push(path);
+                continue;
+            };
+            match FileStamp::of(&path) {
+                Ok(stamp) if self.documents[&id].stamp == stamp => {}
+                Ok(_) => {
+                    self.remove(id);
+                    known.insert(path.clone());
+                    changed.push(path);
+                }
+                Err(e) => {
+                    self.remove(id);
+                    skipped.push(SkippedFile::new(path, e));
+                    summary.removed += 1;
+                }
+            }
+        }
+        for doc in read_documents(&self.base_dir, changed, &mut skipped) {
+            if known.remove(&doc.path) {
+                summary.updated += 1;
+            } else {
+                summary.added += 1;
+            }
+            self.insert(doc);
+        }
+        // Changed files that could not be read again are gone from the index
+        summary.removed += known.len();
+
+        // Anything left over was not found on disk anymore
+        for id in by_path.into_values() {
+            self.remove(id);
+            summary.removed += 1;
+        }
+
+        Ok(Partial::new(summary, skipped))
+    }
+
+    /// Applies a change reported by a [`RecipeWatcher`](crate::watch::RecipeWatcher)
+    /// watching the indexed directory.
+    ///
+    /// Added, modified and renamed recipes are re-read and removed ones
+    /// dropped, without walking the directory like
+    /// [`refresh`](SearchIndex::refresh) does. Recipes left out by
+    /// `.cookignore` files or the walk options of the index are not added,
+    /// and image events don't affect the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SearchError` if the metadata of an added or modified recipe
+    /// cannot be read. The recipe is then left out of the index.
+    pub fn apply(&mut self, event: &WatchEvent) -> Result<(), SearchError> {
+        let (removed, added) = match event {
+            WatchEvent::RecipeAdded(path) | WatchEvent::RecipeModified(path) => (path, Some(path)),
+            WatchEvent::RecipeRemoved(path) => (path, None),
+            WatchEvent::RecipeRenamed { from, to } => (from, Some(to)),
+            WatchEvent::ImageAdded(_) | WatchEvent::ImageRemoved(_) => return Ok(()),
+        };
+
+        let ids: Vec<u32> = self
+            .documents
+            .iter()
+            .filter(|(_, doc)| doc.path == *removed || Some(&doc.path) == added)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.remove(id);
+        }
+        let Some(path) = added.filter(|path| !is_ignored(&self.base_dir, path, &self.walk)) else {
+            return Ok(());
+        };
+        // Directory configs are not watched, so only those above the recipe
+        // are read again, as they may have changed since the last event
+        let inherited = InheritedMetadata::new(&[&self.base_dir]);
+        self.insert(IndexedDocument::read(path.clone(), &inherited)?);
+        Ok(())
+    }
+
+    /// Searches the index for recipes matching a query.
+    ///
+    /// Returns the same recipes, in the same order, as
+    /// [`search`](super::search) would for the indexed directory.
+    pub fn search(&self, query: impl IntoQuery) -> Result<Vec<RecipeEntry>, SearchError> {
+        self.search_with_options(query, &SearchOptions::default())
+    }
+
+    /// Searches the index like [`search_with_options`](super::search_with_options).
+    pub fn search_with_options(
+        &self,
+        query: impl IntoQuery,
+        options: &SearchOptions,
+    ) -> Result<Vec<RecipeEntry>, SearchError> {
+        Ok(self.search_with_diagnostics(query, options)?.into_value())
+    }
+
+    /// Searches the index like [`search_with_diagnostics`](super::search_with_diagnostics),
+    /// reporting indexed files that could not be read.
+    pub fn search_with_diagnostics(
+        &self,
+        query: impl IntoQuery,
+        options: &SearchOptions,
+    ) -> Result<Partial<Vec<RecipeEntry>>, SearchError> {
+        Ok(self.search_iter(query, options)?.load_all())
+    }
+
+    /// Searches the index like [`search_iter`](super::search_iter), loading
+    /// the ranked recipes lazily.
+    pub fn search_iter(
+        &self,
+        query: impl IntoQuery,
+        options: &SearchOptions,
+    ) -> Result<SearchResults, SearchError> {
+        let scorer = Scorer::new(query.into_query()?, options, &[&self.base_dir])?;
+        let mut skipped = Vec::new();
+        let results: Vec<SearchResult> = self
+            .search_results(&scorer, &mut skipped)
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect();
+        Ok(SearchResults::new(
+            results.len(),
+            options.paginate(results).collect(),
+            skipped,
+            scorer.into_inherited(),
+        ))
+    }
+
+    /// Searches the index and explains why each recipe matched.
+    ///
+    /// Returns the same hits as [`search_detailed`](super::search_detailed)
+    /// would for the indexed directory, with snippets taken from the
+    /// indexed content.
+    pub fn search_detailed(&self, query: impl IntoQuery) -> Result<Vec<SearchHit>, SearchError> {
+        self.search_detailed_with_options(query, &SearchOptions::default())
+    }
+
+    /// Searches the index like [`search_detailed_with_options`](super::search_detailed_with_options).
+    pub fn search_detailed_with_options(
+        &self,
+        query: impl IntoQuery,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchHit>, SearchError> {
+        Ok(self
+            .search_detailed_with_diagnostics(query, options)?
+            .into_value())
+    }
+
+    /// Searches the index like
+    /// [`search_detailed_with_diagnostics`](super::search_detailed_with_diagnostics),
+    /// reporting indexed files that could not be read.
+    pub fn search_detailed_with_diagnostics(
+        &self,
+        query: impl IntoQuery,
+        options: &SearchOptions,
+    ) -> Result<Partial<Vec<SearchHit>>, SearchError> {
+        let scorer = Scorer::new(query.into_query()?, options, &[&self.base_dir])?;
+        let mut skipped = Vec::new();
+        let results = self.search_results(&scorer, &mut skipped);
+        let mut hits = Vec::new();
+        for (id, result) in options.paginate(results) {
+            let doc = &self.documents[&id];
+            let document = Document::indexed(
+                &doc.path,
+                doc.content.as_deref(),
+                &doc.metadata,
+                doc.lengths,
+            );
+            match scorer.hit(&self.base_dir, &document, result.score) {
+                Ok(hit) => hits.push(hit),
+                Err(e) => skipped.push(SkippedFile::new(&doc.path, e)),
+            }
+        }
+        Ok(Partial::new(hits, skipped))
+    }
+
+    /// Returns the directory this index was built from.
+    pub fn base_dir(&self) -> &Utf8Path {
+        &self.base_dir
+    }
+
+    /// Returns the number of indexed recipe files.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns true if the index contains no recipe files.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Scores all documents and returns the matching ones, sorted, with their ids.
+    ///
+    /// Documents whose content could not be read are recorded in `skipped`.
+    fn search_results(
+        &self,
+        scorer: &Scorer,
+        skipped: &mut Vec<SkippedFile>,
+    ) -> Vec<(u32, SearchResult)> {
+        // Fuzzy and stem matches can't be found through exact trigrams
+        let candidates = if scorer.matches_inexactly() {
+            None
+        } else {
+            self.candidates(&scorer.variants())
+        };
+
+        // Documents are evaluated in parallel, keeping their order
+        let evaluated: Vec<_> = self
+            .documents
+            .par_iter()
+            .map(|(id, doc)| {
+                let content = doc.content.as_deref()?;
+                let document =
+                    Document::indexed(&doc.path, Some(content), &doc.metadata, doc.lengths);
+                // Documents that cannot contain any term can only match by filename
+                let scan_content = candidates.as_ref().is_none_or(|c| c.contains(id));
+                Some(scorer.evaluate(&document, scan_content))
+            })
+            .collect();
+
+        // Relevance depends on statistics of all documents, so score in a second pass
+        let mut corpus = scorer.corpus();
+        let mut matching = vec![];
+        for ((id, doc), evaluated) in self.documents.iter().zip(evaluated) {
+            let Some((candidate, accepted)) = evaluated else {
+                skipped.push(SkippedFile::new(
+                    &doc.path,
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "File content is not valid UTF-8",
+                    ),
+                ));
+                continue;
+            };
+            corpus.add(&candidate.frequencies);
+            if accepted {
+                matching.push((*id, candidate));
+            }
+        }
+
+        let mut ids = HashMap::new();
+        let mut scored_results = vec![];
+        for (id, candidate) in matching {
+            if let Some(score) = scorer.score(&candidate, &corpus) {
+                let path = &self.documents[&id].path;
+                let mut result = SearchResult::new(path.clone());
+                result.add_score(score);
+                ids.insert(path.clone(), id);
+                scored_results.push(result);
+            }
+        }
+
+        sort_results(&mut scored_results);
+        scored_results
+            .into_iter()
+            .map(|result| (ids[&result.path], result))
+            .collect()
+    }
+
+    /// Returns the ids of documents whose content may contain any of the terms.
+    ///
+    /// Returns `None` if a term is too short to be looked up, in which case
+    /// every document is a candidate.
+    fn candidates(&self, terms: &[String]) -> Option<HashSet<u32>> {
+        let mut candidates = HashSet::new();
+
+        for term in terms {
+            let grams = trigrams(term);
+            if grams.is_empty() {
+                return None;
+            }
+
+            // A document must contain every trigram of the term
+            let mut matching: Option<BTreeSet<u32>> = None;
+            for gram in &grams {
+                let ids = self.postings.get(gram).cloned().unwrap_or_default();
+                matching = Some(match matching {
+                    Some(current) => current.intersection(&ids).copied().collect(),
+                    None => ids,
+                });
+            }
+            candidates.extend(matching.unwrap_or_default());
+        }
+
+        Some(candidates)
+    }
+
+    fn insert(&mut self, doc: IndexedDocument) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        for gram in doc.trigrams() {
+            self.postings.entry(gram).or_default().insert(id);
+        }
+        self.documents.insert(id, doc);
+    }
+
+    fn remove(&mut self, id: u32) {
+        let Some(doc) = self.documents.remove(&id) else {
+            return;
+        };
+
+        for gram in doc.trigrams() {
+            if let Some(ids) = self.postings.get_mut(&gram) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.postings.remove(&gram);
+                }
+            }
+        }
+    }
+}
+
+/// Reads recipe files below a base directory in parallel, keeping their
+/// order and recording those that cannot be read in `skipped`.
+fn read_documents(
+    base_dir: &Utf8Path,
+    paths: Vec<Utf8PathBuf>,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<IndexedDocument> {
+    let inherited = InheritedMetadata::new(&[base_dir]);
+    let read: Vec<_> = paths
+        .into_par_iter()
+        .map(|path| IndexedDocument::read(path.clone(), &inherited).map_err(|e| (path, e)))
+        .collect();
+    let mut documents = Vec::new();
+    for result in read {
+        match result {
+            Ok(document) => documents.push(document),
+            Err((path, e)) => skipped.push(SkippedFile::new(path, e)),
+        }
+    }
+    documents
+}
+
+/// Splits text into its set of character trigrams.
+fn trigrams(text: &str) -> HashSet<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::search;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    fn create_test_recipe(dir: &Utf8Path, name: &str, content: &str) -> Utf8PathBuf {
+        let path = dir.join(format!("{name}.cook"));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn setup_test_recipes() -> (TempDir, Utf8PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+
+        create_test_recipe(
+            &temp_dir_path,
+            "pancakes",
+            "Make delicious pancakes with @maple syrup{}",
+        );
+        create_test_recipe(&temp_dir_path, "waffles", "Crispy @waffles with @syrup");
+        create_test_recipe(&temp_dir_path, "syrup", "Boil @sugar{100%g} in @water");
+
+        let breakfast_dir = temp_dir_path.join("breakfast");
+        fs::create_dir_all(&breakfast_dir).unwrap();
+        create_test_recipe(&breakfast_dir, "omelette", "@Cheese and @mushroom omelette");
+
+        (temp_dir, temp_dir_path)
+    }
+
+    fn paths(recipes: &[RecipeEntry]) -> Vec<Utf8PathBuf> {
+        recipes.iter().map(|r| r.path().unwrap().clone()).collect()
+    }
+
+    #[test]
+    fn test_index_matches_search() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let index = SearchIndex::build(&dir).unwrap();
+        assert_eq!(index.len(), 4);
+
+        for query in [
+            "syrup",
+            "pancake",
+            "cheese omelette",
+            "a",
+            "nonexistent",
+            "",
+        ] {
+            assert_eq!(
+                paths(&index.search(query).unwrap()),
+                paths(&search(&dir, query).unwrap()),
+                "query {query:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_search_detailed_matches_search() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let index = SearchIndex::build(&dir).unwrap();
+
+        let from_index = index.search_detailed("syrup").unwrap();
+        let from_walk = crate::search::search_detailed(&dir, "syrup").unwrap();
+        assert_eq!(from_index.len(), from_walk.len());
+        for (a, b) in from_index.iter().zip(&from_walk) {
+            assert_eq!(a.recipe.path(), b.recipe.path());
+            assert_eq!(a.score, b.score);
+            assert_eq!(a.matches, b.matches);
+            assert_eq!(a.snippets, b.snippets);
+        }
+    }
+
+    #[test]
+    fn test_index_fuzzy_matches_search() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let index = SearchIndex::build(&dir).unwrap();
+        let options = SearchOptions {
+            fuzziness: crate::search::Fuzziness::Auto,
+            ..Default::default()
+        };
+
+        let from_index = index.search_with_options("syrop", &options).unwrap();
+        assert_eq!(from_index.len(), 3);
+        assert_eq!(
+            paths(&from_index),
+            paths(&crate::search::search_with_options(&dir, "syrop", &options).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_index_pagination_matches_search() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let index = SearchIndex::build(&dir).unwrap();
+
+        for offset in 0..3 {
+            let options = SearchOptions {
+                offset,
+                limit: Some(1),
+                ..Default::default()
+            };
+            assert_eq!(
+                paths(&index.search_with_options("syrup", &options).unwrap()),
+                paths(&crate::search::search_with_options(&dir, "syrup", &options).unwrap())
+            );
+        }
+
+        let results = index
+            .search_iter("syrup", &SearchOptions::default())
+            .unwrap();
+        assert_eq!(results.total(), search(&dir, "syrup").unwrap().len());
+    }
+
+    #[test]
+    fn test_index_skips_unreadable_files() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let broken = dir.join("syrup_cake.cook");
+        fs::write(&broken, b"Pour @syrup\xff{}").unwrap();
+        let index = SearchIndex::build(&dir).unwrap();
+
+        let report = index
+            .search_with_diagnostics("syrup", &SearchOptions::default())
+            .unwrap();
+        let walk = crate::search::search_with_diagnostics(&dir, "syrup", &SearchOptions::default())
+            .unwrap();
+        assert_eq!(paths(&report.value), paths(&walk.value));
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, broken);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_index_reports_files_it_cannot_list() {
+        use std::os::unix::fs::symlink;
+
+        let (_temp_dir, dir) = setup_test_recipes();
+        let dangling = dir.join("ghost.cook");
+        symlink(dir.join("missing.cook"), &dangling).unwrap();
+
+        // One broken file doesn't keep the others out of the index
+        let report = SearchIndex::build_with_diagnostics(&dir, &WalkOptions::default()).unwrap();
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, dangling);
+        let mut index = report.value;
+        assert_eq!(index.len(), 4);
+
+        fs::remove_file(&dangling).unwrap();
+        assert!(index.refresh_with_diagnostics().unwrap().is_complete());
+        symlink(dir.join("missing.cook"), &dangling).unwrap();
+        create_test_recipe(&dir, "toast", "Toast with @honey");
+        let report = index.refresh_with_diagnostics().unwrap();
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, dangling);
+        assert_eq!(report.value.added, 1);
+        assert_eq!(index.len(), 5);
+    }
+
+    #[test]
+    fn test_index_synonyms_and_stemming_match_search() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        fs::write(
+            dir.join(crate::search::SYNONYMS_FILE),
+            "- [syrup, treacle]\n",
+        )
+        .unwrap();
+        create_test_recipe(&dir, "tart", "Pour the @treacle{} into the crust");
+        let index = SearchIndex::build(&dir).unwrap();
+
+        let options = SearchOptions {
+            stemmer: Some(std::sync::Arc::new(crate::search::Language::English)),
+            ..Default::default()
+        };
+        for (query, options) in [
+            ("syrup", SearchOptions::default()),
+            ("syrups", options.clone()),
+            ("omelettes", options),
+        ] {
+            assert_eq!(
+                paths(&index.search_with_options(query, &options).unwrap()),
+                paths(&crate::search::search_with_options(&dir, query, &options).unwrap()),
+                "query {query:?}"
+            );
+        }
+        // pancakes, waffles, syrup and the treacle tart
+        assert_eq!(index.search("syrup").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_index_save_and_load() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let cache = dir.join("index.json");
+
+        let index = SearchIndex::build(&dir).unwrap();
+        index.save(&cache).unwrap();
+
+        let loaded = SearchIndex::load(&cache).unwrap();
+        assert_eq!(loaded.base_dir(), dir);
+        assert_eq!(loaded.len(), index.len());
+        assert_eq!(
+            paths(&loaded.search("syrup").unwrap()),
+            paths(&index.search("syrup").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_index_load_rejects_other_version() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let cache = dir.join("index.json");
+
+        let mut index = SearchIndex::build(&dir).unwrap();
+        index.version = INDEX_FORMAT_VERSION + 1;
+        index.save(&cache).unwrap();
+
+        assert!(matches!(
+            SearchIndex::load(&cache),
+            Err(SearchError::IndexError(_))
+        ));
+    }
+
+    #[test]
+    fn test_index_refresh() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let mut index = SearchIndex::build(&dir).unwrap();
+        assert!(index.refresh().unwrap().is_empty());
+
+        // Modify, add and remove files
+        let waffles = dir.join("waffles.cook");
+        fs::write(&waffles, "Crispy @waffles with @honey and more text").unwrap();
+        let file = fs::File::options().write(true).open(&waffles).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        create_test_recipe(&dir, "toast", "Toast with @honey");
+        fs::remove_file(dir.join("syrup.cook")).unwrap();
+
+        let summary = index.refresh().unwrap();
+        assert_eq!(
+            summary,
+            RefreshSummary {
+                added: 1,
+                updated: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(index.len(), 4);
+        assert_eq!(
+            paths(&index.search("honey").unwrap()),
+            paths(&search(&dir, "honey").unwrap())
+        );
+        assert_eq!(index.search("honey").unwrap().len(), 2);
+        assert_eq!(
+            paths(&index.search("syrup").unwrap()),
+            paths(&search(&dir, "syrup").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_index_apply_watch_events() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let mut index = SearchIndex::build(&dir).unwrap();
+
+        let syrup = dir.join("syrup.cook");
+        let moved = dir.join("breakfast/syrup.cook");
+        fs::rename(&syrup, &moved).unwrap();
+        let toast = create_test_recipe(&dir, "toast", "Toast with @honey");
+        let waffles = dir.join("waffles.cook");
+        fs::write(&waffles, "Crispy @waffles with @honey").unwrap();
+        fs::remove_file(dir.join("pancakes.cook")).unwrap();
+        for event in [
+            WatchEvent::RecipeRenamed {
+                from: syrup,
+                to: moved.clone(),
+            },
+            WatchEvent::RecipeAdded(toast),
+            WatchEvent::RecipeModified(waffles),
+            WatchEvent::RecipeRemoved(dir.join("pancakes.cook")),
+            WatchEvent::ImageAdded(dir.join("toast.jpg")),
+        ] {
+            index.apply(&event).unwrap();
+        }
+
+        assert_eq!(index.len(), 4);
+        for query in ["honey", "syrup", "sugar", "pancakes"] {
+            assert_eq!(
+                paths(&index.search(query).unwrap()),
+                paths(&search(&dir, query).unwrap()),
+                "{query}"
+            );
+        }
+        assert_eq!(paths(&index.search("sugar").unwrap()), vec![moved]);
+        assert!(index.refresh().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_apply_skips_ignored_recipes() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        fs::write(dir.join(".cookignore"), "archive/\n").unwrap();
+        let options = WalkOptions {
+            exclude: vec!["drafts/".to_string()],
+            ..Default::default()
+        };
+        let mut index = SearchIndex::build_with_options(&dir, &options).unwrap();
+
+        fs::create_dir_all(dir.join("archive")).unwrap();
+        fs::create_dir_all(dir.join("drafts")).unwrap();
+        let waffles = dir.join("waffles.cook");
+        let moved = dir.join("archive/waffles.cook");
+        fs::rename(&waffles, &moved).unwrap();
+        let archived = create_test_recipe(&dir, "archive/crepes", "Thin @crepes");
+        let draft = create_test_recipe(&dir, "drafts/blinis", "Small @blinis");
+        for event in [
+            WatchEvent::RecipeAdded(archived.clone()),
+            WatchEvent::RecipeModified(draft),
+            WatchEvent::RecipeRenamed {
+                from: waffles,
+                to: moved,
+            },
+        ] {
+            index.apply(&event).unwrap();
+        }
+
+        assert_eq!(index.len(), 3);
+        assert!(index.search("crepes").unwrap().is_empty());
+        assert!(index.search("blinis").unwrap().is_empty());
+        assert!(index.search("waffles").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_open_builds_without_cache() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let cache = dir.join("missing.json");
+
+        let index = SearchIndex::open(&dir, &cache).unwrap();
+        assert_eq!(index.len(), 4);
+    }
+
+    #[test]
+    fn test_index_open_with_options() {
+        let (_temp_dir, dir) = setup_test_recipes();
+        let cache = dir.join("index.json");
+        let options = WalkOptions {
+            exclude: vec!["breakfast/".to_string()],
+            ..Default::default()
+        };
+
+        // Rebuilt without a cache, and from a cache built with other options
+        let index = SearchIndex::open_with_options(&dir, &cache, &options).unwrap();
+        assert_eq!(index.len(), 3);
+        SearchIndex::build(&dir).unwrap().save(&cache).unwrap();
+        let index = SearchIndex::open_with_options(&dir, &cache, &options).unwrap();
+        assert_eq!(index.len(), 3);
+
+        // The cache is used and keeps its options when they match
+        index.save(&cache).unwrap();
+        create_test_recipe(&dir, "breakfast/toast", "Toast with @honey");
+        let index = SearchIndex::open_with_options(&dir, &cache, &options).unwrap();
+        assert_eq!(index.len(), 3);
+        assert!(index.search("honey").unwrap().is_empty());
+        let index = SearchIndex::open(&dir, &cache).unwrap();
+        assert_eq!(index.len(), 3);
+    }
+}
@@ -3,15 +3,42 @@
 //! This module provides full-text search capabilities for recipe files,
 //! supporting both filename and content matching with relevance scoring.
 
-use crate::model::{RecipeEntry, RecipeEntryError};
+use crate::diagnostics::{Partial, SkippedFile};
+use crate::model::{parse_frontmatter, Metadata, RecipeEntry, RecipeEntryError};
+use crate::normalize::{fold, nfc, FoldedText};
+use crate::tree::InheritedMetadata;
+use crate::walk::{recipe_files, WalkOptions};
 use camino::{Utf8Path, Utf8PathBuf};
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use query::QueryTarget;
+use rank::{CorpusStats, Field, FieldLengths, FieldTexts, TermFrequencies, FIELD_COUNT};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::sync::Arc;
+use text::{find_fuzzy, merge_ranges, within_edit_distance, words};
 use thiserror::Error;
+use tokens::{cookware_names, ingredient_matches, ingredient_names, total_timer_minutes};
 
+mod index;
 mod model;
+mod options;
+mod query;
+mod rank;
+mod stemming;
+mod synonyms;
+mod text;
+mod tokens;
 
-pub use model::SearchResult;
+pub use index::{RefreshSummary, SearchIndex};
+pub use model::{FieldMatch, MatchField, SearchHit, SearchResult, SearchResults, Snippet};
+pub use options::{FieldWeights, Fuzziness, SearchOptions};
+pub use query::{Clause, Comparison, FieldFilter, IntoQuery, Query, QueryError, TimerFilter};
+pub use stemming::{Language, Stemmer};
+pub use synonyms::{Synonyms, SYNONYMS_FILE};
 
 /// Errors that can occur during recipe searching.
 #[derive(Error, Debug)]
@@ -27,21 +54,35 @@ pub enum SearchError {
 
     #[error("Failed to read file: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Failed to (de)serialize search index: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Invalid search index: {0}")]
+    IndexError(String),
+
+    #[error("Invalid search query: {0}")]
+    QueryError(#[from] QueryError),
+
+    #[error("Invalid synonyms file: {0}")]
+    SynonymsError(String),
 }
 
-/// Searches for recipes in a directory tree that match a query string.
+/// Searches for recipes in a directory tree that match a query.
 ///
 /// This function performs a comprehensive search through all .cook and .menu files
-/// in the specified directory and its subdirectories. The search algorithm:
+/// in the specified directory and its subdirectories, leaving out those
+/// excluded by `.cookignore` files (see [`crate::walk`]). The search algorithm:
 ///
-/// 1. Searches for exact and partial filename matches (highest priority)
-/// 2. Searches for query terms within file contents
-/// 3. Scores and ranks results by relevance
+/// 1. Drops recipes that fail the query's filters and negations
+/// 2. Counts the query terms in the file stem, title, tags, other metadata and body
+/// 3. Scores each field with BM25, taking term rarity and field length into account
+/// 4. Ranks results by the weighted sum of the field scores, see [`FieldWeights`]
 ///
 /// # Arguments
 ///
 /// * `base_dir` - The root directory to search in
-/// * `query` - The search query (can contain multiple terms separated by spaces)
+/// * `query` - The search query, either a string in the [`Query`] syntax or a parsed `Query`
 ///
 /// # Returns
 ///
@@ -59,134 +100,903 @@ pub enum SearchError {
 ///
 /// // Search with multiple terms
 /// let results = search(Utf8Path::new("./recipes"), "chocolate cake")?;
+///
+/// // Search with filters, negations and phrases
+/// let results = search(Utf8Path::new("./recipes"), r#"tag:vegan -mushroom "sheet pan""#)?;
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
-pub fn search(base_dir: &Utf8Path, query: &str) -> Result<Vec<RecipeEntry>, SearchError> {
-    let paths = search_paths(base_dir, query)?;
-    let mut recipes = Vec::new();
-
-    for path in paths {
-        match RecipeEntry::from_path(path) {
-            Ok(recipe) => recipes.push(recipe),
-            Err(e) => return Err(SearchError::RecipeEntryError(e)),
+pub fn search(base_dir: &Utf8Path, query: impl IntoQuery) -> Result<Vec<RecipeEntry>, SearchError> {
+    search_with_options(base_dir, query, &SearchOptions::default())
+}
+
+/// Searches for recipes like [`search`], with options controlling matching
+/// and pagination.
+///
+/// See [`SearchOptions`] for the available settings. Recipe files that
+/// cannot be read are left out; use [`search_with_diagnostics`] to find
+/// out which.
+pub fn search_with_options(
+    base_dir: &Utf8Path,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<Vec<RecipeEntry>, SearchError> {
+    Ok(search_with_diagnostics(base_dir, query, options)?.into_value())
+}
+
+/// Searches for recipes like [`search_with_options`] and reports the
+/// recipe files that were skipped because they could not be read.
+///
+/// Errors are only returned for problems with the query or the search
+/// itself, never for individual files.
+pub fn search_with_diagnostics(
+    base_dir: &Utf8Path,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<Partial<Vec<RecipeEntry>>, SearchError> {
+    Ok(search_iter(base_dir, query, options)?.load_all())
+}
+
+/// Searches for recipes like [`search_with_options`], loading the ranked
+/// recipes lazily.
+///
+/// Only the recipes the returned iterator actually yields are loaded, so
+/// taking the first few results of a large result set is cheap.
+/// [`SearchResults::total`] reports the number of matches before `offset`
+/// and `limit` were applied.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::{search_iter, SearchOptions};
+/// use camino::Utf8Path;
+///
+/// let results = search_iter(Utf8Path::new("./recipes"), "soup", &SearchOptions::default())?;
+/// println!("{} matches", results.total());
+/// for recipe in results.take(20) {
+///     println!("{:?}", recipe?.name());
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn search_iter(
+    base_dir: &Utf8Path,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<SearchResults, SearchError> {
+    let scorer = Scorer::new(query.into_query()?, options, &[base_dir])?;
+    let mut skipped = Vec::new();
+    let results = search_results(base_dir, &scorer, &options.walk, &mut skipped);
+    Ok(SearchResults::new(
+        results.len(),
+        options.paginate(results).collect(),
+        skipped,
+        scorer.into_inherited(),
+    ))
+}
+
+/// Searches for recipes like [`search`] and explains why each one matched.
+///
+/// Each [`SearchHit`] carries the relevance score, the recipe fields in which
+/// query terms were found, and the body lines containing them with
+/// highlight ranges for every term.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::search_detailed;
+/// use camino::Utf8Path;
+///
+/// for hit in search_detailed(Utf8Path::new("./recipes"), "maple syrup")? {
+///     println!("{:?} ({:.1})", hit.recipe.name(), hit.score);
+///     for snippet in &hit.snippets {
+///         println!("  {}: {}", snippet.line, snippet.text);
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn search_detailed(
+    base_dir: &Utf8Path,
+    query: impl IntoQuery,
+) -> Result<Vec<SearchHit>, SearchError> {
+    search_detailed_with_options(base_dir, query, &SearchOptions::default())
+}
+
+/// Searches for recipes like [`search_detailed`], with options controlling matching.
+pub fn search_detailed_with_options(
+    base_dir: &Utf8Path,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<Vec<SearchHit>, SearchError> {
+    Ok(search_detailed_with_diagnostics(base_dir, query, options)?.into_value())
+}
+
+/// Searches for recipes like [`search_detailed_with_options`] and reports
+/// the recipe files that were skipped because they could not be read.
+pub fn search_detailed_with_diagnostics(
+    base_dir: &Utf8Path,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<Partial<Vec<SearchHit>>, SearchError> {
+    let scorer = Scorer::new(query.into_query()?, options, &[base_dir])?;
+    let mut skipped = Vec::new();
+    let results = search_results(base_dir, &scorer, &options.walk, &mut skipped);
+    let hits = options
+        .paginate(results)
+        .filter_map(|result| load_hit(&scorer, base_dir, result, &mut skipped))
+        .collect();
+    Ok(Partial::new(hits, skipped))
+}
+
+/// Searches several base directories at once and merges their results.
+///
+/// Directories are given in order of precedence, like for
+/// [`get_recipe`](crate::get_recipe): when recipe files at the same path
+/// relative to their base directory exist in more than one directory,
+/// only the one from the first directory is searched, so a personal
+/// recipe shadows a shared one with the same name. Each hit reports the
+/// base directory it came from in [`SearchHit::root`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::search_roots;
+///
+/// let roots = ["./personal", "./family", "./team"];
+/// for hit in search_roots(roots, "lasagna")? {
+///     println!("{:?} from {}", hit.recipe.name(), hit.root);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn search_roots<P: AsRef<Utf8Path>>(
+    base_dirs: impl IntoIterator<Item = P>,
+    query: impl IntoQuery,
+) -> Result<Vec<SearchHit>, SearchError> {
+    search_roots_with_options(base_dirs, query, &SearchOptions::default())
+}
+
+/// Searches several base directories like [`search_roots`], with options
+/// controlling matching.
+pub fn search_roots_with_options<P: AsRef<Utf8Path>>(
+    base_dirs: impl IntoIterator<Item = P>,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<Vec<SearchHit>, SearchError> {
+    Ok(search_roots_with_diagnostics(base_dirs, query, options)?.into_value())
+}
+
+/// Searches several base directories like [`search_roots_with_options`]
+/// and reports the recipe files that were skipped because they could not
+/// be read.
+pub fn search_roots_with_diagnostics<P: AsRef<Utf8Path>>(
+    base_dirs: impl IntoIterator<Item = P>,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<Partial<Vec<SearchHit>>, SearchError> {
+    let base_dirs: Vec<P> = base_dirs.into_iter().collect();
+    let scorer = Scorer::new(query.into_query()?, options, &base_dirs)?;
+
+    // Paths relative to their base directory, in composed form, that an
+    // earlier directory already provides
+    let mut seen = HashSet::new();
+    let mut skipped = Vec::new();
+    let mut roots = HashMap::new();
+    let mut paths = vec![];
+    for (index, base_dir) in base_dirs.iter().enumerate() {
+        let base_dir = base_dir.as_ref();
+        for path in recipe_files(base_dir, &options.walk, &mut skipped) {
+            let relative = path.strip_prefix(base_dir).unwrap_or(&path);
+            if seen.insert(nfc(relative.as_str())) {
+                roots.entry(path.clone()).or_insert(index);
+                paths.push(path);
+            }
         }
     }
 
-    Ok(recipes)
+    // All directories are ranked as one collection
+    let mut scored_results: Vec<_> = score_paths(paths, &scorer, &mut skipped)
+        .into_iter()
+        .map(|result| (roots[&result.path], result))
+        .collect();
+
+    // Equal scores and names keep the directory order
+    scored_results.sort_unstable_by(|(a_index, a), (b_index, b)| {
+        compare_results(a, b)
+            .then_with(|| a_index.cmp(b_index))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    let hits = options
+        .paginate(scored_results)
+        .filter_map(|(index, result)| {
+            load_hit(&scorer, base_dirs[index].as_ref(), result, &mut skipped)
+        })
+        .collect();
+    Ok(Partial::new(hits, skipped))
 }
 
 /// Search for .cook and .menu files in a directory and return scored results
-fn search_paths(base_dir: &Utf8Path, query: &str) -> Result<Vec<Utf8PathBuf>, SearchError> {
-    let mut scored_results = vec![];
-    let query_lower = query.to_lowercase();
-    let terms: Vec<String> = query_lower.split_whitespace().map(String::from).collect();
-
-    // Search for both .cook and .menu files
-    let patterns = vec![
-        base_dir.join("**/*.cook").to_string(),
-        base_dir.join("**/*.menu").to_string(),
-    ];
-
-    for pattern in patterns {
-        for entry in glob::glob(&pattern)? {
-            let path = entry?;
-            let path = Utf8PathBuf::from_path_buf(path).map_err(|_| {
-                SearchError::IoError(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Path contains invalid UTF-8",
-                ))
-            })?;
-            let mut result = SearchResult::new(path);
+fn search_results(
+    base_dir: &Utf8Path,
+    scorer: &Scorer,
+    walk: &WalkOptions,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<SearchResult> {
+    let paths = recipe_files(base_dir, walk, skipped);
+    let mut scored_results = score_paths(paths, scorer, skipped);
 
-            // Score based on filename match (using full query)
-            let filename_score = score_filename_match(&result.path, &query_lower);
-            result.add_score(filename_score);
+    // Sort results by score
+    sort_results(&mut scored_results);
+    scored_results
+}
 
-            // Score based on content matches (using individual terms)
-            if let Ok(content_score) = score_content_matches(&result.path, &terms) {
-                result.add_score(content_score);
+/// Scores recipe files, keeping those that match the query
+///
+/// Files that cannot be read are recorded in `skipped`.
+fn score_paths(
+    paths: Vec<Utf8PathBuf>,
+    scorer: &Scorer,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<SearchResult> {
+    // Files are read and evaluated in parallel, keeping their order
+    let evaluated: Vec<_> = paths
+        .into_par_iter()
+        .map(|path| match fs::read_to_string(&path) {
+            Ok(content) => {
+                let document =
+                    Document::new(&path, Some(&content)).inheriting(scorer.inherited(&path));
+                Ok((scorer.evaluate(&document, true), path))
             }
+            Err(e) => Err(SkippedFile::new(&path, e)),
+        })
+        .collect();
 
-            // Include result if it has any score
-            if result.score > 0.0 {
-                scored_results.push(result);
+    // Relevance depends on statistics of all files, so score in a second pass
+    let mut corpus = scorer.corpus();
+    let mut candidates = Vec::new();
+    for evaluated in evaluated {
+        match evaluated {
+            Ok(((candidate, accepted), path)) => {
+                corpus.add(&candidate.frequencies);
+                // Include result if it passes the query's requirements
+                if accepted {
+                    candidates.push((path, candidate));
+                }
             }
+            Err(e) => skipped.push(e),
         }
     }
 
-    // Sort results by score
-    sort_results(&mut scored_results);
-    // Return only the paths in sorted order
-    Ok(scored_results.into_iter().map(|r| r.path).collect())
+    candidates
+        .into_iter()
+        .filter_map(|(path, candidate)| {
+            let score = scorer.score(&candidate, &corpus)?;
+            let mut result = SearchResult::new(path);
+            result.add_score(score);
+            Some(result)
+        })
+        .collect()
 }
 
-/// Calculate score for filename matches
-fn score_filename_match(path: &Utf8Path, query: &str) -> f64 {
-    let query = query.to_lowercase();
-    path.file_stem()
-        .map(|name| {
-            let name = name.to_lowercase();
-            if name == query {
-                20.0 // Highest score for exact match
-            } else if name.contains(&query) {
-                10.0 // High score for partial match
-            } else {
-                0.0
+/// Returns the recipe files among `paths` that match a query, as
+/// [`search_with_options`] would find them in `base_dir`, ignoring `offset`
+/// and `limit`.
+///
+/// Files that cannot be read don't match.
+pub(crate) fn matching_paths(
+    base_dir: &Utf8Path,
+    paths: Vec<Utf8PathBuf>,
+    query: impl IntoQuery,
+    options: &SearchOptions,
+) -> Result<HashSet<Utf8PathBuf>, SearchError> {
+    let scorer = Scorer::new(query.into_query()?, options, &[base_dir])?;
+    let results = score_paths(paths, &scorer, &mut Vec::new());
+    Ok(results.into_iter().map(|result| result.path).collect())
+}
+
+/// Builds the detailed hit for a ranked result, recording it in `skipped`
+/// if the recipe cannot be loaded
+fn load_hit(
+    scorer: &Scorer,
+    root: &Utf8Path,
+    result: SearchResult,
+    skipped: &mut Vec<SkippedFile>,
+) -> Option<SearchHit> {
+    let content = fs::read_to_string(&result.path).ok();
+    let document =
+        Document::new(&result.path, content.as_deref()).inheriting(scorer.inherited(&result.path));
+    match scorer.hit(root, &document, result.score) {
+        Ok(hit) => Some(hit),
+        Err(e) => {
+            skipped.push(SkippedFile::new(&result.path, e));
+            None
+        }
+    }
+}
+
+/// A recipe file prepared for scoring.
+///
+/// Folded content, frontmatter metadata, ranking fields, ingredients,
+/// cookware and timers are computed on first use, so queries that don't need them don't pay
+/// for them.
+pub(crate) struct Document<'a> {
+    path: &'a Utf8Path,
+    /// File content, `None` if the file could not be read as UTF-8
+    content: Option<&'a str>,
+    content_folded: OnceCell<Option<String>>,
+    metadata: OnceCell<Cow<'a, Metadata>>,
+    /// Metadata the file inherits from directory configs
+    inherited: Option<Arc<Metadata>>,
+    fields: OnceCell<FieldTexts>,
+    lengths: OnceCell<FieldLengths>,
+    ingredients: OnceCell<Vec<String>>,
+    cookware: OnceCell<Vec<String>>,
+    timer_minutes: OnceCell<Option<f64>>,
+}
+
+impl<'a> Document<'a> {
+    pub(crate) fn new(path: &'a Utf8Path, content: Option<&'a str>) -> Self {
+        Document {
+            path,
+            content,
+            content_folded: OnceCell::new(),
+            metadata: OnceCell::new(),
+            inherited: None,
+            fields: OnceCell::new(),
+            lengths: OnceCell::new(),
+            ingredients: OnceCell::new(),
+            cookware: OnceCell::new(),
+            timer_minutes: OnceCell::new(),
+        }
+    }
+
+    /// Sets the metadata the file inherits from directory configs, which is
+    /// merged into its frontmatter.
+    pub(crate) fn inheriting(mut self, inherited: Option<Arc<Metadata>>) -> Self {
+        self.inherited = inherited;
+        self
+    }
+
+    /// Creates a document whose metadata and field lengths have already been computed.
+    pub(crate) fn indexed(
+        path: &'a Utf8Path,
+        content: Option<&'a str>,
+        metadata: &'a Metadata,
+        lengths: FieldLengths,
+    ) -> Self {
+        let document = Document::new(path, content);
+        let _ = document.metadata.set(Cow::Borrowed(metadata));
+        let _ = document.lengths.set(lengths);
+        document
+    }
+
+    /// Returns the content folded for comparison, see [`fold`].
+    fn content_folded(&self) -> Option<&str> {
+        self.content_folded
+            .get_or_init(|| self.content.map(fold))
+            .as_deref()
+    }
+
+    fn stem(&self) -> &str {
+        self.path.file_stem().unwrap_or("")
+    }
+
+    /// Returns the folded text of the fields used for ranking.
+    fn fields(&self) -> &FieldTexts {
+        self.fields
+            .get_or_init(|| FieldTexts::new(self.stem(), self.metadata(), self.body()))
+    }
+
+    /// Returns the recipe body below the frontmatter.
+    fn body(&self) -> &str {
+        self.content.map_or("", |c| frontmatter_split(c).1)
+    }
+
+    /// Returns the number of words in each ranking field.
+    pub(crate) fn lengths(&self) -> FieldLengths {
+        *self.lengths.get_or_init(|| self.fields().lengths())
+    }
+}
+
+impl QueryTarget for Document<'_> {
+    fn metadata(&self) -> &Metadata {
+        self.metadata.get_or_init(|| {
+            let mut metadata = self.content.map(parse_frontmatter).unwrap_or_default();
+            if let Some(inherited) = &self.inherited {
+                metadata.inherit(inherited);
             }
+            Cow::Owned(metadata)
         })
-        .unwrap_or(0.0)
-}
+    }
 
-/// Calculate score for content matches
-fn score_content_matches(path: &Utf8Path, terms: &[String]) -> io::Result<f64> {
-    let matches = count_matches(path, terms)?;
-    if matches > 0 {
-        // Base score for having any match
-        let mut score = 1.0;
-        // Additional score for multiple matches (capped)
-        score += f64::min(0.1 * matches as f64, 5.0);
-        Ok(score)
-    } else {
-        Ok(0.0)
+    /// Checks whether a term occurs in the file stem or content, ignoring
+    /// case and diacritics.
+    fn contains(&self, term: &str) -> bool {
+        let term = fold(term);
+        let in_stem = fold(self.stem()).contains(&term);
+        in_stem
+            || self
+                .content_folded()
+                .is_some_and(|c| c.contains(term.as_str()))
     }
+
+    fn ingredients(&self) -> &[String] {
+        self.ingredients
+            .get_or_init(|| ingredient_names(self.body()))
+    }
+
+    fn cookware(&self) -> &[String] {
+        self.cookware.get_or_init(|| cookware_names(self.body()))
+    }
+
+    fn total_timer_minutes(&self) -> Option<f64> {
+        *self
+            .timer_minutes
+            .get_or_init(|| total_timer_minutes(self.body()))
+    }
+}
+
+/// A document that passed the query's filters, waiting to be scored once
+/// the statistics of all documents are known.
+pub(crate) struct Candidate {
+    frequencies: TermFrequencies,
+    on_hand: f64,
+}
+
+/// A query prepared for scoring many documents.
+pub(crate) struct Scorer {
+    query: Query,
+    /// Folded terms and phrases, matched against every ranking field
+    terms: Vec<String>,
+    /// Per term, the term followed by its synonyms
+    variants: Vec<Vec<String>>,
+    /// Per term, the stems of its single-word variants
+    stems: Vec<Vec<String>>,
+    /// Folded ingredients the query asks for
+    ingredients: Vec<String>,
+    /// Folded cookware the query asks for
+    cookware: Vec<String>,
+    /// Folded ingredients the user has on hand
+    on_hand: Vec<String>,
+    fuzziness: Fuzziness,
+    weights: FieldWeights,
+    stemmer: Option<Arc<dyn Stemmer>>,
+    /// Metadata recipe files inherit from directory configs
+    inherited: InheritedMetadata,
 }
 
-/// Count how many times the terms appear in the file
-fn count_matches(path: &Utf8Path, terms: &[String]) -> io::Result<usize> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut total_matches = 0;
+impl Scorer {
+    /// Prepares a query for searching recipe directories.
+    ///
+    /// Unless the options provide synonyms, the synonym files of `base_dirs`
+    /// are loaded. Directory configs below `base_dirs` are read as needed.
+    pub(crate) fn new<P: AsRef<Utf8Path>>(
+        query: Query,
+        options: &SearchOptions,
+        base_dirs: &[P],
+    ) -> Result<Self, SearchError> {
+        let loaded;
+        let synonyms = match &options.synonyms {
+            Some(synonyms) => synonyms,
+            None => {
+                loaded = Synonyms::for_dirs(base_dirs)?;
+                &loaded
+            }
+        };
+        let stemmer = options.stemmer.clone();
 
-    for line in reader.lines() {
-        let line = line?.to_lowercase();
-        total_matches += terms
+        let terms: Vec<String> = query.terms().iter().map(|term| fold(term)).collect();
+        let variants: Vec<Vec<String>> = terms
+            .iter()
+            .map(|term| synonyms.expand_with(term, stemmer.as_deref()))
+            .collect();
+        let stems = variants
             .iter()
-            .map(|term| line.matches(term).count())
-            .sum::<usize>();
+            .map(|variants| match &stemmer {
+                Some(stemmer) => variants
+                    .iter()
+                    .filter(|variant| is_word(variant))
+                    .map(|variant| stemmer.stem(variant).into_owned())
+                    .collect(),
+                None => Vec::new(),
+            })
+            .collect();
+
+        Ok(Scorer {
+            terms,
+            variants,
+            stems,
+            ingredients: query.ingredients().iter().map(|name| fold(name)).collect(),
+            cookware: query.cookware().iter().map(|name| fold(name)).collect(),
+            on_hand: options
+                .on_hand
+                .iter()
+                .map(|name| fold(name.trim()))
+                .collect(),
+            query,
+            fuzziness: options.fuzziness,
+            weights: options.weights,
+            stemmer,
+            inherited: InheritedMetadata::new(base_dirs),
+        })
+    }
+
+    /// Returns the metadata a recipe file inherits from directory configs.
+    pub(crate) fn inherited(&self, path: &Utf8Path) -> Option<Arc<Metadata>> {
+        self.inherited.for_file(path)
+    }
+
+    /// Returns the directory configs read while scoring, to load the
+    /// ranked recipes with.
+    pub(crate) fn into_inherited(self) -> InheritedMetadata {
+        self.inherited
+    }
+
+    /// Returns all terms with their synonyms.
+    pub(crate) fn variants(&self) -> Vec<String> {
+        self.variants.concat()
+    }
+
+    /// Returns true if documents can match terms they don't literally
+    /// contain, other than through synonyms.
+    pub(crate) fn matches_inexactly(&self) -> bool {
+        self.fuzziness.is_enabled() || self.stemmer.is_some()
+    }
+
+    /// Returns empty statistics for the documents searched with this query.
+    pub(crate) fn corpus(&self) -> CorpusStats {
+        CorpusStats::new(self.terms.len())
+    }
+
+    /// Evaluates a document for the query, returning it as a candidate
+    /// and whether it passes the query's filters.
+    ///
+    /// The search statistics are left alone so documents can be evaluated
+    /// in parallel; every evaluated document must be added to them with
+    /// [`CorpusStats::add`], whether it passes or not, before any
+    /// candidate is scored.
+    ///
+    /// With `scan_content` set to false only the file stem is searched for
+    /// terms; the content is still used for filters.
+    pub(crate) fn evaluate(&self, document: &Document, scan_content: bool) -> (Candidate, bool) {
+        let frequencies = self.frequencies(document, scan_content);
+        let accepted = self.query.accepts(document);
+        let on_hand = if accepted {
+            self.score_on_hand(document)
+        } else {
+            0.0
+        };
+        (
+            Candidate {
+                frequencies,
+                on_hand,
+            },
+            accepted,
+        )
+    }
+
+    /// Calculate the total score of a candidate.
+    ///
+    /// Returns `None` if the candidate does not match. Candidates match
+    /// when the query has no free text, or when they have a positive
+    /// relevance score.
+    pub(crate) fn score(&self, candidate: &Candidate, corpus: &CorpusStats) -> Option<f64> {
+        if self.terms.is_empty() {
+            return Some(candidate.on_hand);
+        }
+        let score = corpus.score(&candidate.frequencies, &self.weights);
+        (score > 0.0).then_some(score + candidate.on_hand)
+    }
+
+    /// Counts the occurrences of every term in every ranking field.
+    ///
+    /// Fuzzy occurrences count [`FUZZY_WEIGHT`] each, and only in fields
+    /// where the term does not occur otherwise.
+    fn frequencies(&self, document: &Document, scan_content: bool) -> TermFrequencies {
+        let stem_only;
+        let fields = if scan_content {
+            document.fields()
+        } else {
+            stem_only = FieldTexts::stem_only(document.stem());
+            &stem_only
+        };
+
+        let mut counts = vec![[0.0; FIELD_COUNT]; self.terms.len()];
+        for field in Field::ALL {
+            let text = fields.get(field);
+            let words = self.stemmed_words(text);
+            for (index, counts) in counts.iter_mut().enumerate() {
+                let (exact, fuzzy) = self.occurrences(index, text, words.as_deref());
+                counts[field as usize] = if exact > 0 {
+                    exact as f64
+                } else {
+                    FUZZY_WEIGHT * fuzzy as f64
+                };
+            }
+        }
+        TermFrequencies::new(counts, document.lengths())
+    }
+
+    /// Returns the words of a folded text with their stems, if stemming is enabled.
+    fn stemmed_words<'t>(&self, text: &'t str) -> Option<Vec<(&'t str, String)>> {
+        let stemmer = self.stemmer.as_ref()?;
+        Some(
+            words(text)
+                .map(|(_, word)| (word, stemmer.stem(word).into_owned()))
+                .collect(),
+        )
+    }
+
+    /// Counts the exact and the fuzzy occurrences of a term in a folded text.
+    ///
+    /// Occurrences of synonyms and, with stemming, of words with the same
+    /// stem count as exact. Fuzzy occurrences are only counted if there
+    /// are no exact ones.
+    fn occurrences(
+        &self,
+        index: usize,
+        text: &str,
+        words: Option<&[(&str, String)]>,
+    ) -> (usize, usize) {
+        let variants = &self.variants[index];
+        let exact = match words {
+            Some(words) => {
+                // Single words are compared word by word to include stem matches
+                let stems = &self.stems[index];
+                let word_matches = words
+                    .iter()
+                    .filter(|(word, stem)| {
+                        stems.contains(stem)
+                            || variants
+                                .iter()
+                                .any(|variant| is_word(variant) && word.contains(variant.as_str()))
+                    })
+                    .count();
+                let phrase_matches: usize = variants
+                    .iter()
+                    .filter(|variant| !is_word(variant))
+                    .map(|variant| text.matches(variant.as_str()).count())
+                    .sum();
+                word_matches + phrase_matches
+            }
+            None => variants
+                .iter()
+                .map(|variant| text.matches(variant.as_str()).count())
+                .sum(),
+        };
+        if exact > 0 {
+            return (exact, 0);
+        }
+        let fuzzy = variants
+            .iter()
+            .map(|variant| self.fuzzy_count(text, variant))
+            .sum();
+        (0, fuzzy)
+    }
+
+    /// Calculate score for the fraction of the recipe's ingredients on hand
+    fn score_on_hand(&self, document: &Document) -> f64 {
+        if self.on_hand.is_empty() {
+            return 0.0;
+        }
+        let ingredients = document.ingredients();
+        if ingredients.is_empty() {
+            return 0.0;
+        }
+
+        let available = ingredients
+            .iter()
+            .filter(|ingredient| {
+                self.on_hand
+                    .iter()
+                    .any(|item| ingredient_matches(ingredient, item))
+            })
+            .count();
+        ON_HAND_SCORE * available as f64 / ingredients.len() as f64
+    }
+
+    /// Counts the words of the folded text within the typo tolerance of a term.
+    fn fuzzy_count(&self, text: &str, term: &str) -> usize {
+        let max_edits = self.fuzziness.max_edits(term);
+        if max_edits == 0 || term.contains(char::is_whitespace) {
+            return 0;
+        }
+        words(text)
+            .filter(|(_, word)| within_edit_distance(word, term, max_edits))
+            .count()
+    }
+
+    /// Builds a detailed hit for a document that matched with `score`.
+    pub(crate) fn hit(
+        &self,
+        root: &Utf8Path,
+        document: &Document,
+        score: f64,
+    ) -> Result<SearchHit, RecipeEntryError> {
+        let recipe =
+            RecipeEntry::from_path(document.path.to_path_buf())?.inherit(document.metadata());
+        let snippets = match document.content {
+            Some(content) => build_snippets(content, &self.snippet_terms(), self.fuzziness),
+            None => Vec::new(),
+        };
+
+        Ok(SearchHit {
+            recipe,
+            root: root.to_path_buf(),
+            score,
+            matches: self.field_matches(document),
+            snippets,
+        })
+    }
+
+    /// Returns the terms, synonyms, stems, ingredients and cookware
+    /// highlighted in snippets.
+    fn snippet_terms(&self) -> Vec<String> {
+        let mut terms = self.variants();
+        terms.extend(self.stems.concat());
+        terms.extend(self.ingredients.iter().cloned());
+        terms.extend(self.cookware.iter().cloned());
+        terms
+    }
+
+    /// Lists which recipe fields contain which query terms.
+    fn field_matches(&self, document: &Document) -> Vec<FieldMatch> {
+        let fields = document.fields();
+
+        let mut matches = Vec::new();
+        for (index, term) in self.terms.iter().enumerate() {
+            for field in Field::ALL {
+                let text = fields.get(field);
+                let words = self.stemmed_words(text);
+                let fuzzy = match self.occurrences(index, text, words.as_deref()) {
+                    (0, 0) => continue,
+                    (0, _) => true,
+                    _ => false,
+                };
+                matches.push(FieldMatch {
+                    field: field.match_field(),
+                    term: term.clone(),
+                    fuzzy,
+                });
+            }
+        }
+
+        let tokens = [
+            (
+                &self.ingredients,
+                document.ingredients(),
+                MatchField::Ingredient,
+            ),
+            (&self.cookware, document.cookware(), MatchField::Cookware),
+        ];
+        for (names, used, field) in tokens {
+            for name in names {
+                if used.iter().any(|item| ingredient_matches(item, name)) {
+                    matches.push(FieldMatch {
+                        field,
+                        term: name.clone(),
+                        fuzzy: false,
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Checks whether a folded term is a single word, as opposed to a phrase
+/// or a term with punctuation.
+fn is_word(term: &str) -> bool {
+    !term.is_empty() && term.chars().all(char::is_alphanumeric)
+}
+
+/// Splits content into its frontmatter block and body.
+///
+/// Returns the number of lines taken by the frontmatter (including the
+/// `---` markers) and the body text. Content without a closed frontmatter
+/// block is returned as body.
+fn frontmatter_split(content: &str) -> (usize, &str) {
+    let mut lines = content.split_inclusive('\n');
+    if lines.next().map(str::trim) != Some("---") {
+        return (0, content);
     }
 
-    Ok(total_matches)
+    let mut offset = content.find('\n').map_or(content.len(), |i| i + 1);
+    for (index, line) in lines.enumerate() {
+        offset += line.len();
+        if line.trim() == "---" {
+            return (index + 2, &content[offset..]);
+        }
+    }
+    (0, content)
 }
 
+/// Maximum number of snippets returned per hit
+const MAX_SNIPPETS: usize = 5;
+
+/// Maximum length in bytes of a snippet's text
+const MAX_SNIPPET_LEN: usize = 160;
+
+/// Bytes of context kept before the first match when shortening a long line
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Collect body lines containing any of the terms, with highlight ranges
+fn build_snippets(content: &str, terms: &[String], fuzziness: Fuzziness) -> Vec<Snippet> {
+    let (skipped_lines, body) = frontmatter_split(content);
+
+    body.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let folded = FoldedText::new(line);
+            let ranges: Vec<_> = terms
+                .iter()
+                .flat_map(|term| {
+                    let mut ranges = folded.find(term);
+                    let max_edits = fuzziness.max_edits(term);
+                    if max_edits > 0 && !term.contains(char::is_whitespace) {
+                        ranges.extend(find_fuzzy(&folded, term, max_edits));
+                    }
+                    ranges
+                })
+                .collect();
+            if ranges.is_empty() {
+                return None;
+            }
+            let (text, highlights) = shorten_line(line, merge_ranges(ranges));
+            Some(Snippet {
+                line: skipped_lines + index + 1,
+                text,
+                highlights,
+            })
+        })
+        .take(MAX_SNIPPETS)
+        .collect()
+}
+
+/// Shortens a long line to a window around its first highlight.
+fn shorten_line(line: &str, highlights: Vec<Range<usize>>) -> (String, Vec<Range<usize>>) {
+    if line.len() <= MAX_SNIPPET_LEN {
+        return (line.to_string(), highlights);
+    }
+
+    let first = highlights.first().map_or(0, |r| r.start);
+    let mut start = first.saturating_sub(SNIPPET_CONTEXT);
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = usize::min(start + MAX_SNIPPET_LEN, line.len());
+    while !line.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let highlights = highlights
+        .into_iter()
+        .filter(|r| r.start < end)
+        .map(|r| r.start - start..usize::min(r.end, end) - start)
+        .collect();
+    (line[start..end].to_string(), highlights)
+}
+
+/// Score of a recipe whose ingredients are all on hand
+const ON_HAND_SCORE: f64 = 10.0;
+
+/// Weight of a fuzzy occurrence of a term relative to an exact one
+const FUZZY_WEIGHT: f64 = 0.1;
+
 /// Sort search results by score in descending order
 fn sort_results(results: &mut [SearchResult]) {
-    results.sort_unstable_by(|a, b| {
-        // First sort by score (highest first)
-        let score_cmp = b
-            .score
-            .partial_cmp(&a.score)
-            .unwrap_or(std::cmp::Ordering::Equal);
-
-        if score_cmp != std::cmp::Ordering::Equal {
-            return score_cmp;
-        }
+    results.sort_unstable_by(|a, b| compare_results(a, b).then_with(|| a.path.cmp(&b.path)));
+}
 
-        // If scores are equal, sort by filename
-        let a_name = a.path.file_stem().unwrap_or("").to_lowercase();
-        let b_name = b.path.file_stem().unwrap_or("").to_lowercase();
+/// Orders results by score (highest first), then by filename
+fn compare_results(a: &SearchResult, b: &SearchResult) -> Ordering {
+    let score_cmp = b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal);
 
-        a_name.cmp(&b_name)
-    });
+    if score_cmp != Ordering::Equal {
+        return score_cmp;
+    }
+
+    let a_name = fold(a.path.file_stem().unwrap_or(""));
+    let b_name = fold(b.path.file_stem().unwrap_or(""));
+    a_name.cmp(&b_name)
 }
 
 #[cfg(test)]
@@ -317,4 +1127,718 @@ mod tests {
         assert!(result.is_ok()); // Search should succeed but return empty results
         assert!(result.unwrap().is_empty());
     }
+
+    fn setup_tagged_recipes() -> (TempDir, Utf8PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+
+        create_test_recipe(
+            &temp_dir_path,
+            "tray_bake",
+            "---\ntags: [vegan, dinner]\nservings: 4\n---\nRoast @potatoes on a sheet pan",
+        );
+        create_test_recipe(
+            &temp_dir_path,
+            "mushroom_bake",
+            "---\ntags: [vegan]\nservings: 2\n---\nRoast @mushroom on a sheet pan",
+        );
+        create_test_recipe(
+            &temp_dir_path,
+            "steak",
+            "---\ntags: [dinner]\nservings: 2\n---\nSear @steak in a pan",
+        );
+
+        (temp_dir, temp_dir_path)
+    }
+
+    fn names(results: &[RecipeEntry]) -> Vec<String> {
+        results
+            .iter()
+            .map(|r| r.name().as_ref().unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_structured_query() {
+        let (_temp_dir, dir) = setup_tagged_recipes();
+
+        let results = search(&dir, r#"tag:vegan -mushroom "sheet pan""#).unwrap();
+        assert_eq!(names(&results), vec!["tray_bake"]);
+
+        let results = search(&dir, "tag:dinner servings:<3").unwrap();
+        assert_eq!(names(&results), vec!["steak"]);
+
+        let results = search(&dir, "tag:vegan pan").unwrap();
+        assert_eq!(names(&results), vec!["mushroom_bake", "tray_bake"]);
+    }
+
+    #[test]
+    fn test_search_filters_only() {
+        let (_temp_dir, dir) = setup_tagged_recipes();
+
+        // Without free text, every recipe passing the filters is returned
+        let results = search(&dir, "tag:vegan OR servings:>3").unwrap();
+        assert_eq!(names(&results), vec!["mushroom_bake", "tray_bake"]);
+    }
+
+    #[test]
+    fn test_search_with_parsed_query() {
+        let (_temp_dir, dir) = setup_tagged_recipes();
+        let query = Query::parse("-tag:vegan").unwrap();
+
+        let results = search(&dir, &query).unwrap();
+        assert_eq!(names(&results), vec!["steak"]);
+    }
+
+    #[test]
+    fn test_search_invalid_query() {
+        let (_temp_dir, dir) = setup_tagged_recipes();
+        let result = search(&dir, "(soup");
+        assert!(matches!(result, Err(SearchError::QueryError(_))));
+    }
+
+    #[test]
+    fn test_search_detailed() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(
+            &dir,
+            "syrup_pancakes",
+            "---\ntitle: Maple Pancakes\ntags: [breakfast, syrup]\n---\nMix the batter.\nPour @Maple syrup{} over the pancakes.",
+        );
+
+        let hits = search_detailed(&dir, "maple syrup").unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let hit = &hits[0];
+        assert_eq!(hit.recipe.name().as_ref().unwrap(), "Maple Pancakes");
+        assert!(hit.score > 0.0);
+
+        let fields: Vec<(MatchField, &str)> = hit
+            .matches
+            .iter()
+            .map(|m| (m.field, m.term.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (MatchField::Title, "maple"),
+                (MatchField::Body, "maple"),
+                (MatchField::FileStem, "syrup"),
+                (MatchField::Tag, "syrup"),
+                (MatchField::Body, "syrup"),
+            ]
+        );
+
+        assert_eq!(hit.snippets.len(), 1);
+        let snippet = &hit.snippets[0];
+        assert_eq!(snippet.line, 6);
+        assert_eq!(snippet.text, "Pour @Maple syrup{} over the pancakes.");
+        let highlighted: Vec<&str> = snippet
+            .highlights
+            .iter()
+            .map(|r| &snippet.text[r.clone()])
+            .collect();
+        assert_eq!(highlighted, vec!["Maple", "syrup"]);
+    }
+
+    #[test]
+    fn test_snippets_shorten_long_lines() {
+        let line = format!("{} needle {}", "a".repeat(200), "b".repeat(200));
+        let snippets = build_snippets(&line, &["needle".to_string()], Fuzziness::Off);
+
+        assert_eq!(snippets.len(), 1);
+        let snippet = &snippets[0];
+        assert!(snippet.text.len() <= MAX_SNIPPET_LEN);
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "needle");
+    }
+
+    #[test]
+    fn test_frontmatter_split() {
+        assert_eq!(frontmatter_split("---\na: 1\n---\nbody"), (3, "body"));
+        assert_eq!(frontmatter_split("no frontmatter"), (0, "no frontmatter"));
+        assert_eq!(frontmatter_split("---\nunclosed"), (0, "---\nunclosed"));
+    }
+
+    #[test]
+    fn test_relevance_ranking() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(
+            &dir,
+            "curry",
+            "---\ntitle: Chicken Curry\n---\nSimmer @chicken{500%g}",
+        );
+        create_test_recipe(
+            &dir,
+            "stock",
+            &format!("Make a stock. {}", "Add the chicken bones. ".repeat(30)),
+        );
+        create_test_recipe(&dir, "salad", "---\ntags: [chicken]\n---\nToss the leaves");
+        create_test_recipe(&dir, "bread", "Knead the dough");
+
+        // The title and tag matches beat a long body repeating the term
+        let results = search(&dir, "chicken").unwrap();
+        assert_eq!(names(&results), vec!["Chicken Curry", "salad", "stock"]);
+
+        // Weights shift the balance between fields
+        let options = SearchOptions {
+            weights: FieldWeights {
+                title: 0.0,
+                tags: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let results = search_with_options(&dir, "chicken", &options).unwrap();
+        assert_eq!(names(&results), vec!["stock", "Chicken Curry"]);
+    }
+
+    #[test]
+    fn test_rare_terms_rank_higher() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "a", "Add salt and saffron");
+        create_test_recipe(&dir, "b", "Add salt and pepper");
+        create_test_recipe(&dir, "c", "Add salt and oil");
+        create_test_recipe(&dir, "d", "Add saffron and oil");
+
+        // "saffron" is rarer than "salt", so it decides between b/c and d
+        let results = search(&dir, "salt saffron").unwrap();
+        assert_eq!(names(&results), vec!["a", "d", "b", "c"]);
+    }
+
+    #[test]
+    fn test_search_synonyms() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "moussaka", "Slice the @eggplant{2}");
+        create_test_recipe(&dir, "baba_ganoush", "Roast the @aubergines{2}");
+        create_test_recipe(&dir, "eggplant_parmesan", "Bread the slices");
+        create_test_recipe(&dir, "ratatouille", "Dice the @courgette{1}");
+
+        // Without a dictionary only literal matches are found
+        assert_eq!(
+            names(&search(&dir, "aubergine").unwrap()),
+            vec!["baba_ganoush"]
+        );
+
+        // The dictionary in the recipe directory is picked up, for file
+        // stems as well as content
+        fs::write(
+            dir.join(SYNONYMS_FILE),
+            "- [aubergine, eggplant]
+",
+        )
+        .unwrap();
+        let results = search(&dir, "aubergine").unwrap();
+        assert_eq!(
+            names(&results),
+            vec!["eggplant_parmesan", "baba_ganoush", "moussaka"]
+        );
+
+        // Options override the file
+        let options = SearchOptions {
+            synonyms: Some(Synonyms::new()),
+            ..Default::default()
+        };
+        let results = search_with_options(&dir, "aubergine", &options).unwrap();
+        assert_eq!(names(&results), vec!["baba_ganoush"]);
+
+        let hits = search_detailed(&dir, "aubergine").unwrap();
+        let moussaka = hits
+            .iter()
+            .find(|hit| hit.recipe.name().as_deref() == Some("moussaka"))
+            .unwrap();
+        assert_eq!(moussaka.matches[0].term, "aubergine");
+        let snippet = &moussaka.snippets[0];
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "eggplant");
+    }
+
+    #[test]
+    fn test_invalid_synonyms_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "soup", "A soup");
+        fs::write(dir.join(SYNONYMS_FILE), "just some text").unwrap();
+
+        assert!(matches!(
+            search(&dir, "soup"),
+            Err(SearchError::SynonymsError(_))
+        ));
+    }
+
+    #[test]
+    fn test_search_stemming() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "salsa", "Chop a @tomato{1} and an @onion");
+        create_test_recipe(&dir, "sauce", "Simmer the @tomatoes{4}");
+        create_test_recipe(&dir, "baked_beans", "Bake the beans");
+
+        assert_eq!(names(&search(&dir, "tomatoes").unwrap()), vec!["sauce"]);
+        assert!(search(&dir, "baking").unwrap().is_empty());
+
+        let options = SearchOptions {
+            stemmer: Some(Arc::new(Language::English)),
+            ..Default::default()
+        };
+        let results = search_with_options(&dir, "tomatoes", &options).unwrap();
+        assert_eq!(names(&results), vec!["sauce", "salsa"]);
+        let results = search_with_options(&dir, "baking", &options).unwrap();
+        assert_eq!(names(&results), vec!["baked_beans"]);
+
+        let hits = search_detailed_with_options(&dir, "tomatoes", &options).unwrap();
+        let salsa = &hits[1];
+        assert!(!salsa.matches[0].fuzzy);
+        let snippet = &salsa.snippets[0];
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "tomato");
+    }
+
+    #[test]
+    fn test_search_fuzzy() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "lasagna", "Layer the sheets");
+        create_test_recipe(&dir, "spaghetti", "Serve with ragu alla bolognese");
+        create_test_recipe(&dir, "lasagana_notes", "Notes");
+
+        // Without fuzziness only the literal match is found
+        let results = search(&dir, "lasagana").unwrap();
+        assert_eq!(names(&results), vec!["lasagana_notes"]);
+
+        let options = SearchOptions {
+            fuzziness: Fuzziness::Auto,
+            ..Default::default()
+        };
+        let results = search_with_options(&dir, "lasagana", &options).unwrap();
+        // Exact hits rank above fuzzy ones
+        assert_eq!(names(&results), vec!["lasagana_notes", "lasagna"]);
+
+        let results = search_with_options(&dir, "bolognaise", &options).unwrap();
+        assert_eq!(names(&results), vec!["spaghetti"]);
+
+        let strict = SearchOptions {
+            fuzziness: Fuzziness::MaxEdits(1),
+            ..Default::default()
+        };
+        assert!(search_with_options(&dir, "bolognaise", &strict)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_ranks_below_exact_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "tomato", "Many tomatos tomatos tomatos");
+        create_test_recipe(&dir, "salad", "One tomatoes");
+
+        let options = SearchOptions {
+            fuzziness: Fuzziness::Auto,
+            ..Default::default()
+        };
+        let results = search_with_options(&dir, "tomatoes", &options).unwrap();
+        assert_eq!(names(&results), vec!["salad", "tomato"]);
+    }
+
+    #[test]
+    fn test_search_detailed_fuzzy() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "pasta", "Cook the Lasagna sheets");
+
+        let options = SearchOptions {
+            fuzziness: Fuzziness::Auto,
+            ..Default::default()
+        };
+        let hits = search_detailed_with_options(&dir, "lasagana", &options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].matches,
+            vec![FieldMatch {
+                field: MatchField::Body,
+                term: "lasagana".to_string(),
+                fuzzy: true,
+            }]
+        );
+        let snippet = &hits[0].snippets[0];
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "Lasagna");
+    }
+
+    #[test]
+    fn test_search_ignores_accents_and_normalization() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+
+        // Decomposed (NFD) file name and content, as saved on macOS
+        create_test_recipe(
+            &dir,
+            "Cre\u{300}me Bru\u{302}le\u{301}e",
+            "---\ntags: [pâtisserie]\n---\nTorch the cre\u{300}me until golden",
+        );
+        create_test_recipe(&dir, "Flan", "Bake the custard");
+
+        for query in ["creme brulee", "Crème Brûlée", "CRÈME", "tag:patisserie"] {
+            let results = search(&dir, query).unwrap();
+            assert_eq!(results.len(), 1, "query {query:?}");
+            // The original spelling is preserved
+            assert_eq!(
+                results[0].name().as_deref(),
+                Some("Cre\u{300}me Bru\u{302}le\u{301}e")
+            );
+        }
+
+        let hits = search_detailed(&dir, "creme").unwrap();
+        let snippet = &hits[0].snippets[0];
+        let highlighted = &snippet.text[snippet.highlights[0].clone()];
+        assert_eq!(highlighted, "cre\u{300}me");
+    }
+
+    #[test]
+    fn test_search_roots() {
+        let personal_dir = TempDir::new().unwrap();
+        let family_dir = TempDir::new().unwrap();
+        let personal = Utf8PathBuf::from_path_buf(personal_dir.path().to_path_buf()).unwrap();
+        let family = Utf8PathBuf::from_path_buf(family_dir.path().to_path_buf()).unwrap();
+
+        create_test_recipe(&personal, "lasagna", "My lasagna with @spinach{}");
+        create_test_recipe(&family, "lasagna", "Grandma's lasagna with @beef{}");
+        create_test_recipe(&family, "lasagna soup", "Soup with lasagna sheets");
+        // Shadowed by the personal recipe of the same name, which doesn't mention beef
+        create_test_recipe(&family, "bolognese", "Brown the @beef{}");
+        create_test_recipe(&personal, "bolognese", "Lentil bolognese");
+
+        let hits = search_roots([&personal, &family], "lasagna").unwrap();
+        let found: Vec<_> = hits
+            .iter()
+            .map(|hit| (hit.recipe.name().clone().unwrap(), hit.root.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("lasagna".to_string(), personal.clone()),
+                ("lasagna soup".to_string(), family.clone()),
+            ]
+        );
+
+        let hits = search_roots([&personal, &family], "beef").unwrap();
+        assert!(hits.is_empty());
+
+        // The order of the directories decides which copy is used
+        let hits = search_roots([&family, &personal], "beef").unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.root == family));
+    }
+
+    fn setup_ingredient_recipes() -> (TempDir, Utf8PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(
+            &dir,
+            "leek gratin",
+            "Slice the @leeks{3} and cover with @heavy cream{200%ml} and @gruyere{100%g}.",
+        );
+        create_test_recipe(
+            &dir,
+            "leek soup",
+            "Sweat the @leeks{2} in @butter, add @stock{1%l} and a splash of @cream.",
+        );
+        // Mentions leeks and cream in prose only
+        create_test_recipe(
+            &dir,
+            "potato soup",
+            "Boil @potatoes{4}. Tastes great with leeks or a swirl of cream.",
+        );
+        (temp_dir, dir)
+    }
+
+    #[test]
+    fn test_search_ingredients() {
+        let (_temp_dir, dir) = setup_ingredient_recipes();
+
+        // All of
+        let results = search(&dir, "@leek @cream").unwrap();
+        assert_eq!(names(&results), vec!["leek gratin", "leek soup"]);
+        assert_eq!(
+            names(&search(&dir, Query::all_ingredients(["leeks", "gruyere"])).unwrap()),
+            vec!["leek gratin"]
+        );
+
+        // Any of
+        let results = search(&dir, Query::any_ingredients(["gruyere", "potato"])).unwrap();
+        assert_eq!(names(&results), vec!["leek gratin", "potato soup"]);
+
+        // Prose mentions are not ingredients
+        assert!(names(&search(&dir, "@cream -@leek").unwrap()).is_empty());
+
+        // Ingredients combine with free text
+        assert_eq!(
+            names(&search(&dir, "@leek soup").unwrap()),
+            vec!["leek soup"]
+        );
+
+        let hits = search_detailed(&dir, "@cream").unwrap();
+        assert!(hits[0].matches.contains(&FieldMatch {
+            field: MatchField::Ingredient,
+            term: "cream".to_string(),
+            fuzzy: false,
+        }));
+    }
+
+    #[test]
+    fn test_search_cookware_and_timers() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(
+            &dir,
+            "beef stew",
+            "Brown @beef{500%g} in a #Dutch Oven{}, then braise for ~{2%hours}.",
+        );
+        create_test_recipe(
+            &dir,
+            "omelette",
+            "Whisk @eggs{3} and cook in a #pan for ~{4%minutes}, flip, ~{1%min}.",
+        );
+        create_test_recipe(
+            &dir,
+            "salad",
+            "Toss @lettuce{} in a #bowl. No oven or timer needed.",
+        );
+
+        assert_eq!(
+            names(&search(&dir, "#\"dutch oven\"").unwrap()),
+            vec!["beef stew"]
+        );
+        // Prose mentions are not cookware
+        assert_eq!(names(&search(&dir, "#oven").unwrap()), vec!["beef stew"]);
+        assert_eq!(
+            names(&search(&dir, "timer:<30m").unwrap()),
+            vec!["omelette"]
+        );
+        assert_eq!(
+            names(&search(&dir, "timer:>=2h").unwrap()),
+            vec!["beef stew"]
+        );
+        assert_eq!(names(&search(&dir, "-timer:").unwrap()), vec!["salad"]);
+        assert_eq!(
+            names(&search(&dir, "timer:<1h OR #bowl").unwrap()),
+            vec!["omelette", "salad"]
+        );
+
+        let hits = search_detailed(&dir, "#pan").unwrap();
+        assert!(hits[0].matches.contains(&FieldMatch {
+            field: MatchField::Cookware,
+            term: "pan".to_string(),
+            fuzzy: false,
+        }));
+        assert_eq!(hits[0].snippets.len(), 1);
+    }
+
+    #[test]
+    fn test_search_honors_cookignore() {
+        let temp_dir = setup_test_recipes();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let versions = dir.join(".stversions");
+        fs::create_dir_all(&versions).unwrap();
+        create_test_recipe(&versions, "pancakes~20240101", "Old @maple syrup{}");
+        fs::write(dir.join(crate::COOKIGNORE_FILE), ".stversions/\n").unwrap();
+
+        let results = search(&dir, "syrup").unwrap();
+        assert_eq!(names(&results), vec!["waffles", "pancakes"]);
+
+        let options = SearchOptions {
+            walk: WalkOptions {
+                exclude: vec!["waffles.cook".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let results = search_with_options(&dir, "syrup", &options).unwrap();
+        assert_eq!(names(&results), vec!["pancakes"]);
+
+        let index = SearchIndex::build_with_options(&dir, &options.walk).unwrap();
+        assert_eq!(names(&index.search("syrup").unwrap()), vec!["pancakes"]);
+    }
+
+    #[test]
+    fn test_search_inherits_dir_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let baking = dir.join("baking");
+        let bread = baking.join("bread");
+        fs::create_dir_all(&bread).unwrap();
+        fs::write(
+            baking.join(crate::tree::DIR_CONFIG_FILE),
+            "tags: [oven]\nmetadata:\n  course: baked\n  difficulty: easy\n",
+        )
+        .unwrap();
+        fs::write(
+            bread.join(crate::tree::DIR_CONFIG_FILE),
+            "metadata:\n  difficulty: hard\n",
+        )
+        .unwrap();
+        create_test_recipe(
+            &bread,
+            "sourdough",
+            "---\ntags: [slow]\n---\nKnead @flour{}",
+        );
+        create_test_recipe(
+            &baking,
+            "cookies",
+            "---\ncourse: dessert\n---\nBake @flour{}",
+        );
+        create_test_recipe(&dir, "soup", "Boil @leeks{}");
+
+        let results = search(&dir, "tag:oven").unwrap();
+        assert_eq!(names(&results), vec!["cookies", "sourdough"]);
+        let results = search(&dir, "tag:slow tag:oven difficulty:hard").unwrap();
+        assert_eq!(names(&results), vec!["sourdough"]);
+        // Recipes keep the metadata they set themselves
+        let results = search(&dir, "course:baked").unwrap();
+        assert_eq!(names(&results), vec!["sourdough"]);
+
+        let hits = search_detailed(&dir, "sourdough").unwrap();
+        assert_eq!(hits[0].recipe.tags(), vec!["slow", "oven"]);
+
+        let index = SearchIndex::build(&dir).unwrap();
+        assert_eq!(
+            names(&index.search("difficulty:easy").unwrap()),
+            vec!["cookies"]
+        );
+        // Inherited values are found by plain terms as well, like by search()
+        for query in ["oven", "baked", "easy", "hard oven", "leeks"] {
+            assert_eq!(
+                names(&index.search(query).unwrap()),
+                names(&search(&dir, query).unwrap()),
+                "{query}"
+            );
+        }
+        assert_eq!(
+            names(&index.search("oven").unwrap()),
+            vec!["cookies", "sourdough"]
+        );
+
+        // Recipes are returned with the metadata they were matched by
+        let results = search(&dir, "tag:slow").unwrap();
+        assert_eq!(results[0].tags(), vec!["slow", "oven"]);
+        assert_eq!(
+            results[0].metadata().get("difficulty"),
+            Some(&serde_yaml::Value::from("hard"))
+        );
+        let mut results = search_iter(&dir, "cookies", &SearchOptions::default()).unwrap();
+        assert_eq!(results.next().unwrap().unwrap().tags(), vec!["oven"]);
+        let results = index.search("tag:slow").unwrap();
+        assert_eq!(results[0].tags(), vec!["slow", "oven"]);
+    }
+
+    #[test]
+    fn test_rank_by_ingredients_on_hand() {
+        let (_temp_dir, dir) = setup_ingredient_recipes();
+
+        let options = SearchOptions {
+            on_hand: vec![
+                "Leek".to_string(),
+                "butter".to_string(),
+                "stock".to_string(),
+            ],
+            ..Default::default()
+        };
+        // Soup: all 4 but cream on hand; gratin: 1 of 3; potato soup: none
+        let results = search_with_options(&dir, "", &options).unwrap();
+        assert_eq!(
+            names(&results),
+            vec!["leek soup", "leek gratin", "potato soup"]
+        );
+
+        let results = search_with_options(&dir, "@leek", &options).unwrap();
+        assert_eq!(names(&results), vec!["leek soup", "leek gratin"]);
+    }
+
+    #[test]
+    fn test_search_pagination() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        for (name, count) in [("a", 5), ("b", 4), ("c", 3), ("d", 2), ("e", 1)] {
+            create_test_recipe(&dir, name, &"soup ".repeat(count));
+        }
+
+        let page = |offset, limit| {
+            let options = SearchOptions {
+                offset,
+                limit,
+                ..Default::default()
+            };
+            names(&search_with_options(&dir, "soup", &options).unwrap())
+        };
+        assert_eq!(page(0, Some(2)), vec!["a", "b"]);
+        assert_eq!(page(2, Some(2)), vec!["c", "d"]);
+        assert_eq!(page(4, Some(2)), vec!["e"]);
+        assert!(page(5, Some(2)).is_empty());
+
+        let options = SearchOptions {
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        };
+        let hits = search_detailed_with_options(&dir, "soup", &options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].recipe.name().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_search_iter_loads_lazily() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "soup", "A soup");
+        let broken = create_test_recipe(&dir, "stale soup", "Another soup");
+
+        let mut results = search_iter(&dir, "soup", &SearchOptions::default()).unwrap();
+        assert_eq!(results.total(), 2);
+        assert_eq!(results.len(), 2);
+
+        // Recipes are only loaded when reached
+        fs::remove_file(&broken).unwrap();
+        let first = results.next().unwrap().unwrap();
+        assert_eq!(first.name().as_deref(), Some("soup"));
+        assert!(results.next().unwrap().is_err());
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn test_search_skips_unreadable_files() {
+        let temp_dir = setup_test_recipes();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let broken = dir.join("syrup_cake.cook");
+        fs::write(&broken, b"Pour @syrup\xff{}").unwrap();
+
+        let report = search_with_diagnostics(&dir, "syrup", &SearchOptions::default()).unwrap();
+        assert_eq!(report.value.len(), 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, broken);
+
+        let report =
+            search_detailed_with_diagnostics(&dir, "syrup", &SearchOptions::default()).unwrap();
+        assert_eq!(report.value.len(), 2);
+        assert_eq!(report.skipped.len(), 1);
+
+        // The plain variants return the partial results
+        assert_eq!(search(&dir, "syrup").unwrap().len(), 2);
+        assert_eq!(search_roots([&dir], "syrup").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_load_all_reports_failed_loads() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&dir, "soup", "A soup");
+        let broken = create_test_recipe(&dir, "stale soup", "Another soup");
+
+        let results = search_iter(&dir, "soup", &SearchOptions::default()).unwrap();
+        fs::remove_file(&broken).unwrap();
+        let report = results.load_all();
+        assert_eq!(report.value.len(), 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, broken);
+    }
 }
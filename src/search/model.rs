@@ -1,4 +1,10 @@
+use super::SearchError;
+use crate::diagnostics::{Partial, SkippedFile};
+use crate::model::{RecipeEntry, RecipeEntryError};
+use crate::tree::InheritedMetadata;
 use camino::Utf8PathBuf;
+use serde::Serialize;
+use std::ops::Range;
 
 /// Represents a search result with its relevance score.
 ///
@@ -21,3 +27,147 @@ impl SearchResult {
         self.score += points;
     }
 }
+
+/// Ranked search results whose recipes are loaded one at a time.
+///
+/// Returned by [`search_iter`](super::search_iter). Ranking needs every
+/// recipe to be scored up front, but a [`RecipeEntry`] is only loaded
+/// when the iterator reaches it, so showing the first page of a large
+/// result set only loads the recipes on that page.
+#[derive(Debug)]
+pub struct SearchResults {
+    total: usize,
+    results: std::vec::IntoIter<SearchResult>,
+    skipped: Vec<SkippedFile>,
+    /// Metadata the recipes inherit from directory configs
+    inherited: InheritedMetadata,
+}
+
+impl SearchResults {
+    pub(crate) fn new(
+        total: usize,
+        results: Vec<SearchResult>,
+        skipped: Vec<SkippedFile>,
+        inherited: InheritedMetadata,
+    ) -> Self {
+        SearchResults {
+            total,
+            results: results.into_iter(),
+            skipped,
+            inherited,
+        }
+    }
+
+    /// Returns the number of matching recipes, ignoring offset and limit.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the recipe files skipped while ranking because they could
+    /// not be read.
+    ///
+    /// Recipes that fail to load later are reported by the iterator itself.
+    pub fn skipped(&self) -> &[SkippedFile] {
+        &self.skipped
+    }
+
+    /// Loads all remaining recipes, skipping and reporting those that fail to load.
+    pub fn load_all(mut self) -> Partial<Vec<RecipeEntry>> {
+        let mut recipes = Vec::with_capacity(self.results.len());
+        for result in self.results.by_ref() {
+            match load_recipe(&result, &self.inherited) {
+                Ok(recipe) => recipes.push(recipe),
+                Err(e) => self.skipped.push(SkippedFile::new(&result.path, e)),
+            }
+        }
+        Partial::new(recipes, self.skipped)
+    }
+}
+
+impl Iterator for SearchResults {
+    type Item = Result<RecipeEntry, SearchError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.results.next()?;
+        Some(load_recipe(&result, &self.inherited).map_err(SearchError::RecipeEntryError))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.results.size_hint()
+    }
+}
+
+impl ExactSizeIterator for SearchResults {}
+
+/// Loads the recipe of a result with the metadata it inherits from
+/// directory configs, as it was matched.
+fn load_recipe(
+    result: &SearchResult,
+    inherited: &InheritedMetadata,
+) -> Result<RecipeEntry, RecipeEntryError> {
+    let recipe = RecipeEntry::from_path(result.path.clone())?;
+    Ok(match inherited.for_file(&result.path) {
+        Some(defaults) => recipe.inherit(&defaults),
+        None => recipe,
+    })
+}
+
+/// The part of a recipe in which a query term was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    /// The recipe's file name without extension
+    FileStem,
+    /// The `title` metadata field
+    Title,
+    /// One of the recipe's tags
+    Tag,
+    /// A frontmatter field other than `title` and the tags
+    Metadata,
+    /// The recipe body below the frontmatter
+    Body,
+    /// One of the recipe's Cooklang ingredients (`@name`)
+    Ingredient,
+    /// One of the recipe's Cooklang cookware items (`#name`)
+    Cookware,
+}
+
+/// A query term found in a specific part of a recipe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMatch {
+    /// Where the term was found
+    pub field: MatchField,
+    /// The query term or phrase, lowercase and without diacritics
+    pub term: String,
+    /// True if only a misspelled variant of the term was found
+    pub fuzzy: bool,
+}
+
+/// A line of a recipe body containing query terms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    /// One-indexed line number in the recipe file
+    pub line: usize,
+    /// Text of the line, shortened around the first match for long lines
+    pub text: String,
+    /// Byte ranges within `text` to highlight, sorted and non-overlapping
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// A search result with its score and an explanation of why it matched.
+///
+/// Returned by [`search_detailed`](super::search_detailed) and
+/// [`search_roots`](super::search_roots).
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// The matching recipe
+    pub recipe: RecipeEntry,
+    /// The base directory the recipe was found in
+    pub root: Utf8PathBuf,
+    /// Relevance score; higher is better
+    pub score: f64,
+    /// Query terms found per recipe field
+    pub matches: Vec<FieldMatch>,
+    /// Body lines containing query terms, in file order
+    pub snippets: Vec<Snippet>,
+}
@@ -0,0 +1,193 @@
+//! Options that tune how searches match and rank recipes.
+
+use super::{Stemmer, Synonyms};
+use crate::walk::WalkOptions;
+use std::sync::Arc;
+
+/// Typo tolerance for fuzzy matching.
+///
+/// Fuzzy matches compare query terms against file stems and the words of
+/// recipe content by edit distance. They count for less than exact matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fuzziness {
+    /// Only exact (substring) matches are found
+    #[default]
+    Off,
+    /// Allow edits depending on term length: none up to 3 characters,
+    /// one up to 7 characters and two for longer terms
+    Auto,
+    /// Allow up to this many edits for terms longer than the limit
+    MaxEdits(usize),
+}
+
+impl Fuzziness {
+    /// Returns the number of edits allowed for a term.
+    pub fn max_edits(&self, term: &str) -> usize {
+        let len = term.chars().count();
+        match *self {
+            Fuzziness::Off => 0,
+            Fuzziness::Auto => match len {
+                0..=3 => 0,
+                4..=7 => 1,
+                _ => 2,
+            },
+            Fuzziness::MaxEdits(max) if len > max => max,
+            Fuzziness::MaxEdits(_) => 0,
+        }
+    }
+
+    /// Returns true if fuzzy matching is enabled.
+    pub fn is_enabled(&self) -> bool {
+        *self != Fuzziness::Off && *self != Fuzziness::MaxEdits(0)
+    }
+}
+
+/// Relevance weights of the parts of a recipe.
+///
+/// Search results are ranked with BM25, scoring every field separately
+/// and adding up the field scores multiplied by these weights. With the
+/// default weights a term in the file name or title counts several times
+/// as much as the same term in the body. A weight of zero ignores a
+/// field for ranking; filters still apply to it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::{search_with_options, FieldWeights, SearchOptions};
+/// use camino::Utf8Path;
+///
+/// // Rank mostly by tags
+/// let options = SearchOptions {
+///     weights: FieldWeights {
+///         tags: 10.0,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let results = search_with_options(Utf8Path::new("./recipes"), "vegan", &options)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldWeights {
+    /// The recipe's file name without extension
+    pub file_stem: f64,
+    /// The `title` metadata field
+    pub title: f64,
+    /// The recipe's tags
+    pub tags: f64,
+    /// All other frontmatter fields
+    pub metadata: f64,
+    /// The recipe body below the frontmatter
+    pub body: f64,
+}
+
+impl Default for FieldWeights {
+    fn default() -> Self {
+        FieldWeights {
+            file_stem: 4.0,
+            title: 3.0,
+            tags: 2.0,
+            metadata: 1.0,
+            body: 1.0,
+        }
+    }
+}
+
+/// Options for [`search_with_options`](super::search_with_options) and
+/// [`search_detailed_with_options`](super::search_detailed_with_options).
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::{search_with_options, Fuzziness, SearchOptions};
+/// use camino::Utf8Path;
+///
+/// let options = SearchOptions {
+///     fuzziness: Fuzziness::Auto,
+///     ..Default::default()
+/// };
+/// // Finds "lasagna" despite the typo
+/// let results = search_with_options(Utf8Path::new("./recipes"), "lasagana", &options)?;
+///
+/// // Second page of 20 results
+/// let options = SearchOptions {
+///     offset: 20,
+///     limit: Some(20),
+///     ..Default::default()
+/// };
+/// let results = search_with_options(Utf8Path::new("./recipes"), "soup", &options)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Typo tolerance for file stem and content matches
+    pub fuzziness: Fuzziness,
+    /// How much a query term counts in each part of a recipe
+    pub weights: FieldWeights,
+    /// Stemmer that lets query terms match other forms of the same word
+    ///
+    /// `None` disables stemming.
+    pub stemmer: Option<Arc<dyn Stemmer>>,
+    /// Synonyms that query terms are expanded with
+    ///
+    /// `None` loads [`SYNONYMS_FILE`](super::SYNONYMS_FILE) from the recipe
+    /// directories, if present; pass an empty dictionary to disable synonyms.
+    pub synonyms: Option<Synonyms>,
+    /// Ingredients the user has on hand
+    ///
+    /// When set, recipes are ranked higher the larger the fraction of their
+    /// Cooklang ingredients found in this list. Names match like `@name`
+    /// query clauses, so "cream" covers "heavy cream".
+    pub on_hand: Vec<String>,
+    /// Number of ranked results to skip
+    pub offset: usize,
+    /// Maximum number of results to return; `None` returns all of them
+    pub limit: Option<usize>,
+    /// Files and directories left out of the search, in addition to those
+    /// listed in `.cookignore` files
+    ///
+    /// A [`SearchIndex`](super::SearchIndex) uses the options it was built
+    /// with instead.
+    pub walk: WalkOptions,
+}
+
+impl SearchOptions {
+    /// Returns the page of ranked items selected by `offset` and `limit`.
+    pub(crate) fn paginate<T>(&self, items: Vec<T>) -> impl Iterator<Item = T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_edits() {
+        assert_eq!(Fuzziness::Off.max_edits("lasagana"), 0);
+        assert_eq!(Fuzziness::Auto.max_edits("egg"), 0);
+        assert_eq!(Fuzziness::Auto.max_edits("leek"), 1);
+        assert_eq!(Fuzziness::Auto.max_edits("bolognaise"), 2);
+        assert_eq!(Fuzziness::MaxEdits(1).max_edits("a"), 0);
+        assert_eq!(Fuzziness::MaxEdits(1).max_edits("ab"), 1);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let page = |offset, limit| {
+            let options = SearchOptions {
+                offset,
+                limit,
+                ..Default::default()
+            };
+            options.paginate(vec![1, 2, 3, 4, 5]).collect::<Vec<_>>()
+        };
+        assert_eq!(page(0, None), vec![1, 2, 3, 4, 5]);
+        assert_eq!(page(1, Some(2)), vec![2, 3]);
+        assert_eq!(page(4, Some(2)), vec![5]);
+        assert!(page(10, None).is_empty());
+    }
+}
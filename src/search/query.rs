@@ -0,0 +1,1080 @@
+//! Structured search queries.
+//!
+//! A query string is parsed into a [`Query`] made of clauses:
+//!
+//! - `chocolate cake` - free text terms, matched against file names and content
+//! - `"sheet pan"` - a quoted phrase, matched as a whole
+//! - `tag:vegan`, `title:soup`, `servings:>=4`, `cuisine:italian` - metadata filters
+//! - `-mushroom`, `-tag:spicy` - negated clauses that must not match
+//! - `tag:vegan OR tag:vegetarian` - alternatives, at least one must match
+//! - `(soup OR stew) -tag:spicy` - parentheses group clauses
+//! - `@leek`, `@"heavy cream"` - ingredients the recipe must use
+//! - `#pan`, `#"dutch oven"` - cookware the recipe must use
+//! - `timer:<30m`, `timer:>=1h`, `timer:` - total duration of the recipe's timers
+//!
+//! Free text terms only affect ranking, like a plain search did before:
+//! a recipe matches if any of them is found. Filters, ingredients,
+//! cookware, timers, negations and groups are hard requirements.
+
+use super::tokens::{duration_unit_minutes, ingredient_matches};
+use crate::model::Metadata;
+use crate::normalize::fold;
+use serde_yaml::Value;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors that can occur when parsing a query string.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    #[error("Unterminated quote in query")]
+    UnterminatedQuote,
+
+    #[error("Unbalanced parentheses in query")]
+    UnbalancedParentheses,
+
+    #[error("Missing clause after '{0}'")]
+    MissingClause(String),
+
+    #[error("Empty field name in '{0}'")]
+    EmptyField(String),
+
+    #[error("Invalid duration '{0}', expected a number with an optional unit like 30m or 1.5h")]
+    InvalidDuration(String),
+}
+
+/// Comparison applied by a field filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `key:value` - case-insensitive containment for text, equality for numbers and tags
+    Matches,
+    /// `key:>value`
+    Greater,
+    /// `key:>=value`
+    GreaterOrEqual,
+    /// `key:<value`
+    Less,
+    /// `key:<=value`
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Matches => "",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+        }
+    }
+
+    fn compare(&self, actual: f64, expected: f64) -> bool {
+        match self {
+            Comparison::Matches => actual == expected,
+            Comparison::Greater => actual > expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+        }
+    }
+}
+
+/// A filter on a metadata field, such as `tag:vegan` or `servings:>2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldFilter {
+    /// Lowercase metadata key (`tag`/`tags`, `title`, `servings`, or any frontmatter key)
+    pub key: String,
+    /// How the value is compared
+    pub comparison: Comparison,
+    /// Expected value; empty to only require that the key is present
+    pub value: String,
+}
+
+impl FieldFilter {
+    /// Checks whether the filter holds for the given metadata.
+    ///
+    /// Text values are compared ignoring case and diacritics.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let needle = fold(&self.value);
+
+        match self.key.as_str() {
+            "tag" | "tags" => {
+                let tags = metadata.tags();
+                if needle.is_empty() {
+                    !tags.is_empty()
+                } else {
+                    tags.iter().any(|tag| fold(tag) == needle)
+                }
+            }
+            key => match metadata.get(key) {
+                Some(value) => needle.is_empty() || self.value_matches(value, &needle),
+                None => false,
+            },
+        }
+    }
+
+    fn value_matches(&self, value: &Value, needle: &str) -> bool {
+        match value {
+            Value::Sequence(items) => items.iter().any(|v| self.value_matches(v, needle)),
+            Value::Number(n) => match (n.as_f64(), needle.parse::<f64>()) {
+                (Some(actual), Ok(expected)) => self.comparison.compare(actual, expected),
+                _ => false,
+            },
+            Value::String(s) => match self.comparison {
+                Comparison::Matches => fold(s).contains(needle),
+                comparison => match (s.trim().parse::<f64>(), needle.parse::<f64>()) {
+                    (Ok(actual), Ok(expected)) => comparison.compare(actual, expected),
+                    _ => false,
+                },
+            },
+            Value::Bool(b) => self.comparison == Comparison::Matches && b.to_string() == needle,
+            Value::Tagged(tagged) => self.value_matches(&tagged.value, needle),
+            Value::Null | Value::Mapping(_) => false,
+        }
+    }
+}
+
+/// A filter on the total duration of a recipe's timers, such as `timer:<30m`.
+///
+/// Durations in queries are a number followed by an optional unit: `s`,
+/// `m` (the default), `h` or `d`, or their longer forms like `min` and
+/// `hours`. Recipes without timers never match a timer filter, so
+/// `-timer:` finds them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimerFilter {
+    /// How the total duration is compared
+    pub comparison: Comparison,
+    /// Expected total duration in minutes; `None` to only require a timer
+    pub minutes: Option<f64>,
+}
+
+impl TimerFilter {
+    /// Checks whether the filter holds for a recipe's total timer duration in minutes.
+    pub fn matches(&self, total_minutes: Option<f64>) -> bool {
+        match (total_minutes, self.minutes) {
+            (Some(actual), Some(expected)) => self.comparison.compare(actual, expected),
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// A single clause of a [`Query`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clause {
+    /// A single lowercase word
+    Term(String),
+    /// A lowercase quoted phrase
+    Phrase(String),
+    /// A metadata field filter
+    Field(FieldFilter),
+    /// A lowercase ingredient name that must be used as a Cooklang
+    /// ingredient (`@name`), not merely mentioned in the text
+    Ingredient(String),
+    /// A lowercase cookware name that must be used as Cooklang cookware (`#name`)
+    Cookware(String),
+    /// A filter on the total duration of the recipe's timers
+    Timer(TimerFilter),
+    /// A clause that must not match
+    Not(Box<Clause>),
+    /// Alternatives of which at least one must match
+    Any(Vec<Clause>),
+    /// A parenthesized group of clauses that must all match
+    All(Vec<Clause>),
+}
+
+/// A parsed search query.
+///
+/// Queries are usually created by parsing a string, either explicitly with
+/// [`Query::parse`] or implicitly by passing a `&str` to [`search`](super::search).
+///
+/// # Examples
+///
+/// ```
+/// use cooklang_find::search::Query;
+///
+/// let query = Query::parse(r#"tag:vegan -mushroom "sheet pan""#)?;
+/// assert_eq!(query.terms(), vec!["sheet pan"]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    clauses: Vec<Clause>,
+}
+
+impl Query {
+    /// Parses a query string.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError` if the query has unterminated quotes, unbalanced
+    /// parentheses, or a dangling `-` / `OR`.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(query)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let clauses = parser.parse_sequence()?;
+        if parser.pos < parser.tokens.len() {
+            // Only a closing parenthesis can stop a top-level sequence
+            return Err(QueryError::UnbalancedParentheses);
+        }
+        Ok(Query { clauses })
+    }
+
+    /// Creates a query from already parsed clauses.
+    pub fn from_clauses(clauses: Vec<Clause>) -> Self {
+        Query { clauses }
+    }
+
+    /// Creates a query for recipes using all of the given ingredients.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cooklang_find::search::Query;
+    ///
+    /// let query = Query::all_ingredients(["leeks", "heavy cream"]);
+    /// assert_eq!(query.to_string(), r#"@leeks @"heavy cream""#);
+    /// ```
+    pub fn all_ingredients(names: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Query {
+            clauses: names.into_iter().map(ingredient_clause).collect(),
+        }
+    }
+
+    /// Creates a query for recipes using at least one of the given ingredients.
+    pub fn any_ingredients(names: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let mut alternatives: Vec<Clause> = names.into_iter().map(ingredient_clause).collect();
+        let clauses = match alternatives.len() {
+            0 => Vec::new(),
+            1 => vec![alternatives.remove(0)],
+            _ => vec![Clause::Any(alternatives)],
+        };
+        Query { clauses }
+    }
+
+    /// Returns the top-level clauses of the query.
+    pub fn clauses(&self) -> &[Clause] {
+        &self.clauses
+    }
+
+    /// Returns true if the query has no clauses.
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Returns the free text terms and phrases used for ranking.
+    ///
+    /// These are all terms and phrases not inside a negation.
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        collect_terms(&self.clauses, &mut terms);
+        terms
+    }
+
+    /// Returns the ingredients the query asks for.
+    ///
+    /// These are all ingredient clauses not inside a negation.
+    pub fn ingredients(&self) -> Vec<String> {
+        fn collect(clauses: &[Clause], ingredients: &mut Vec<String>) {
+            for clause in clauses {
+                match clause {
+                    Clause::Ingredient(name) => ingredients.push(name.clone()),
+                    Clause::Any(inner) | Clause::All(inner) => collect(inner, ingredients),
+                    Clause::Term(_)
+                    | Clause::Phrase(_)
+                    | Clause::Field(_)
+                    | Clause::Cookware(_)
+                    | Clause::Timer(_)
+                    | Clause::Not(_) => {}
+                }
+            }
+        }
+        let mut ingredients = Vec::new();
+        collect(&self.clauses, &mut ingredients);
+        ingredients
+    }
+
+    /// Returns the cookware the query asks for.
+    ///
+    /// These are all cookware clauses not inside a negation.
+    pub fn cookware(&self) -> Vec<String> {
+        fn collect(clauses: &[Clause], cookware: &mut Vec<String>) {
+            for clause in clauses {
+                match clause {
+                    Clause::Cookware(name) => cookware.push(name.clone()),
+                    Clause::Any(inner) | Clause::All(inner) => collect(inner, cookware),
+                    Clause::Term(_)
+                    | Clause::Phrase(_)
+                    | Clause::Field(_)
+                    | Clause::Ingredient(_)
+                    | Clause::Timer(_)
+                    | Clause::Not(_) => {}
+                }
+            }
+        }
+        let mut cookware = Vec::new();
+        collect(&self.clauses, &mut cookware);
+        cookware
+    }
+
+    /// Returns the free text of the query, used to match file names.
+    pub fn text(&self) -> String {
+        self.terms().join(" ")
+    }
+
+    /// Returns true if the query contains any field filter, ingredient,
+    /// cookware or timer filter.
+    pub fn has_filters(&self) -> bool {
+        fn any_filter(clause: &Clause) -> bool {
+            match clause {
+                Clause::Field(_)
+                | Clause::Ingredient(_)
+                | Clause::Cookware(_)
+                | Clause::Timer(_) => true,
+                Clause::Term(_) | Clause::Phrase(_) => false,
+                Clause::Not(inner) => any_filter(inner),
+                Clause::Any(clauses) | Clause::All(clauses) => clauses.iter().any(any_filter),
+            }
+        }
+        self.clauses.iter().any(any_filter)
+    }
+
+    /// Checks the hard requirements of the query against a recipe.
+    ///
+    /// Top-level terms and phrases are not requirements; they only contribute
+    /// to ranking.
+    pub(crate) fn accepts(&self, recipe: &dyn QueryTarget) -> bool {
+        self.clauses.iter().all(|clause| match clause {
+            Clause::Term(_) | Clause::Phrase(_) => true,
+            clause => clause_matches(clause, recipe),
+        })
+    }
+}
+
+/// The parts of a recipe that query clauses are checked against.
+pub(crate) trait QueryTarget {
+    /// Frontmatter metadata, used by field filters
+    fn metadata(&self) -> &Metadata;
+
+    /// Checks whether a lowercase term occurs in the file name or content.
+    fn contains(&self, term: &str) -> bool;
+
+    /// Returns the folded names of the ingredients the recipe uses.
+    fn ingredients(&self) -> &[String];
+
+    /// Returns the folded names of the cookware the recipe uses.
+    fn cookware(&self) -> &[String];
+
+    /// Returns the total duration of the recipe's timers in minutes, or
+    /// `None` if it has no timers.
+    fn total_timer_minutes(&self) -> Option<f64>;
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_clauses(f, &self.clauses, " ")
+    }
+}
+
+impl fmt::Display for Clause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Clause::Term(term) => write!(f, "{term}"),
+            Clause::Phrase(phrase) => write!(f, "\"{phrase}\""),
+            Clause::Field(filter) => {
+                write!(f, "{}:{}", filter.key, filter.comparison.symbol())?;
+                if filter.value.contains(char::is_whitespace) {
+                    write!(f, "\"{}\"", filter.value)
+                } else {
+                    write!(f, "{}", filter.value)
+                }
+            }
+            Clause::Ingredient(name) => write_token(f, '@', name),
+            Clause::Cookware(name) => write_token(f, '#', name),
+            Clause::Timer(filter) => {
+                write!(f, "timer:{}", filter.comparison.symbol())?;
+                match filter.minutes {
+                    Some(minutes) => write!(f, "{minutes}m"),
+                    None => Ok(()),
+                }
+            }
+            Clause::Not(inner) => write!(f, "-{inner}"),
+            Clause::Any(clauses) => write_clauses(f, clauses, " OR "),
+            Clause::All(clauses) => {
+                write!(f, "(")?;
+                write_clauses(f, clauses, " ")?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Writes an ingredient or cookware name with its sigil, quoted if needed.
+fn write_token(f: &mut fmt::Formatter<'_>, sigil: char, name: &str) -> fmt::Result {
+    if name.contains(char::is_whitespace) {
+        write!(f, "{sigil}\"{name}\"")
+    } else {
+        write!(f, "{sigil}{name}")
+    }
+}
+
+fn write_clauses(f: &mut fmt::Formatter<'_>, clauses: &[Clause], separator: &str) -> fmt::Result {
+    for (i, clause) in clauses.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{separator}")?;
+        }
+        write!(f, "{clause}")?;
+    }
+    Ok(())
+}
+
+/// Conversion into a [`Query`], implemented for query strings and parsed queries.
+///
+/// This lets search functions accept either `"tag:vegan soup"` or a `Query`.
+pub trait IntoQuery {
+    /// Converts the value into a query, parsing it if necessary.
+    fn into_query(self) -> Result<Query, QueryError>;
+}
+
+impl IntoQuery for Query {
+    fn into_query(self) -> Result<Query, QueryError> {
+        Ok(self)
+    }
+}
+
+impl IntoQuery for &Query {
+    fn into_query(self) -> Result<Query, QueryError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoQuery for &str {
+    fn into_query(self) -> Result<Query, QueryError> {
+        Query::parse(self)
+    }
+}
+
+impl IntoQuery for String {
+    fn into_query(self) -> Result<Query, QueryError> {
+        Query::parse(&self)
+    }
+}
+
+impl IntoQuery for &String {
+    fn into_query(self) -> Result<Query, QueryError> {
+        Query::parse(self)
+    }
+}
+
+fn collect_terms(clauses: &[Clause], terms: &mut Vec<String>) {
+    for clause in clauses {
+        match clause {
+            Clause::Term(term) | Clause::Phrase(term) => terms.push(term.clone()),
+            Clause::Any(inner) | Clause::All(inner) => collect_terms(inner, terms),
+            Clause::Field(_)
+            | Clause::Ingredient(_)
+            | Clause::Cookware(_)
+            | Clause::Timer(_)
+            | Clause::Not(_) => {}
+        }
+    }
+}
+
+fn ingredient_clause(name: impl AsRef<str>) -> Clause {
+    Clause::Ingredient(name.as_ref().trim().to_lowercase())
+}
+
+fn cookware_clause(name: impl AsRef<str>) -> Clause {
+    Clause::Cookware(name.as_ref().trim().to_lowercase())
+}
+
+fn clause_matches(clause: &Clause, recipe: &dyn QueryTarget) -> bool {
+    match clause {
+        Clause::Term(term) | Clause::Phrase(term) => recipe.contains(term),
+        Clause::Field(filter) => filter.matches(recipe.metadata()),
+        Clause::Ingredient(name) => {
+            let name = fold(name);
+            recipe
+                .ingredients()
+                .iter()
+                .any(|ingredient| ingredient_matches(ingredient, &name))
+        }
+        Clause::Cookware(name) => {
+            let name = fold(name);
+            recipe
+                .cookware()
+                .iter()
+                .any(|cookware| ingredient_matches(cookware, &name))
+        }
+        Clause::Timer(filter) => filter.matches(recipe.total_timer_minutes()),
+        Clause::Not(inner) => !clause_matches(inner, recipe),
+        Clause::Any(clauses) => clauses.iter().any(|c| clause_matches(c, recipe)),
+        Clause::All(clauses) => clauses.iter().all(|c| clause_matches(c, recipe)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field(String, String),
+    Ingredient(String),
+    Cookware(String),
+    Not,
+    Or,
+    Open,
+    Close,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Phrase(read_quoted(&mut chars)?));
+            }
+            '@' | '#' => {
+                chars.next();
+                let name = match chars.peek() {
+                    Some('"') => {
+                        chars.next();
+                        read_quoted(&mut chars)?
+                    }
+                    _ => read_word(&mut chars),
+                };
+                // A lone `@` or `#` is a regular word
+                if name.trim().is_empty() {
+                    tokens.push(Token::Word(c.to_string()));
+                } else if c == '@' {
+                    tokens.push(Token::Ingredient(name));
+                } else {
+                    tokens.push(Token::Cookware(name));
+                }
+            }
+            '-' => {
+                chars.next();
+                // A lone dash is a regular word, not a negation
+                match chars.peek() {
+                    Some(&next) if !next.is_whitespace() && next != ')' => tokens.push(Token::Not),
+                    _ => tokens.push(Token::Word("-".to_string())),
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    word.push(c);
+                    // `key:"quoted value"`
+                    if c == ':' && chars.peek() == Some(&'"') {
+                        chars.next();
+                        word.push_str(&read_quoted(&mut chars)?);
+                        break;
+                    }
+                }
+                tokens.push(word_token(word)?);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn read_word(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut word = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            break;
+        }
+        chars.next();
+        word.push(c);
+    }
+    word
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<String, QueryError> {
+    let mut text = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(text);
+        }
+        text.push(c);
+    }
+    Err(QueryError::UnterminatedQuote)
+}
+
+fn word_token(word: String) -> Result<Token, QueryError> {
+    if word == "OR" || word == "|" {
+        return Ok(Token::Or);
+    }
+    match word.split_once(':') {
+        // Words like "12:30" are kept as plain text
+        Some((key, value)) if is_field_key(key) => {
+            Ok(Token::Field(key.to_string(), value.to_string()))
+        }
+        Some(("", _)) => Err(QueryError::EmptyField(word)),
+        _ => Ok(Token::Word(word)),
+    }
+}
+
+fn is_field_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().next().is_some_and(|c| c.is_alphabetic())
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// sequence := alternatives*
+    fn parse_sequence(&mut self) -> Result<Vec<Clause>, QueryError> {
+        let mut clauses = Vec::new();
+        while let Some(token) = self.peek() {
+            if *token == Token::Close {
+                break;
+            }
+            clauses.push(self.parse_alternatives()?);
+        }
+        Ok(clauses)
+    }
+
+    /// alternatives := unary ("OR" unary)*
+    fn parse_alternatives(&mut self) -> Result<Clause, QueryError> {
+        let mut alternatives = vec![self.parse_unary()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            alternatives.push(self.parse_unary()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Clause::Any(alternatives)
+        })
+    }
+
+    /// unary := "-" unary | primary
+    fn parse_unary(&mut self) -> Result<Clause, QueryError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return match self.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => {
+                    Err(QueryError::MissingClause("-".to_string()))
+                }
+                _ => Ok(Clause::Not(Box::new(self.parse_unary()?))),
+            };
+        }
+        self.parse_primary()
+    }
+
+    /// primary := "(" sequence ")" | phrase | field | timer | ingredient | cookware | word
+    fn parse_primary(&mut self) -> Result<Clause, QueryError> {
+        match self.next() {
+            Some(Token::Open) => {
+                let clauses = self.parse_sequence()?;
+                if self.next() != Some(Token::Close) {
+                    return Err(QueryError::UnbalancedParentheses);
+                }
+                Ok(Clause::All(clauses))
+            }
+            Some(Token::Word(word)) => Ok(Clause::Term(word.to_lowercase())),
+            Some(Token::Phrase(phrase)) => Ok(Clause::Phrase(phrase.to_lowercase())),
+            Some(Token::Field(key, value)) if key.eq_ignore_ascii_case("timer") => {
+                Ok(Clause::Timer(parse_timer(&value)?))
+            }
+            Some(Token::Field(key, value)) => Ok(Clause::Field(parse_field(key, value))),
+            Some(Token::Ingredient(name)) => Ok(ingredient_clause(name)),
+            Some(Token::Cookware(name)) => Ok(cookware_clause(name)),
+            Some(Token::Close) => Err(QueryError::UnbalancedParentheses),
+            Some(Token::Or) | None => Err(QueryError::MissingClause("OR".to_string())),
+            Some(Token::Not) => unreachable!("negation is handled by parse_unary"),
+        }
+    }
+}
+
+fn parse_field(key: String, value: String) -> FieldFilter {
+    let (comparison, value) = split_comparison(&value);
+    FieldFilter {
+        key: key.to_lowercase(),
+        comparison,
+        value: value.to_string(),
+    }
+}
+
+/// Parses the value of a `timer:` filter, such as `<30m` or `>=1.5h`.
+fn parse_timer(value: &str) -> Result<TimerFilter, QueryError> {
+    let (comparison, duration) = split_comparison(value);
+    if duration.is_empty() {
+        return Ok(TimerFilter {
+            comparison,
+            minutes: None,
+        });
+    }
+
+    let invalid = || QueryError::InvalidDuration(value.to_string());
+    let unit_start = duration
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(duration.len());
+    let (amount, unit) = duration.split_at(unit_start);
+    let amount: f64 = amount.parse().map_err(|_| invalid())?;
+    let factor = match unit {
+        "" => 1.0,
+        unit => duration_unit_minutes(unit).ok_or_else(invalid)?,
+    };
+    Ok(TimerFilter {
+        comparison,
+        minutes: Some(amount * factor),
+    })
+}
+
+/// Splits a leading comparison operator off a filter value.
+fn split_comparison(value: &str) -> (Comparison, &str) {
+    if let Some(v) = value.strip_prefix(">=") {
+        (Comparison::GreaterOrEqual, v)
+    } else if let Some(v) = value.strip_prefix("<=") {
+        (Comparison::LessOrEqual, v)
+    } else if let Some(v) = value.strip_prefix('>') {
+        (Comparison::Greater, v)
+    } else if let Some(v) = value.strip_prefix('<') {
+        (Comparison::Less, v)
+    } else {
+        (Comparison::Matches, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(yaml: &str) -> Metadata {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[derive(Default)]
+    struct TestRecipe {
+        metadata: Metadata,
+        words: Vec<&'static str>,
+        ingredients: Vec<String>,
+        cookware: Vec<String>,
+        timer_minutes: Option<f64>,
+    }
+
+    impl QueryTarget for TestRecipe {
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+
+        fn contains(&self, term: &str) -> bool {
+            self.words.contains(&term)
+        }
+
+        fn ingredients(&self) -> &[String] {
+            &self.ingredients
+        }
+
+        fn cookware(&self) -> &[String] {
+            &self.cookware
+        }
+
+        fn total_timer_minutes(&self) -> Option<f64> {
+            self.timer_minutes
+        }
+    }
+
+    fn term(t: &str) -> Clause {
+        Clause::Term(t.to_string())
+    }
+
+    fn field(key: &str, comparison: Comparison, value: &str) -> Clause {
+        Clause::Field(FieldFilter {
+            key: key.to_string(),
+            comparison,
+            value: value.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_parse_plain_terms() {
+        let query = Query::parse("Chocolate  cake").unwrap();
+        assert_eq!(query.clauses(), &[term("chocolate"), term("cake")]);
+        assert_eq!(query.text(), "chocolate cake");
+    }
+
+    #[test]
+    fn test_parse_mixed_query() {
+        let query = Query::parse(r#"tag:vegan -mushroom "Sheet Pan""#).unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[
+                field("tag", Comparison::Matches, "vegan"),
+                Clause::Not(Box::new(term("mushroom"))),
+                Clause::Phrase("sheet pan".to_string()),
+            ]
+        );
+        assert_eq!(query.terms(), vec!["sheet pan"]);
+        assert!(query.has_filters());
+    }
+
+    #[test]
+    fn test_parse_or_groups() {
+        let query = Query::parse("(soup OR stew) tag:vegan | tag:vegetarian").unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[
+                Clause::All(vec![Clause::Any(vec![term("soup"), term("stew")])]),
+                Clause::Any(vec![
+                    field("tag", Comparison::Matches, "vegan"),
+                    field("tag", Comparison::Matches, "vegetarian"),
+                ]),
+            ]
+        );
+        assert_eq!(query.terms(), vec!["soup", "stew"]);
+    }
+
+    #[test]
+    fn test_parse_comparisons_and_quoted_values() {
+        let query = Query::parse(r#"servings:>=4 title:"french toast" time:<30"#).unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[
+                field("servings", Comparison::GreaterOrEqual, "4"),
+                field("title", Comparison::Matches, "french toast"),
+                field("time", Comparison::Less, "30"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_non_field_colons() {
+        let query = Query::parse("12:30 - x").unwrap();
+        assert_eq!(query.clauses(), &[term("12:30"), term("-"), term("x")]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Query::parse(r#""sheet pan"#),
+            Err(QueryError::UnterminatedQuote)
+        );
+        assert_eq!(
+            Query::parse("(soup"),
+            Err(QueryError::UnbalancedParentheses)
+        );
+        assert_eq!(
+            Query::parse("soup)"),
+            Err(QueryError::UnbalancedParentheses)
+        );
+        assert!(matches!(
+            Query::parse("soup OR"),
+            Err(QueryError::MissingClause(_))
+        ));
+        assert!(matches!(
+            Query::parse(":vegan"),
+            Err(QueryError::EmptyField(_))
+        ));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let input = r#"(soup OR stew) -tag:spicy title:"french toast" servings:>2"#;
+        let query = Query::parse(input).unwrap();
+        assert_eq!(query.to_string(), input);
+        assert_eq!(Query::parse(&query.to_string()).unwrap(), query);
+    }
+
+    #[test]
+    fn test_field_filter_matches() {
+        let meta =
+            metadata("title: French Toast\nservings: 4\ntags: [Breakfast, sweet]\ncuisine: French");
+
+        let recipe = TestRecipe {
+            metadata: meta,
+            ..Default::default()
+        };
+        let check = |q: &str| Query::parse(q).unwrap().accepts(&recipe);
+        assert!(check("tag:breakfast"));
+        assert!(!check("tag:break"));
+        assert!(check("title:toast"));
+        assert!(check("servings:4"));
+        assert!(check("servings:>3"));
+        assert!(!check("servings:<4"));
+        assert!(check("cuisine:french"));
+        assert!(check("cuisine:"));
+        assert!(!check("course:"));
+        assert!(check("tag:vegan OR tag:sweet"));
+        assert!(!check("-tag:sweet"));
+    }
+
+    #[test]
+    fn test_accepts_text_constraints() {
+        let recipe = TestRecipe {
+            words: vec!["mushroom"],
+            ..Default::default()
+        };
+
+        // Top-level terms never reject a recipe
+        assert!(Query::parse("pasta").unwrap().accepts(&recipe));
+        assert!(!Query::parse("-mushroom").unwrap().accepts(&recipe));
+        assert!(Query::parse("-onion").unwrap().accepts(&recipe));
+        assert!(Query::parse("onion OR mushroom").unwrap().accepts(&recipe));
+    }
+
+    #[test]
+    fn test_parse_ingredients() {
+        let query = Query::parse(r#"@Leeks @"heavy cream" -@mushroom soup"#).unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[
+                Clause::Ingredient("leeks".to_string()),
+                Clause::Ingredient("heavy cream".to_string()),
+                Clause::Not(Box::new(Clause::Ingredient("mushroom".to_string()))),
+                term("soup"),
+            ]
+        );
+        assert_eq!(query.ingredients(), vec!["leeks", "heavy cream"]);
+        assert_eq!(query.terms(), vec!["soup"]);
+        assert!(query.has_filters());
+        assert_eq!(Query::parse(&query.to_string()).unwrap(), query);
+
+        // A lone `@` is plain text
+        assert_eq!(Query::parse("@").unwrap().clauses(), &[term("@")]);
+    }
+
+    #[test]
+    fn test_ingredient_queries() {
+        assert_eq!(
+            Query::any_ingredients(["leek", "cream"]),
+            Query::parse("@leek OR @cream").unwrap()
+        );
+        assert_eq!(
+            Query::all_ingredients(["Leek", "cream"]),
+            Query::parse("@leek @cream").unwrap()
+        );
+        assert!(Query::any_ingredients(Vec::<String>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_accepts_ingredients() {
+        let recipe = TestRecipe {
+            // Mentioned in the text, but not used as an ingredient
+            words: vec!["salt"],
+            ingredients: vec!["leek".to_string(), "heavy cream".to_string()],
+            ..Default::default()
+        };
+        let check = |q: &str| Query::parse(q).unwrap().accepts(&recipe);
+
+        assert!(check("@leeks @cream"));
+        assert!(!check("@leeks @salt"));
+        assert!(check("@salt OR @leek"));
+        assert!(!check("-@leek"));
+        assert!(check("-@mushroom"));
+    }
+
+    #[test]
+    fn test_parse_cookware_and_timers() {
+        let query =
+            Query::parse(r#"#Pot #"dutch oven" -#wok timer:<30m timer:>=1.5h timer:"#).unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[
+                Clause::Cookware("pot".to_string()),
+                Clause::Cookware("dutch oven".to_string()),
+                Clause::Not(Box::new(Clause::Cookware("wok".to_string()))),
+                Clause::Timer(TimerFilter {
+                    comparison: Comparison::Less,
+                    minutes: Some(30.0),
+                }),
+                Clause::Timer(TimerFilter {
+                    comparison: Comparison::GreaterOrEqual,
+                    minutes: Some(90.0),
+                }),
+                Clause::Timer(TimerFilter {
+                    comparison: Comparison::Matches,
+                    minutes: None,
+                }),
+            ]
+        );
+        assert_eq!(query.cookware(), vec!["pot", "dutch oven"]);
+        assert!(query.terms().is_empty());
+        assert!(query.has_filters());
+        assert_eq!(Query::parse(&query.to_string()).unwrap(), query);
+
+        // Durations default to minutes
+        assert_eq!(
+            Query::parse("timer:<=45").unwrap(),
+            Query::parse("timer:<=45min").unwrap()
+        );
+        // A lone `#` is plain text
+        assert_eq!(Query::parse("#").unwrap().clauses(), &[term("#")]);
+        assert!(matches!(
+            Query::parse("timer:<soon"),
+            Err(QueryError::InvalidDuration(_))
+        ));
+        assert!(matches!(
+            Query::parse("timer:30y"),
+            Err(QueryError::InvalidDuration(_))
+        ));
+    }
+
+    #[test]
+    fn test_accepts_cookware_and_timers() {
+        let recipe = TestRecipe {
+            words: vec!["pan"],
+            cookware: vec!["dutch oven".to_string()],
+            timer_minutes: Some(25.0),
+            ..Default::default()
+        };
+        let check = |q: &str| Query::parse(q).unwrap().accepts(&recipe);
+
+        assert!(check("#oven"));
+        assert!(!check("#pan"));
+        assert!(check("#pan OR #\"dutch oven\""));
+        assert!(check("timer:<30m"));
+        assert!(!check("timer:<20"));
+        assert!(check("timer:>1200s"));
+        assert!(check("timer:"));
+
+        let untimed = TestRecipe::default();
+        assert!(!Query::parse("timer:<30").unwrap().accepts(&untimed));
+        assert!(Query::parse("-timer:").unwrap().accepts(&untimed));
+    }
+}
@@ -0,0 +1,310 @@
+//! BM25 relevance ranking over the fields of a recipe.
+//!
+//! Every query term is counted separately in the file stem, the `title`,
+//! the tags, the other frontmatter fields and the body. Each count is
+//! normalized by the field's length relative to its average over the
+//! searched recipes and saturates as the term repeats; the per-field
+//! scores are then added up, multiplied by the field weights. Terms found
+//! in fewer recipes weigh more (inverse document frequency).
+
+use super::options::FieldWeights;
+use super::text::words;
+use super::MatchField;
+use crate::model::Metadata;
+use crate::normalize::fold;
+use serde_yaml::Value;
+
+/// Term frequency saturation: how quickly repeated terms stop adding to the score
+const K1: f64 = 1.2;
+
+/// Length normalization: 0 ignores field lengths, 1 fully normalizes them
+const B: f64 = 0.75;
+
+/// Number of fields a recipe is split into for ranking
+pub(crate) const FIELD_COUNT: usize = 5;
+
+/// Number of words in each field of a recipe.
+pub(crate) type FieldLengths = [u32; FIELD_COUNT];
+
+/// A part of a recipe that is ranked separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Field {
+    FileStem,
+    Title,
+    Tags,
+    Metadata,
+    Body,
+}
+
+impl Field {
+    pub(crate) const ALL: [Field; FIELD_COUNT] = [
+        Field::FileStem,
+        Field::Title,
+        Field::Tags,
+        Field::Metadata,
+        Field::Body,
+    ];
+
+    pub(crate) fn match_field(self) -> MatchField {
+        match self {
+            Field::FileStem => MatchField::FileStem,
+            Field::Title => MatchField::Title,
+            Field::Tags => MatchField::Tag,
+            Field::Metadata => MatchField::Metadata,
+            Field::Body => MatchField::Body,
+        }
+    }
+
+    fn weight(self, weights: &FieldWeights) -> f64 {
+        match self {
+            Field::FileStem => weights.file_stem,
+            Field::Title => weights.title,
+            Field::Tags => weights.tags,
+            Field::Metadata => weights.metadata,
+            Field::Body => weights.body,
+        }
+    }
+}
+
+/// Folded text of each field of a recipe.
+pub(crate) struct FieldTexts([String; FIELD_COUNT]);
+
+impl FieldTexts {
+    /// Splits a recipe into its fields; `body` is the content below the frontmatter.
+    pub(crate) fn new(stem: &str, metadata: &Metadata, body: &str) -> Self {
+        let title = metadata.title().map(fold).unwrap_or_default();
+        let tags: Vec<String> = metadata.tags().iter().map(|tag| fold(tag)).collect();
+
+        // Other fields in key order, so the text doesn't depend on map order
+        let mut other: Vec<(&str, &Value)> = metadata
+            .iter()
+            .filter(|(key, _)| !matches!(*key, "title" | "tags" | "tag"))
+            .collect();
+        other.sort_by_key(|(key, _)| *key);
+        let mut values = Vec::new();
+        for (_, value) in other {
+            collect_scalars(value, &mut values);
+        }
+
+        FieldTexts([
+            fold(stem),
+            title,
+            tags.join("\n"),
+            fold(&values.join("\n")),
+            fold(body),
+        ])
+    }
+
+    /// Creates fields from the file stem only, leaving the others empty.
+    pub(crate) fn stem_only(stem: &str) -> Self {
+        FieldTexts([
+            fold(stem),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+        ])
+    }
+
+    pub(crate) fn get(&self, field: Field) -> &str {
+        &self.0[field as usize]
+    }
+
+    pub(crate) fn lengths(&self) -> FieldLengths {
+        self.0.each_ref().map(|text| words(text).count() as u32)
+    }
+}
+
+/// Appends the text of every scalar in a YAML value.
+fn collect_scalars(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Number(n) => out.push(n.to_string()),
+        Value::Bool(b) => out.push(b.to_string()),
+        Value::Sequence(items) => items.iter().for_each(|item| collect_scalars(item, out)),
+        Value::Mapping(map) => map.values().for_each(|item| collect_scalars(item, out)),
+        Value::Tagged(tagged) => collect_scalars(&tagged.value, out),
+        Value::Null => {}
+    }
+}
+
+/// How often each query term occurs in each field of a recipe.
+#[derive(Debug, Clone)]
+pub(crate) struct TermFrequencies {
+    /// Per query term, the (possibly fractional) number of occurrences per field
+    counts: Vec<[f64; FIELD_COUNT]>,
+    lengths: FieldLengths,
+}
+
+impl TermFrequencies {
+    pub(crate) fn new(counts: Vec<[f64; FIELD_COUNT]>, lengths: FieldLengths) -> Self {
+        TermFrequencies { counts, lengths }
+    }
+}
+
+/// Statistics over all searched recipes that term weights depend on.
+#[derive(Debug, Clone)]
+pub(crate) struct CorpusStats {
+    documents: usize,
+    /// Per query term, the number of recipes it occurs in
+    document_frequencies: Vec<usize>,
+    /// Per field, the number of recipes in which it is not empty
+    field_documents: [usize; FIELD_COUNT],
+    total_lengths: [u64; FIELD_COUNT],
+}
+
+impl CorpusStats {
+    pub(crate) fn new(term_count: usize) -> Self {
+        CorpusStats {
+            documents: 0,
+            document_frequencies: vec![0; term_count],
+            field_documents: [0; FIELD_COUNT],
+            total_lengths: [0; FIELD_COUNT],
+        }
+    }
+
+    /// Adds a recipe to the statistics.
+    pub(crate) fn add(&mut self, frequencies: &TermFrequencies) {
+        self.documents += 1;
+        for (df, counts) in self
+            .document_frequencies
+            .iter_mut()
+            .zip(&frequencies.counts)
+        {
+            if counts.iter().any(|&count| count > 0.0) {
+                *df += 1;
+            }
+        }
+        for field in Field::ALL {
+            let length = frequencies.lengths[field as usize];
+            if length > 0 {
+                self.field_documents[field as usize] += 1;
+                self.total_lengths[field as usize] += u64::from(length);
+            }
+        }
+    }
+
+    /// Scores a recipe's term frequencies against the collection.
+    pub(crate) fn score(&self, frequencies: &TermFrequencies, weights: &FieldWeights) -> f64 {
+        let documents = self.documents.max(1) as f64;
+
+        let mut score = 0.0;
+        for (&df, counts) in self.document_frequencies.iter().zip(&frequencies.counts) {
+            let df = df as f64;
+            let idf = (1.0 + (documents - df + 0.5) / (df + 0.5)).ln();
+            for field in Field::ALL {
+                let count = counts[field as usize];
+                if count == 0.0 {
+                    continue;
+                }
+                // Averaged over recipes that have the field, so that a
+                // title isn't penalized because most recipes have none
+                let average = match self.field_documents[field as usize] {
+                    0 => 0.0,
+                    n => self.total_lengths[field as usize] as f64 / n as f64,
+                };
+                let length = frequencies.lengths[field as usize] as f64;
+                let norm = if average > 0.0 {
+                    1.0 - B + B * length / average
+                } else {
+                    1.0
+                };
+                let tf = count / norm;
+                score += field.weight(weights) * idf * tf * (K1 + 1.0) / (tf + K1);
+            }
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_frontmatter;
+
+    fn frequencies(counts: &[f64], lengths: FieldLengths) -> TermFrequencies {
+        TermFrequencies::new(
+            counts
+                .iter()
+                .map(|&count| [0.0, 0.0, 0.0, 0.0, count])
+                .collect(),
+            lengths,
+        )
+    }
+
+    #[test]
+    fn test_field_texts() {
+        let metadata = parse_frontmatter(
+            "---\ntitle: Crème Brûlée\ntags: [French, dessert]\ncuisine: French\nservings: 4\n---\n",
+        );
+        let fields = FieldTexts::new("creme_brulee", &metadata, "Torch the sugar");
+        assert_eq!(fields.get(Field::FileStem), "creme_brulee");
+        assert_eq!(fields.get(Field::Title), "creme brulee");
+        assert_eq!(fields.get(Field::Tags), "french\ndessert");
+        assert_eq!(fields.get(Field::Metadata), "french\n4");
+        assert_eq!(fields.get(Field::Body), "torch the sugar");
+        assert_eq!(fields.lengths(), [2, 2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn test_repeated_terms_saturate() {
+        let mut corpus = CorpusStats::new(1);
+        let once = frequencies(&[1.0], [1, 0, 0, 0, 10]);
+        let often = frequencies(&[10.0], [1, 0, 0, 0, 10]);
+        corpus.add(&once);
+        corpus.add(&often);
+        corpus.add(&frequencies(&[0.0], [1, 0, 0, 0, 10]));
+
+        let weights = FieldWeights::default();
+        let once = corpus.score(&once, &weights);
+        let often = corpus.score(&often, &weights);
+        assert!(often > once);
+        assert!(often < 10.0 * once);
+    }
+
+    #[test]
+    fn test_long_fields_weigh_less() {
+        let mut corpus = CorpusStats::new(1);
+        let short = frequencies(&[1.0], [1, 0, 0, 0, 5]);
+        let long = frequencies(&[1.0], [1, 0, 0, 0, 500]);
+        corpus.add(&short);
+        corpus.add(&long);
+        corpus.add(&frequencies(&[0.0], [1, 0, 0, 0, 50]));
+
+        let weights = FieldWeights::default();
+        assert!(corpus.score(&short, &weights) > corpus.score(&long, &weights));
+    }
+
+    #[test]
+    fn test_rare_terms_weigh_more() {
+        let mut corpus = CorpusStats::new(2);
+        let both = frequencies(&[1.0, 1.0], [1, 0, 0, 0, 5]);
+        corpus.add(&both);
+        for _ in 0..5 {
+            corpus.add(&frequencies(&[1.0, 0.0], [1, 0, 0, 0, 5]));
+        }
+
+        let weights = FieldWeights::default();
+        let common = corpus.score(&frequencies(&[1.0, 0.0], [1, 0, 0, 0, 5]), &weights);
+        let rare = corpus.score(&frequencies(&[0.0, 1.0], [1, 0, 0, 0, 5]), &weights);
+        assert!(rare > common);
+    }
+
+    #[test]
+    fn test_field_weights() {
+        let mut corpus = CorpusStats::new(1);
+        let in_title = TermFrequencies::new(vec![[0.0, 1.0, 0.0, 0.0, 0.0]], [1, 2, 0, 0, 5]);
+        let in_body = TermFrequencies::new(vec![[0.0, 0.0, 0.0, 0.0, 1.0]], [1, 2, 0, 0, 5]);
+        corpus.add(&in_title);
+        corpus.add(&in_body);
+
+        let weights = FieldWeights::default();
+        assert!(corpus.score(&in_title, &weights) > corpus.score(&in_body, &weights));
+
+        let body_first = FieldWeights {
+            title: 0.5,
+            ..FieldWeights::default()
+        };
+        assert!(corpus.score(&in_title, &body_first) < corpus.score(&in_body, &body_first));
+    }
+}
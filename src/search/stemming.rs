@@ -0,0 +1,108 @@
+//! Word stemming, so that inflected forms of a query term match.
+
+use rust_stemmers::Algorithm;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Reduces words to a common stem, e.g. "tomatoes" and "tomato" to "tomato".
+///
+/// Search compares the stems of query terms with the stems of recipe words
+/// when [`SearchOptions::stemmer`](super::SearchOptions::stemmer) is set.
+/// Words are passed in folded form: lowercase and without diacritics.
+/// Implement this trait to plug in a custom stemmer; [`Language`] provides
+/// Snowball stemmers for common languages.
+pub trait Stemmer: Send + Sync + fmt::Debug {
+    /// Returns the stem of a folded word.
+    fn stem<'a>(&self, word: &'a str) -> Cow<'a, str>;
+}
+
+/// Languages with a built-in Snowball stemmer.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::{search_with_options, Language, SearchOptions};
+/// use camino::Utf8Path;
+/// use std::sync::Arc;
+///
+/// let options = SearchOptions {
+///     stemmer: Some(Arc::new(Language::English)),
+///     ..Default::default()
+/// };
+/// // Also finds recipes that only mention "tomato"
+/// let results = search_with_options(Utf8Path::new("./recipes"), "tomatoes", &options)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl Language {
+    fn algorithm(self) -> Algorithm {
+        match self {
+            Language::Arabic => Algorithm::Arabic,
+            Language::Danish => Algorithm::Danish,
+            Language::Dutch => Algorithm::Dutch,
+            Language::English => Algorithm::English,
+            Language::Finnish => Algorithm::Finnish,
+            Language::French => Algorithm::French,
+            Language::German => Algorithm::German,
+            Language::Greek => Algorithm::Greek,
+            Language::Hungarian => Algorithm::Hungarian,
+            Language::Italian => Algorithm::Italian,
+            Language::Norwegian => Algorithm::Norwegian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Romanian => Algorithm::Romanian,
+            Language::Russian => Algorithm::Russian,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Swedish => Algorithm::Swedish,
+            Language::Tamil => Algorithm::Tamil,
+            Language::Turkish => Algorithm::Turkish,
+        }
+    }
+}
+
+impl Stemmer for Language {
+    fn stem<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        rust_stemmers::Stemmer::create(self.algorithm()).stem(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_stems() {
+        let english = Language::English;
+        assert_eq!(english.stem("tomatoes"), english.stem("tomato"));
+        assert_eq!(english.stem("baking"), english.stem("baked"));
+        assert_ne!(english.stem("bake"), english.stem("bacon"));
+    }
+
+    #[test]
+    fn test_other_languages() {
+        let french = Language::French;
+        assert_eq!(french.stem("tomates"), french.stem("tomate"));
+        let spanish = Language::Spanish;
+        assert_eq!(spanish.stem("cebollas"), spanish.stem("cebolla"));
+    }
+}
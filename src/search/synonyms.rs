@@ -0,0 +1,238 @@
+//! User-supplied synonym dictionaries that queries are expanded with.
+
+use super::stemming::Stemmer;
+use super::SearchError;
+use crate::normalize::fold;
+use camino::Utf8Path;
+use serde_yaml::Value;
+use std::fs;
+
+/// Name of the synonym file loaded from a recipe directory.
+pub const SYNONYMS_FILE: &str = "synonyms.yaml";
+
+/// Groups of words and phrases that mean the same, like "aubergine" and
+/// "eggplant".
+///
+/// A query term matching any word of a group also matches all other words
+/// of the group, in file names as well as in content. Comparisons ignore
+/// case and diacritics.
+///
+/// Searches load `synonyms.yaml` from the recipe directory unless
+/// [`SearchOptions::synonyms`](super::SearchOptions::synonyms) is set. The
+/// file either lists groups:
+///
+/// ```yaml
+/// - [aubergine, eggplant, brinjal]
+/// - [courgette, zucchini]
+/// ```
+///
+/// or maps words to their synonyms:
+///
+/// ```yaml
+/// aubergine: [eggplant, brinjal]
+/// scallion: green onion
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Synonyms {
+    /// Folded words of each group, without duplicates
+    groups: Vec<Vec<String>>,
+}
+
+impl Synonyms {
+    /// Creates an empty dictionary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a group of words that mean the same.
+    ///
+    /// Groups with fewer than two distinct words are ignored.
+    pub fn add_group<I, S>(&mut self, words: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut group: Vec<String> = Vec::new();
+        for word in words {
+            let word = fold(word.as_ref().trim());
+            if !word.is_empty() && !group.contains(&word) {
+                group.push(word);
+            }
+        }
+        if group.len() > 1 {
+            self.groups.push(group);
+        }
+    }
+
+    /// Parses a dictionary in the format of `synonyms.yaml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SearchError::SynonymsError` if the text is not valid YAML
+    /// or neither a list of groups nor a mapping.
+    pub fn from_yaml(yaml: &str) -> Result<Self, SearchError> {
+        let value: Value =
+            serde_yaml::from_str(yaml).map_err(|e| SearchError::SynonymsError(e.to_string()))?;
+
+        let mut synonyms = Synonyms::new();
+        match value {
+            Value::Null => {}
+            Value::Sequence(groups) => {
+                for group in groups {
+                    synonyms.add_group(words(&group)?);
+                }
+            }
+            Value::Mapping(map) => {
+                for (word, others) in map {
+                    let mut group = words(&word)?;
+                    group.extend(words(&others)?);
+                    synonyms.add_group(group);
+                }
+            }
+            _ => {
+                return Err(SearchError::SynonymsError(
+                    "expected a list of groups or a mapping".to_string(),
+                ))
+            }
+        }
+        Ok(synonyms)
+    }
+
+    /// Loads a dictionary from a YAML file, see [`from_yaml`](Synonyms::from_yaml).
+    pub fn load(path: &Utf8Path) -> Result<Self, SearchError> {
+        let yaml = fs::read_to_string(path)?;
+        Self::from_yaml(&yaml).map_err(|e| match e {
+            SearchError::SynonymsError(message) => {
+                SearchError::SynonymsError(format!("{path}: {message}"))
+            }
+            e => e,
+        })
+    }
+
+    /// Loads and merges the synonym files of several recipe directories.
+    ///
+    /// Directories without a synonym file are skipped.
+    pub(crate) fn for_dirs<P: AsRef<Utf8Path>>(dirs: &[P]) -> Result<Self, SearchError> {
+        let mut synonyms = Synonyms::new();
+        for dir in dirs {
+            let path = dir.as_ref().join(SYNONYMS_FILE);
+            if path.is_file() {
+                synonyms.extend(Synonyms::load(&path)?);
+            }
+        }
+        Ok(synonyms)
+    }
+
+    /// Adds all groups of another dictionary.
+    pub fn extend(&mut self, other: Synonyms) {
+        self.groups.extend(other.groups);
+    }
+
+    /// Returns true if the dictionary has no groups.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Returns the folded term followed by all its synonyms.
+    pub fn expand(&self, term: &str) -> Vec<String> {
+        self.expand_with(term, None)
+    }
+
+    /// Returns the folded term followed by all its synonyms, also looking
+    /// up groups containing a word with the same stem as the term.
+    pub(crate) fn expand_with(&self, term: &str, stemmer: Option<&dyn Stemmer>) -> Vec<String> {
+        let term = fold(term);
+        let stem = stemmer.map(|stemmer| stemmer.stem(&term).into_owned());
+
+        let mut expanded = vec![term.clone()];
+        for group in &self.groups {
+            let matches = group.iter().any(|word| {
+                *word == term
+                    || stemmer.zip(stem.as_deref()).is_some_and(|(stemmer, stem)| {
+                        !word.contains(char::is_whitespace) && stemmer.stem(word) == stem
+                    })
+            });
+            if !matches {
+                continue;
+            }
+            for word in group {
+                if !expanded.contains(word) {
+                    expanded.push(word.clone());
+                }
+            }
+        }
+        expanded
+    }
+}
+
+/// Returns the words of a YAML string or list of strings.
+fn words(value: &Value) -> Result<Vec<String>, SearchError> {
+    match value {
+        Value::String(word) => Ok(vec![word.clone()]),
+        Value::Sequence(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(word) => Ok(word.clone()),
+                _ => Err(SearchError::SynonymsError(format!(
+                    "expected a word, found {item:?}"
+                ))),
+            })
+            .collect(),
+        _ => Err(SearchError::SynonymsError(format!(
+            "expected a word or a list of words, found {value:?}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Language;
+
+    #[test]
+    fn test_groups() {
+        let synonyms =
+            Synonyms::from_yaml("- [Aubergine, eggplant, brinjal]\n- [courgette, zucchini]\n")
+                .unwrap();
+        assert_eq!(
+            synonyms.expand("EGGPLANT"),
+            vec!["eggplant", "aubergine", "brinjal"]
+        );
+        assert_eq!(synonyms.expand("zucchini"), vec!["zucchini", "courgette"]);
+        assert_eq!(synonyms.expand("leek"), vec!["leek"]);
+    }
+
+    #[test]
+    fn test_mapping() {
+        let synonyms =
+            Synonyms::from_yaml("aubergine: [eggplant]\nscallion: Green Onion\n").unwrap();
+        assert_eq!(synonyms.expand("aubergine"), vec!["aubergine", "eggplant"]);
+        assert_eq!(
+            synonyms.expand("green onion"),
+            vec!["green onion", "scallion"]
+        );
+    }
+
+    #[test]
+    fn test_expand_with_stemmer() {
+        let synonyms = Synonyms::from_yaml("- [aubergine, eggplant]").unwrap();
+        assert_eq!(synonyms.expand("eggplants"), vec!["eggplants"]);
+        assert_eq!(
+            synonyms.expand_with("eggplants", Some(&Language::English)),
+            vec!["eggplants", "aubergine", "eggplant"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_yaml() {
+        assert!(matches!(
+            Synonyms::from_yaml("just a string"),
+            Err(SearchError::SynonymsError(_))
+        ));
+        assert!(matches!(
+            Synonyms::from_yaml("- [aubergine, {a: b}]"),
+            Err(SearchError::SynonymsError(_))
+        ));
+        assert!(Synonyms::from_yaml("").unwrap().is_empty());
+    }
+}
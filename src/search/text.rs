@@ -0,0 +1,118 @@
+//! Text helpers for word splitting and typo-tolerant matching.
+
+use crate::normalize::FoldedText;
+use std::ops::Range;
+
+/// Returns the byte ranges in the original text of words within
+/// `max_edits` of a folded term, excluding exact occurrences.
+pub(crate) fn find_fuzzy(text: &FoldedText, term: &str, max_edits: usize) -> Vec<Range<usize>> {
+    words(&text.text)
+        .filter(|(_, word)| *word != term && within_edit_distance(word, term, max_edits))
+        .map(|(start, word)| text.original_range(start..start + word.len()))
+        .collect()
+}
+
+/// Splits text into alphanumeric words with their byte offsets.
+pub(crate) fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// Checks whether two strings are within `max` edits of each other.
+///
+/// Edits are insertions, deletions, substitutions and transpositions of
+/// adjacent characters (optimal string alignment distance).
+pub(crate) fn within_edit_distance(a: &str, b: &str, max: usize) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return false;
+    }
+
+    // Three rolling rows of the distance matrix
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = current[0];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(before[j - 2] + 1);
+            }
+            current[j] = distance;
+            row_min = row_min.min(distance);
+        }
+        // No later row can get below the best value of this one
+        if row_min > max {
+            return false;
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()] <= max
+}
+
+/// Merges overlapping or adjacent ranges and sorts them by start.
+pub(crate) fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|r| (r.start, r.end));
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_fuzzy() {
+        let text = FoldedText::new("Classic Lasagna, not lasagne or Lasagana");
+        assert_eq!(find_fuzzy(&text, "lasagana", 1), vec![8..15]);
+        assert_eq!(find_fuzzy(&text, "lasagana", 2), vec![8..15, 21..28]);
+        assert!(find_fuzzy(&text, "lasagana", 0).is_empty());
+    }
+
+    #[test]
+    fn test_within_edit_distance() {
+        assert!(within_edit_distance("lasagana", "lasagna", 1));
+        assert!(within_edit_distance("bolognaise", "bolognese", 2));
+        assert!(!within_edit_distance("bolognaise", "bolognese", 1));
+        // Transposition counts as a single edit
+        assert!(within_edit_distance("recipe", "recpie", 1));
+        assert!(within_edit_distance("", "ab", 2));
+        assert!(!within_edit_distance("salt", "pepper", 2));
+    }
+
+    #[test]
+    fn test_words() {
+        let words: Vec<_> = words("Add @salt{1%tsp}, crème").collect();
+        assert_eq!(
+            words,
+            vec![
+                (0, "Add"),
+                (5, "salt"),
+                (10, "1"),
+                (12, "tsp"),
+                (18, "crème")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(merge_ranges(vec![5..8, 0..2, 1..3, 8..9]), vec![0..3, 5..9]);
+    }
+}
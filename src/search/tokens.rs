@@ -0,0 +1,186 @@
+//! Extraction of ingredients, cookware and timers from Cooklang recipe bodies.
+
+use crate::normalize::fold;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Returns the folded names of the ingredients a recipe body uses, without
+/// duplicates and in order of first use.
+///
+/// Both single-word (`@salt`) and multi-word (`@heavy cream{200%ml}`)
+/// ingredients are recognized, with any modifiers (`@?`, `@&`, ...)
+/// stripped. Recipe references (`@./sauces/Pesto{}`) are reported by the
+/// referenced file name. Ingredients inside comments are ignored.
+pub(crate) fn ingredient_names(body: &str) -> Vec<String> {
+    static INGREDIENT: OnceLock<Regex> = OnceLock::new();
+    let ingredient = INGREDIENT
+        .get_or_init(|| Regex::new(r"@[@&?+\-]*(?:([^@#~{}\n]+)\{|([\p{L}\p{N}_\-./]+))").unwrap());
+    token_names(body, ingredient)
+}
+
+/// Returns the folded names of the cookware a recipe body uses, without
+/// duplicates and in order of first use.
+///
+/// Single-word (`#pot`) and multi-word (`#dutch oven{}`) cookware is
+/// recognized like ingredients.
+pub(crate) fn cookware_names(body: &str) -> Vec<String> {
+    static COOKWARE: OnceLock<Regex> = OnceLock::new();
+    let cookware = COOKWARE
+        .get_or_init(|| Regex::new(r"#[&?]*(?:([^@#~{}\n]+)\{|([\p{L}\p{N}_\-]+))").unwrap());
+    token_names(body, cookware)
+}
+
+/// Returns the total duration in minutes of the timers in a recipe body
+/// (`~{25%minutes}`, `~eggs{3%min}`), or `None` if it has no timers.
+///
+/// Timers with an amount or unit that can't be understood are ignored.
+/// Ranges like `~{10-15%minutes}` count with their lower bound.
+pub(crate) fn total_timer_minutes(body: &str) -> Option<f64> {
+    static TIMER: OnceLock<Regex> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| Regex::new(r"~[^@#~{}\n]*\{([^}\n]*)\}").unwrap());
+
+    let mut total = None;
+    for line in code_lines(body).lines() {
+        for captures in timer.captures_iter(line) {
+            let Some((amount, unit)) = captures[1].split_once('%') else {
+                continue;
+            };
+            if let Some(minutes) = timer_minutes(amount, unit) {
+                *total.get_or_insert(0.0) += minutes;
+            }
+        }
+    }
+    total
+}
+
+/// Converts a timer amount and unit into minutes.
+fn timer_minutes(amount: &str, unit: &str) -> Option<f64> {
+    let factor = duration_unit_minutes(unit.trim())?;
+    let amount = amount.trim();
+    // Lower bound of ranges like "10-15"
+    let amount = amount.split_once('-').map_or(amount, |(low, _)| low.trim());
+    let amount = match amount.split_once('/') {
+        Some((numerator, denominator)) => {
+            numerator.trim().parse::<f64>().ok()? / denominator.trim().parse::<f64>().ok()?
+        }
+        None => amount.parse::<f64>().ok()?,
+    };
+    amount.is_finite().then_some(amount * factor)
+}
+
+/// Returns the number of minutes in a duration unit, or `None` for unknown units.
+pub(crate) fn duration_unit_minutes(unit: &str) -> Option<f64> {
+    match unit.to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(1.0 / 60.0),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(1.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(60.0),
+        "d" | "day" | "days" => Some(24.0 * 60.0),
+        _ => None,
+    }
+}
+
+/// Collects the folded names captured by a token pattern, without duplicates.
+fn token_names(body: &str, pattern: &Regex) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    for line in code_lines(body).lines() {
+        for captures in pattern.captures_iter(line) {
+            let name = captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .map_or("", |m| m.as_str());
+            // Recipe references are named after the referenced file
+            let name = name.rsplit('/').next().unwrap_or(name);
+            let name = fold(name.trim().trim_end_matches('.'));
+            if !name.is_empty() && seen.insert(name.clone()) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Returns the body without block (`[- -]`) and line (`--`) comments.
+fn code_lines(body: &str) -> String {
+    static BLOCK_COMMENT: OnceLock<Regex> = OnceLock::new();
+    let block_comment = BLOCK_COMMENT.get_or_init(|| Regex::new(r"(?s)\[-.*?-\]").unwrap());
+
+    block_comment
+        .replace_all(body, "")
+        .lines()
+        .map(|line| line.split_once("--").map_or(line, |(code, _)| code))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks whether a folded ingredient or cookware name matches a folded query term.
+///
+/// The term matches if it, or its singular form, occurs in the name, so
+/// "cream" matches "heavy cream" and "leeks" matches "leek".
+pub(crate) fn ingredient_matches(name: &str, term: &str) -> bool {
+    if term.is_empty() {
+        return false;
+    }
+    name.contains(term)
+        || [term.strip_suffix("es"), term.strip_suffix('s')]
+            .into_iter()
+            .flatten()
+            .any(|singular| singular.chars().count() > 2 && name.contains(singular))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingredient_names() {
+        let body = "Slice the @leeks{2} and add @heavy cream{200%ml}.\n\
+                    Season with @salt and @?black pepper{}, then add more @salt.\n\
+                    Serve with @./sauces/Pesto{} -- or @parmesan\n\
+                    [- @anchovies{} are optional -] Top with @Crème Fraîche{1%tbsp}.";
+        assert_eq!(
+            ingredient_names(body),
+            vec![
+                "leeks",
+                "heavy cream",
+                "salt",
+                "black pepper",
+                "pesto",
+                "creme fraiche"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prose_is_not_an_ingredient() {
+        assert!(ingredient_names("Add salt to taste, then bake for ~{20%minutes}").is_empty());
+    }
+
+    #[test]
+    fn test_ingredient_matches() {
+        assert!(ingredient_matches("heavy cream", "cream"));
+        assert!(ingredient_matches("leek", "leeks"));
+        assert!(ingredient_matches("tomato", "tomatoes"));
+        assert!(ingredient_matches("cheese", "cheeses"));
+        assert!(!ingredient_matches("leek", "onion"));
+        assert!(!ingredient_matches("salt", ""));
+    }
+
+    #[test]
+    fn test_cookware_names() {
+        let body = "Heat the #Dutch Oven{} and a #pan.\n\
+                    Use the #pan{2} again -- not the #wok\n\
+                    Add @salt and bake for ~{20%minutes}.";
+        assert_eq!(cookware_names(body), vec!["dutch oven", "pan"]);
+    }
+
+    #[test]
+    fn test_total_timer_minutes() {
+        let body = "Boil for ~{10%minutes}, then rest ~resting{1/2%hour}.\n\
+                    Sear ~{90%s} [- and ~{5%min} more -]\n\
+                    Simmer ~{10-15%min} and ~{a while%minutes}";
+        assert_eq!(total_timer_minutes(body), Some(10.0 + 30.0 + 1.5 + 10.0));
+        assert_eq!(total_timer_minutes("No timers, only @salt"), None);
+    }
+}
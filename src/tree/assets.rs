@@ -0,0 +1,95 @@
+//! Files other than recipes listed in tree nodes.
+
+use super::dir_config::DIR_CONFIG_FILE;
+use crate::model::IMAGE_EXTENSIONS;
+use crate::search::SYNONYMS_FILE;
+use crate::walk::{COOKIGNORE_FILE, GITIGNORE_FILE};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+/// Extensions of files classified as [`AssetKind::Document`].
+const DOCUMENT_EXTENSIONS: [&str; 7] = ["pdf", "doc", "docx", "odt", "rtf", "pages", "epub"];
+
+/// Extensions of files classified as [`AssetKind::Note`].
+const NOTE_EXTENSIONS: [&str; 6] = ["md", "markdown", "txt", "text", "org", "rst"];
+
+/// A file in a recipe directory that is not a recipe, such as a scanned
+/// recipe card, a note or a photo.
+///
+/// Assets are only listed when [`TreeOptions::assets`](super::TreeOptions::assets)
+/// is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Asset {
+    /// Full path to the file
+    pub path: Utf8PathBuf,
+    /// What kind of file it is, judged by its extension
+    pub kind: AssetKind,
+}
+
+impl Asset {
+    pub(crate) fn new(path: Utf8PathBuf) -> Self {
+        let kind = AssetKind::of(&path);
+        Asset { path, kind }
+    }
+
+    /// Returns the file name of the asset.
+    pub fn file_name(&self) -> &str {
+        self.path.file_name().unwrap_or(self.path.as_str())
+    }
+}
+
+/// The kind of a file listed as an [`Asset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    /// A photo or picture (`.jpg`, `.jpeg`, `.png`, `.webp`), including
+    /// title and step images of recipes
+    Image,
+    /// A document such as a PDF (`.pdf`, `.doc`, `.docx`, `.odt`, `.rtf`,
+    /// `.pages`, `.epub`)
+    Document,
+    /// A plain text or markup note (`.md`, `.markdown`, `.txt`, `.text`,
+    /// `.org`, `.rst`)
+    Note,
+    /// Any other file
+    Unknown,
+}
+
+impl AssetKind {
+    /// Classifies a file by its extension, ignoring case.
+    pub fn of(path: &Utf8Path) -> Self {
+        let Some(extension) = path.extension().map(str::to_lowercase) else {
+            return AssetKind::Unknown;
+        };
+        let extension = extension.as_str();
+        if IMAGE_EXTENSIONS.contains(&extension) {
+            AssetKind::Image
+        } else if DOCUMENT_EXTENSIONS.contains(&extension) {
+            AssetKind::Document
+        } else if NOTE_EXTENSIONS.contains(&extension) {
+            AssetKind::Note
+        } else {
+            AssetKind::Unknown
+        }
+    }
+}
+
+/// Checks whether a file or directory found below a base directory is
+/// listed in the tree: hidden ones, those in hidden directories and the
+/// settings files read by this library are not.
+pub(crate) fn is_listed(base_dir: &Utf8Path, path: &Utf8Path) -> bool {
+    let relative = path.strip_prefix(base_dir).unwrap_or(path);
+    let hidden = relative
+        .components()
+        .any(|component| component.as_str().starts_with('.'));
+    let settings = path.file_name().is_some_and(|name| {
+        [
+            DIR_CONFIG_FILE,
+            SYNONYMS_FILE,
+            COOKIGNORE_FILE,
+            GITIGNORE_FILE,
+        ]
+        .contains(&name)
+    });
+    !hidden && !settings
+}
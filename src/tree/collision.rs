@@ -0,0 +1,118 @@
+//! Collision-safe keys for tree children and reports of clashing names.
+
+use super::model::RecipeTree;
+use crate::normalize::{fold, nfc};
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::map::Entry;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// Siblings in a recipe tree that share a name.
+///
+/// Files with the same stem (`Soup.cook` and `Soup.menu`), a recipe file
+/// next to a directory of the same name, or recipes whose titles compare
+/// equal all end up with the same name. They are kept as separate nodes,
+/// under different keys, and reported so the conflict can be resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameCollision {
+    /// Path of the directory holding the clashing nodes
+    pub parent: Utf8PathBuf,
+    /// The shared name, as displayed by the first of the nodes
+    pub name: String,
+    /// Keys of the clashing nodes in their parent's `children`, in the
+    /// same order
+    pub keys: Vec<String>,
+    /// Paths of the clashing nodes, in the order of `keys`
+    pub paths: Vec<Utf8PathBuf>,
+}
+
+/// Returns the key a recipe file is stored under: its stem, or, if a
+/// sibling already uses that, its file name, or a numbered file name.
+pub(crate) fn recipe_key(children: &IndexMap<String, RecipeTree>, path: &Utf8Path) -> String {
+    let file_name = path.file_name().unwrap_or(path.as_str());
+    let stem = path.file_stem().unwrap_or(file_name);
+    let candidates = [nfc(stem), nfc(file_name)];
+    if let Some(key) = candidates.iter().find(|key| !children.contains_key(*key)) {
+        return key.clone();
+    }
+    (2..)
+        .map(|n| format!("{} ({n})", candidates[1]))
+        .find(|key| !children.contains_key(key))
+        .unwrap()
+}
+
+/// Returns the directory node for a name, creating it if needed.
+///
+/// A recipe that holds the directory's key is moved to a key based on its
+/// file name, so directories always keep their plain name.
+pub(crate) fn dir_entry<'a>(
+    children: &'a mut IndexMap<String, RecipeTree>,
+    name: &str,
+    create: impl FnOnce() -> RecipeTree,
+) -> &'a mut RecipeTree {
+    let key = nfc(name);
+    let displaced = match children.get(&key) {
+        Some(node) if node.recipe.is_some() => children.shift_remove(&key),
+        _ => None,
+    };
+    let index = match children.entry(key) {
+        Entry::Occupied(entry) => entry.index(),
+        Entry::Vacant(entry) => {
+            let index = entry.index();
+            entry.insert(create());
+            index
+        }
+    };
+    if let Some(recipe) = displaced {
+        let key = recipe_key(children, &recipe.path);
+        children.insert(key, recipe);
+    }
+    children.get_index_mut(index).unwrap().1
+}
+
+/// Collects the groups of siblings in a tree that share a name.
+///
+/// Siblings share a name if they are stored under the same plain key, i.e.
+/// their file stems or directory names are equal, or if their display names
+/// compare equal ignoring case, Unicode normalization form and diacritics.
+pub(crate) fn collect_collisions(tree: &RecipeTree, collisions: &mut Vec<NameCollision>) {
+    // Group by plain key first, then by display name, in the order of the children
+    let mut groups: IndexMap<String, Vec<&str>> = IndexMap::new();
+    for (key, child) in &tree.children {
+        groups.entry(plain_key(child)).or_default().push(key);
+    }
+    let mut by_name: IndexMap<String, Vec<&str>> = IndexMap::new();
+    for (key, child) in &tree.children {
+        by_name.entry(fold(&child.name)).or_default().push(key);
+    }
+
+    let mut reported: Vec<Vec<&str>> = Vec::new();
+    for keys in groups.into_values().chain(by_name.into_values()) {
+        if keys.len() < 2 || reported.contains(&keys) {
+            continue;
+        }
+        let nodes: Vec<&RecipeTree> = keys.iter().map(|key| &tree.children[*key]).collect();
+        collisions.push(NameCollision {
+            parent: tree.path.clone(),
+            name: nodes[0].name.clone(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            paths: nodes.iter().map(|node| node.path.clone()).collect(),
+        });
+        reported.push(keys);
+    }
+
+    for child in tree.children.values() {
+        if !child.children.is_empty() {
+            collect_collisions(child, collisions);
+        }
+    }
+}
+
+/// Returns the key a node would be stored under without collisions.
+fn plain_key(node: &RecipeTree) -> String {
+    let name = match node.recipe {
+        Some(_) => node.path.file_stem(),
+        None => node.path.file_name(),
+    };
+    nfc(name.unwrap_or(&node.name))
+}
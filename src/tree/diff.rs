@@ -0,0 +1,168 @@
+//! Differences between two recipe trees.
+
+use super::model::RecipeTree;
+use crate::model::Metadata;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The differences between the recipes of two trees.
+///
+/// Paths are relative to the roots of the trees, so trees of the same
+/// collection in different places, e.g. synced to two devices, can be
+/// compared. Every list is sorted by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeDiff {
+    /// Recipes only in the new tree
+    pub added: Vec<Utf8PathBuf>,
+    /// Recipes only in the old tree
+    pub removed: Vec<Utf8PathBuf>,
+    /// Recipes found at another path in the new tree, with the same content
+    pub moved: Vec<MovedRecipe>,
+    /// Recipes at the same path in both trees whose content changed
+    pub modified: Vec<ModifiedRecipe>,
+}
+
+impl TreeDiff {
+    /// Returns true if both trees hold the same recipes.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.modified.is_empty()
+    }
+}
+
+/// A recipe that was moved or renamed between two trees.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovedRecipe {
+    /// Path in the old tree
+    pub from: Utf8PathBuf,
+    /// Path in the new tree
+    pub to: Utf8PathBuf,
+}
+
+/// A recipe whose content changed between two trees.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModifiedRecipe {
+    /// Path of the recipe in both trees
+    pub path: Utf8PathBuf,
+    /// Metadata keys that were added, removed or changed, sorted; empty if
+    /// only the body of the recipe changed
+    pub metadata_keys: Vec<String>,
+}
+
+/// Compares the recipes of two trees.
+///
+/// Recipes are matched by their paths relative to the roots first. Of the
+/// remaining ones, a recipe in the old tree and one in the new tree with
+/// the same content are reported as moved, preferring one with the same
+/// file name. Whether content changed is decided by content hashes
+/// recorded when the trees were built, falling back to file modification
+/// times and sizes, and finally to the metadata, for trees deserialized
+/// from snapshots that lack them. Directories are not compared on their
+/// own.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::{build_tree, diff_trees, RecipeTree};
+///
+/// let snapshot: RecipeTree = serde_json::from_slice(&std::fs::read("tree.json")?)?;
+/// let diff = diff_trees(&snapshot, &build_tree("./recipes")?);
+/// println!(
+///     "{} recipes added, {} renamed, {} edited",
+///     diff.added.len(),
+///     diff.moved.len(),
+///     diff.modified.len()
+/// );
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn diff_trees(old: &RecipeTree, new: &RecipeTree) -> TreeDiff {
+    let mut old_recipes = BTreeMap::new();
+    collect_recipes(old, &old.path, &mut old_recipes);
+    let mut new_recipes = BTreeMap::new();
+    collect_recipes(new, &new.path, &mut new_recipes);
+
+    let mut diff = TreeDiff::default();
+    let mut removed = Vec::new();
+    for (path, old_node) in &old_recipes {
+        match new_recipes.remove(path) {
+            Some(new_node) => {
+                if let Some(metadata_keys) = modification(old_node, new_node) {
+                    diff.modified.push(ModifiedRecipe {
+                        path: path.clone(),
+                        metadata_keys,
+                    });
+                }
+            }
+            None => removed.push((path, old_node.hash)),
+        }
+    }
+
+    // Whatever is left in the new tree was added or moved
+    let mut added: Vec<_> = new_recipes
+        .iter()
+        .map(|(path, node)| (path.clone(), node.hash))
+        .collect();
+    for (from, hash) in removed {
+        let same_hash = |(_, other): &&(Utf8PathBuf, Option<u64>)| hash.is_some() && *other == hash;
+        let found = added
+            .iter()
+            .filter(same_hash)
+            .find(|(to, _)| to.file_name() == from.file_name())
+            .or_else(|| added.iter().find(same_hash))
+            .map(|(to, _)| to.clone());
+        match found {
+            Some(to) => {
+                added.retain(|(path, _)| *path != to);
+                diff.moved.push(MovedRecipe {
+                    from: from.clone(),
+                    to,
+                });
+            }
+            None => diff.removed.push(from.clone()),
+        }
+    }
+    diff.added = added.into_iter().map(|(path, _)| path).collect();
+    diff
+}
+
+/// Collects the recipe nodes of a tree by their paths relative to the root.
+fn collect_recipes<'a>(
+    tree: &'a RecipeTree,
+    root: &Utf8Path,
+    recipes: &mut BTreeMap<Utf8PathBuf, &'a RecipeTree>,
+) {
+    if tree.recipe.is_some() {
+        let path = tree.path.strip_prefix(root).unwrap_or(&tree.path);
+        recipes.insert(path.to_path_buf(), tree);
+    }
+    for child in tree.children.values() {
+        collect_recipes(child, root, recipes);
+    }
+}
+
+/// Returns the changed metadata keys of a recipe found in both trees, or
+/// `None` if it did not change.
+fn modification(old: &RecipeTree, new: &RecipeTree) -> Option<Vec<String>> {
+    let (Some(old_recipe), Some(new_recipe)) = (&old.recipe, &new.recipe) else {
+        return None;
+    };
+    let metadata_keys = changed_keys(old_recipe.metadata(), new_recipe.metadata());
+    let content_changed = match (old.hash, new.hash, old.stamp, new.stamp) {
+        (Some(old_hash), Some(new_hash), _, _) => old_hash != new_hash,
+        (_, _, Some(old_stamp), Some(new_stamp)) => old_stamp != new_stamp,
+        _ => false,
+    };
+    (content_changed || !metadata_keys.is_empty()).then_some(metadata_keys)
+}
+
+/// Returns the keys whose values differ between two sets of metadata.
+fn changed_keys(old: &Metadata, new: &Metadata) -> Vec<String> {
+    let keys: BTreeSet<&str> = old.iter().chain(new.iter()).map(|(key, _)| key).collect();
+    keys.into_iter()
+        .filter(|key| old.get(key) != new.get(key))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,168 @@
+//! Per-directory settings read from `_dir.yaml` files.
+
+use crate::diagnostics::SkippedFile;
+use crate::model::{Metadata, RecipeEntryError};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Name of the file holding the settings of a recipe directory.
+pub const DIR_CONFIG_FILE: &str = "_dir.yaml";
+
+/// Settings of a recipe directory, read from its [`DIR_CONFIG_FILE`].
+///
+/// ```yaml
+/// title: Breakfast & Brunch
+/// icon: 🥞
+/// description: For slow weekend mornings
+/// cover: cover.jpg
+/// tags: [breakfast]
+/// metadata:
+///   course: breakfast
+/// order:
+///   - Starters
+///   - Pancakes
+///   - Waffles
+/// ```
+///
+/// Recipes inherit the `tags` and `metadata` of every directory they are
+/// in: the tags are added to their own, and metadata fields they don't set
+/// themselves are filled in, taking those of nearer directories first.
+/// Inherited values show in the metadata of recipes in a
+/// [`RecipeTree`](super::RecipeTree), and are matched by searches and tree
+/// filters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirConfig {
+    /// Displayed name of the directory, used as the name of its tree node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Icon of the directory, e.g. an emoji or the name of an icon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Description of the directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Cover image of the directory, as a path relative to it or a URL;
+    /// see [`RecipeTree::cover_image`](super::RecipeTree::cover_image)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    /// Tags added to every recipe in the directory and below
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Metadata fields every recipe in the directory and below has, unless
+    /// it sets them itself
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, Value>,
+    /// Names of the directory's children in the order they are listed with
+    /// [`TreeSort::Custom`](super::TreeSort::Custom)
+    ///
+    /// Entries match a child's name or file name, ignoring case and
+    /// accents, and may leave out the `.cook` extension.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+}
+
+impl DirConfig {
+    /// Reads the settings of a directory.
+    ///
+    /// Returns the default settings if the directory has no config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RecipeEntryError` if the config file cannot be read or parsed.
+    pub fn load(dir: &Utf8Path) -> Result<Self, RecipeEntryError> {
+        Ok(Self::read(dir)?.unwrap_or_default())
+    }
+
+    /// Reads the settings of a directory, or returns `None` if it has no
+    /// config file or the file is empty.
+    pub(crate) fn read(dir: &Utf8Path) -> Result<Option<Self>, RecipeEntryError> {
+        let content = match fs::read_to_string(dir.join(DIR_CONFIG_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+        serde_yaml::from_str(&content)
+            .map(Some)
+            .map_err(|e| RecipeEntryError::MetadataError(e.to_string()))
+    }
+
+    /// Returns the metadata recipes in the directory inherit, given what
+    /// the directory itself inherits from its parents.
+    pub(crate) fn defaults(&self, inherited: &Metadata) -> Metadata {
+        let mut defaults = Metadata::from_fields(self.metadata.clone(), &self.tags);
+        defaults.inherit(inherited);
+        defaults
+    }
+}
+
+/// Reads the settings of a directory for its tree node, recording a config
+/// file that cannot be read in `skipped`.
+pub(crate) fn read_config(dir: &Utf8Path, skipped: &mut Vec<SkippedFile>) -> Option<DirConfig> {
+    DirConfig::read(dir).unwrap_or_else(|e| {
+        skipped.push(SkippedFile::new(dir.join(DIR_CONFIG_FILE), e));
+        None
+    })
+}
+
+/// The metadata recipe files below some base directories inherit from
+/// their directory configs, read once per directory as needed.
+///
+/// Config files that cannot be read are ignored; tree builds report them.
+#[derive(Debug)]
+pub(crate) struct InheritedMetadata {
+    base_dirs: Vec<Utf8PathBuf>,
+    dirs: Mutex<HashMap<Utf8PathBuf, Arc<Metadata>>>,
+}
+
+impl InheritedMetadata {
+    pub(crate) fn new<P: AsRef<Utf8Path>>(base_dirs: &[P]) -> Self {
+        InheritedMetadata {
+            base_dirs: base_dirs
+                .iter()
+                .map(|dir| dir.as_ref().to_path_buf())
+                .collect(),
+            dirs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the metadata a recipe file inherits, or `None` if it doesn't
+    /// inherit any.
+    pub(crate) fn for_file(&self, path: &Utf8Path) -> Option<Arc<Metadata>> {
+        let dir = path.parent()?;
+        let base_dir = self
+            .base_dirs
+            .iter()
+            .filter(|base_dir| dir.starts_with(base_dir))
+            .max_by_key(|base_dir| base_dir.as_str().len())?;
+        let inherited = self.for_dir(base_dir, dir);
+        (!inherited.is_empty()).then_some(inherited)
+    }
+
+    fn for_dir(&self, base_dir: &Utf8Path, dir: &Utf8Path) -> Arc<Metadata> {
+        if let Some(inherited) = self.dirs.lock().unwrap().get(dir) {
+            return inherited.clone();
+        }
+        let parent = match dir.parent() {
+            Some(parent) if dir != base_dir => self.for_dir(base_dir, parent),
+            _ => Arc::new(Metadata::default()),
+        };
+        let inherited = match DirConfig::read(dir) {
+            Ok(Some(config)) => Arc::new(config.defaults(&parent)),
+            _ => parent,
+        };
+        self.dirs
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), inherited.clone());
+        inherited
+    }
+}
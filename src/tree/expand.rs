@@ -0,0 +1,105 @@
+//! Listing directories left out of a tree by its depth limit.
+
+use super::{assemble, check_dir, load_recipe, scan_listing, RecipeTree, TreeError, TreeOptions};
+use crate::diagnostics::SkippedFile;
+use camino::Utf8Path;
+use rayon::prelude::*;
+
+impl RecipeTree {
+    /// Lists the content of a directory of this tree, which must be the
+    /// root built for its base directory, replacing the children and assets
+    /// of the directory's node.
+    ///
+    /// This is how the [`unexpanded`](RecipeTree::unexpanded) directories of
+    /// a tree built with [`TreeOptions::max_depth`] are filled in when an
+    /// app opens them. The directory is listed up to `options.max_depth`
+    /// levels deep, with directories below that again included as
+    /// unexpanded nodes, and its recipes are read or, with
+    /// [`TreeOptions::lazy`], prepared to be read later. Only the
+    /// directories on the way to it are walked besides, so that their
+    /// `.cookignore` files apply. Expanding a directory that was listed
+    /// before lists it again.
+    ///
+    /// Returns the files that were left out because they could not be
+    /// listed or read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cooklang_find::{build_tree_with_options, TreeOptions};
+    ///
+    /// let options = TreeOptions {
+    ///     max_depth: Some(0),
+    ///     lazy: true,
+    ///     ..Default::default()
+    /// };
+    /// let mut tree = build_tree_with_options("./recipes", &options)?;
+    /// let breakfast = tree.child("breakfast").map(|node| node.path.clone());
+    /// if let Some(path) = breakfast {
+    ///     tree.expand(&path, &options)?;
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `TreeError::DirectoryNotFound` if no directory node of the
+    /// tree has the given path, or the directory no longer exists.
+    pub fn expand<P: AsRef<Utf8Path>>(
+        &mut self,
+        dir: P,
+        options: &TreeOptions,
+    ) -> Result<Vec<SkippedFile>, TreeError> {
+        let dir = dir.as_ref();
+        let base_dir = self.path.clone();
+        let node = self
+            .node_at_mut(dir)
+            .filter(|node| node.recipe.is_none())
+            .ok_or_else(|| TreeError::DirectoryNotFound(dir.to_string()))?;
+        check_dir(dir)?;
+
+        let mut skipped = Vec::new();
+        let mut listing = scan_listing(&base_dir, Some(dir), options, &mut skipped);
+        let paths = std::mem::take(&mut listing.recipes);
+        let recipes: Vec<_> = paths
+            .par_iter()
+            .map(|path| load_recipe(path, options))
+            .collect();
+
+        // The tree assembled holds the directory and those on the way to it
+        let mut listed = assemble(
+            &base_dir,
+            paths.into_iter().zip(recipes),
+            &listing,
+            options,
+            &mut skipped,
+        )?;
+        match listed.node_at_mut(dir) {
+            Some(listed) => {
+                node.config = listed.config.take();
+                node.children = std::mem::take(&mut listed.children);
+                node.assets = std::mem::take(&mut listed.assets);
+            }
+            None => {
+                node.children.clear();
+                node.assets.clear();
+            }
+        }
+        node.unexpanded = false;
+        Ok(skipped)
+    }
+
+    /// Returns the node with the given path below this one.
+    fn node_at_mut(&mut self, path: &Utf8Path) -> Option<&mut RecipeTree> {
+        let relative = path.strip_prefix(&self.path).ok()?;
+        let mut current = self;
+        for component in relative.components() {
+            let path = current.path.join(component.as_str());
+            current = current
+                .children
+                .values_mut()
+                .find(|child| child.path == path)?;
+        }
+        Some(current)
+    }
+}
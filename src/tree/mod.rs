@@ -3,13 +3,39 @@
 //! This module provides functionality to build hierarchical tree structures
 //! that represent the organization of recipe files within a directory tree.
 
-use crate::model::{RecipeEntry, RecipeEntryError};
+use crate::diagnostics::{Partial, SkippedFile};
+use crate::model::{Metadata, RecipeEntry, RecipeEntryError};
+use crate::walk::{content_hash, scan_scoped, FileStamp, Listing, Scope};
+use assets::is_listed;
 use camino::{Utf8Path, Utf8PathBuf};
-use glob::glob;
+use collision::{dir_entry, recipe_key};
+use dir_config::read_config;
+use rayon::prelude::*;
+use sort::sort_tree;
+use std::fs;
 use thiserror::Error;
 
+mod assets;
+mod collision;
+mod diff;
+mod dir_config;
+mod expand;
 mod model;
+mod options;
+mod refresh;
+mod sort;
+mod update;
+mod view;
+pub use assets::{Asset, AssetKind};
+pub use collision::NameCollision;
+pub use diff::{diff_trees, ModifiedRecipe, MovedRecipe, TreeDiff};
+pub(crate) use dir_config::InheritedMetadata;
+pub use dir_config::{DirConfig, DIR_CONFIG_FILE};
 pub use model::RecipeTree;
+pub use options::TreeOptions;
+pub use refresh::{refresh_tree, TreeRefresh};
+pub use sort::TreeSort;
+pub use view::RecipeFilter;
 
 /// Errors that can occur when building a recipe tree.
 #[derive(Error, Debug)]
@@ -37,7 +63,10 @@ pub enum TreeError {
 ///
 /// This function recursively scans the specified directory and all its
 /// subdirectories for .cook and .menu files, organizing them into a tree
-/// structure that mirrors the filesystem hierarchy.
+/// structure that mirrors the filesystem hierarchy. Files and directories
+/// excluded by `.cookignore` files (see [`crate::walk`]) are left out.
+/// Recipe files are read in parallel, and their title and step images are
+/// taken from the same directory walk.
 ///
 /// # Arguments
 ///
@@ -66,75 +95,294 @@ pub enum TreeError {
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 pub fn build_tree<P: AsRef<Utf8Path>>(base_dir: P) -> Result<RecipeTree, TreeError> {
+    build_tree_with_options(base_dir, &TreeOptions::default())
+}
+
+/// Builds a recipe tree like [`build_tree`], with options controlling
+/// which files are included.
+///
+/// See [`TreeOptions`] for the available settings. Recipe files that
+/// cannot be read are left out; use [`build_tree_with_diagnostics`] to
+/// find out which.
+pub fn build_tree_with_options<P: AsRef<Utf8Path>>(
+    base_dir: P,
+    options: &TreeOptions,
+) -> Result<RecipeTree, TreeError> {
+    Ok(build_tree_with_diagnostics(base_dir, options)?.into_value())
+}
+
+/// Builds a recipe tree like [`build_tree_with_options`] and reports the
+/// files that were left out because they could not be listed or read.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::{build_tree_with_diagnostics, TreeOptions};
+/// use camino::Utf8Path;
+///
+/// let report = build_tree_with_diagnostics(Utf8Path::new("./recipes"), &TreeOptions::default())?;
+/// for skipped in &report.skipped {
+///     eprintln!("Skipped {}: {}", skipped.path, skipped.error);
+/// }
+/// let tree = report.value;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn build_tree_with_diagnostics<P: AsRef<Utf8Path>>(
+    base_dir: P,
+    options: &TreeOptions,
+) -> Result<Partial<RecipeTree>, TreeError> {
     let base_dir = base_dir.as_ref();
+    check_dir(base_dir)?;
+
+    let mut skipped = Vec::new();
+
+    // Find all .cook and .menu files in one walk, then read them in parallel
+    let mut listing = scan_listing(base_dir, None, options, &mut skipped);
+    let paths = std::mem::take(&mut listing.recipes);
+    let recipes: Vec<_> = paths
+        .par_iter()
+        .map(|path| load_recipe(path, options))
+        .collect();
+
+    let root = assemble(
+        base_dir,
+        paths.into_iter().zip(recipes),
+        &listing,
+        options,
+        &mut skipped,
+    )?;
+    Ok(Partial::new(root, skipped))
+}
+
+/// Walks a directory for a tree, or only the part of it below `below`,
+/// as deep and listing assets as the options ask for.
+fn scan_listing(
+    base_dir: &Utf8Path,
+    below: Option<&Utf8Path>,
+    options: &TreeOptions,
+    skipped: &mut Vec<SkippedFile>,
+) -> Listing {
+    let scope = Scope {
+        below,
+        max_depth: options.max_depth,
+        all_files: options.assets,
+    };
+    scan_scoped(base_dir, &options.walk, scope, skipped)
+}
 
-    // Check if directory exists
+/// Checks that a tree can be built from a directory.
+fn check_dir(base_dir: &Utf8Path) -> Result<(), TreeError> {
     if !base_dir.exists() {
         return Err(TreeError::DirectoryNotFound(base_dir.to_string()));
     }
     if !base_dir.is_dir() {
         return Err(TreeError::NotADirectory(base_dir.to_string()));
     }
+    Ok(())
+}
+
+/// A recipe file as read for the tree.
+struct ReadRecipe {
+    recipe: RecipeEntry,
+    /// Stamp of the file from before it was read
+    stamp: Option<FileStamp>,
+    /// Hash of the content that was read
+    hash: Option<u64>,
+}
+
+/// Reads a recipe file for the tree.
+fn read_recipe(path: &Utf8Path) -> Result<ReadRecipe, RecipeEntryError> {
+    let stamp = FileStamp::of(path).ok();
+    let content = fs::read_to_string(path)?;
+    let recipe = RecipeEntry::from_path_with_content(path.to_path_buf(), &content)?;
+    Ok(ReadRecipe {
+        recipe,
+        stamp,
+        hash: Some(content_hash(content.as_bytes())),
+    })
+}
+
+/// Reads a recipe file for the tree, or with [`TreeOptions::lazy`] only
+/// prepares it to be read later.
+fn load_recipe(path: &Utf8Path, options: &TreeOptions) -> Result<ReadRecipe, RecipeEntryError> {
+    if !options.lazy {
+        return read_recipe(path);
+    }
+    Ok(ReadRecipe {
+        recipe: RecipeEntry::from_path_lazy(path.to_path_buf()),
+        stamp: FileStamp::of(path).ok(),
+        hash: None,
+    })
+}
 
+/// Returns the displayed name of a recipe node: the recipe's name, or for
+/// lazy trees, whose recipes are not read yet, the file name.
+fn recipe_name(recipe: &RecipeEntry, path: &Utf8Path, options: &TreeOptions) -> String {
+    if options.lazy {
+        path.file_stem().unwrap_or(path.as_str()).to_string()
+    } else {
+        recipe.name().clone().unwrap()
+    }
+}
+
+/// Builds the tree of a directory from its listing and recipe files in the
+/// order listed, then sorts it.
+///
+/// Recipes that could not be read are recorded in `skipped`.
+fn assemble(
+    base_dir: &Utf8Path,
+    recipes: impl IntoIterator<Item = (Utf8PathBuf, Result<ReadRecipe, RecipeEntryError>)>,
+    listing: &Listing,
+    options: &TreeOptions,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<RecipeTree, TreeError> {
     let base_name = base_dir
         .file_name()
         .map(|n| n.to_string())
         .unwrap_or_else(|| String::from("./"));
 
-    let mut root = RecipeTree::new(base_name, base_dir.to_path_buf());
-
-    // First, find all .cook and .menu files in this directory and subdirectories
-    let patterns = vec![
-        base_dir.join("**/*.cook").to_string(),
-        base_dir.join("**/*.menu").to_string(),
-    ];
-
-    for pattern in patterns {
-        for entry in glob(&pattern)? {
-            let path = entry?;
-            let path = Utf8PathBuf::from_path_buf(path).map_err(|_| {
-                TreeError::StripPrefixError("Path contains invalid UTF-8".to_string())
-            })?;
-            let recipe = match RecipeEntry::from_path(path.clone()) {
-                Ok(r) => r,
-                Err(_) => continue, // Skip files whose content isn't available (e.g. iCloud)
-            };
-
-            // Calculate the relative path from the base directory
-            let rel_path = path
-                .strip_prefix(base_dir)
-                .map_err(|_| TreeError::StripPrefixError(path.to_string()))?;
-
-            // Build the tree structure
-            let mut current = &mut root;
-            let components: Vec<_> = rel_path
-                .parent()
-                .map(|p| p.components().collect())
-                .unwrap_or_default();
-
-            // Create directory nodes
-            for component in components {
-                let name = component.to_string();
-                let path = current.path.join(&name);
-                current = current
-                    .children
-                    .entry(name.clone())
-                    .or_insert_with(|| RecipeTree::new(name, path));
+    let config = read_config(base_dir, skipped);
+    let mut root = RecipeTree::new_dir(base_name, base_dir.to_path_buf(), config);
+    let root_inherited = match &root.config {
+        Some(config) => config.defaults(&Metadata::default()),
+        None => Metadata::default(),
+    };
+
+    for (path, recipe) in recipes {
+        // Skip files whose content isn't available (e.g. iCloud)
+        let ReadRecipe {
+            recipe,
+            stamp,
+            hash,
+        } = match recipe {
+            Ok(r) => r,
+            Err(e) => {
+                skipped.push(SkippedFile::new(&path, e));
+                continue;
             }
+        };
+
+        // Calculate the relative path from the base directory
+        let rel_path = path
+            .strip_prefix(base_dir)
+            .map_err(|_| TreeError::StripPrefixError(path.to_string()))?;
 
-            // Add the recipe as a leaf node
-            let name = recipe.name().clone().unwrap();
+        // Build the tree structure
+        let mut current = &mut root;
+        let components: Vec<_> = rel_path
+            .parent()
+            .map(|p| p.components().collect())
+            .unwrap_or_default();
 
-            current.children.insert(
-                name.clone(),
-                RecipeTree::new_with_recipe(name, path, recipe),
-            );
+        // Create directory nodes, collecting the metadata their recipes inherit
+        let mut inherited = root_inherited.clone();
+        for component in components {
+            let name = component.to_string();
+            let path = current.path.join(&name);
+            current = dir_entry(&mut current.children, &name, || {
+                new_dir_node(&name, path, listing, skipped)
+            });
+            if let Some(config) = &current.config {
+                inherited = config.defaults(&inherited);
+            }
         }
+
+        // Add the recipe as a leaf node, keyed by its file name; the title
+        // is only used for display
+        let recipe = recipe
+            .inherit(&inherited)
+            .with_images(listing.images_for(&path));
+        let name = recipe_name(&recipe, &path, options);
+        let key = recipe_key(&current.children, &path);
+        let linked = listing.is_linked(&path);
+        let mut node = RecipeTree::new_with_recipe(name, path, recipe);
+        node.linked = linked;
+        node.stamp = stamp;
+        node.hash = hash;
+
+        current.children.insert(key, node);
     }
 
+    if options.assets {
+        add_assets(&mut root, listing, skipped);
+    }
+    add_unexpanded(&mut root, listing, skipped);
+    sort_tree(&mut root, options.sort, options.dirs_first);
     Ok(root)
 }
 
+/// Creates the node of a directory found while assembling a tree.
+fn new_dir_node(
+    name: &str,
+    path: Utf8PathBuf,
+    listing: &Listing,
+    skipped: &mut Vec<SkippedFile>,
+) -> RecipeTree {
+    let config = read_config(&path, skipped);
+    let mut node = RecipeTree::new_dir(name.to_string(), path, config);
+    node.linked = listing.is_linked(&node.path);
+    node
+}
+
+/// Adds the directories without recipes and the assets of all directories
+/// in a listing to a tree.
+fn add_assets(root: &mut RecipeTree, listing: &Listing, skipped: &mut Vec<SkippedFile>) {
+    let base_dir = root.path.clone();
+    for dir in &listing.dirs {
+        if is_listed(&base_dir, dir) {
+            dir_node_at(root, dir, listing, skipped);
+        }
+    }
+    for (dir, files) in listing.images.iter().chain(&listing.others) {
+        let assets: Vec<Asset> = files
+            .iter()
+            .filter(|file| is_listed(&base_dir, file))
+            .map(|file| Asset::new(file.clone()))
+            .collect();
+        if assets.is_empty() {
+            continue;
+        }
+        if let Some(node) = dir_node_at(root, dir, listing, skipped) {
+            node.assets.extend(assets);
+            node.assets.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+    }
+}
+
+/// Adds the directories a listing stopped at to a tree, marked as
+/// unexpanded.
+fn add_unexpanded(root: &mut RecipeTree, listing: &Listing, skipped: &mut Vec<SkippedFile>) {
+    let base_dir = root.path.clone();
+    for dir in &listing.unexpanded {
+        if !is_listed(&base_dir, dir) {
+            continue;
+        }
+        if let Some(node) = dir_node_at(root, dir, listing, skipped) {
+            node.unexpanded = true;
+        }
+    }
+}
+
+/// Returns the node of a directory below the root, creating it and the
+/// directories above it as needed.
+fn dir_node_at<'a>(
+    root: &'a mut RecipeTree,
+    dir: &Utf8Path,
+    listing: &Listing,
+    skipped: &mut Vec<SkippedFile>,
+) -> Option<&'a mut RecipeTree> {
+    let relative = dir.strip_prefix(&root.path).ok()?.to_path_buf();
+    let mut current = root;
+    for component in relative.components() {
+        let name = component.as_str();
+        let path = current.path.join(name);
+        current = dir_entry(&mut current.children, name, || {
+            new_dir_node(name, path, listing, skipped)
+        });
+    }
+    Some(current)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +460,56 @@ mod tests {
         assert!(recipe_node.recipe.as_ref().unwrap().title_image().is_some());
     }
 
+    #[test]
+    fn test_recipe_images_come_from_the_walk() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let breakfast_dir = temp_dir_path.join("breakfast");
+        fs::create_dir_all(&breakfast_dir).unwrap();
+        create_test_recipe(&breakfast_dir, "pancakes", "Make pancakes");
+        create_test_recipe(&breakfast_dir, "pancakes deluxe", "Make better pancakes");
+        let title_image = create_test_image(&breakfast_dir, "pancakes", "png");
+        let step_image = create_test_image(&breakfast_dir, "pancakes.2", "jpg");
+        create_test_image(&breakfast_dir, "pancakes deluxe.1", "jpg");
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+
+        // The images were resolved from the walk, not looked up afterwards
+        fs::remove_file(&title_image).unwrap();
+        fs::remove_file(&step_image).unwrap();
+        let breakfast = tree.children.get("breakfast").unwrap();
+        let recipe = breakfast.children["pancakes"].recipe.as_ref().unwrap();
+        assert_eq!(recipe.title_image().as_deref(), Some(title_image.as_str()));
+        let step_images = recipe.step_images();
+        assert_eq!(step_images.count(), 1);
+        assert_eq!(step_images.get(0, 2), Some(&step_image.to_string()));
+
+        let deluxe = breakfast.children["pancakes deluxe"]
+            .recipe
+            .as_ref()
+            .unwrap();
+        assert!(deluxe.title_image().is_none());
+        assert_eq!(deluxe.step_images().count(), 1);
+    }
+
+    #[test]
+    fn test_recipe_images_with_upper_case_extensions() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&temp_dir_path, "Pancakes", "Make pancakes");
+        let title_image = create_test_image(&temp_dir_path, "Pancakes", "JPG");
+        let step_image = create_test_image(&temp_dir_path, "Pancakes.1", "Jpg");
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+
+        let recipe = tree.children["Pancakes"].recipe.as_ref().unwrap();
+        assert_eq!(recipe.title_image().as_deref(), Some(title_image.as_str()));
+        assert_eq!(
+            recipe.step_images().get(0, 1),
+            Some(&step_image.to_string())
+        );
+    }
+
     #[test]
     fn test_nested_directories() {
         let temp_dir = TempDir::new().unwrap();
@@ -305,6 +603,938 @@ mod tests {
         assert_eq!(recipe.name, "deep_recipe");
     }
 
+    #[test]
+    fn test_unicode_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let desserts = temp_dir_path.join("De\u{301}sserts");
+        fs::create_dir_all(&desserts).unwrap();
+        let decomposed = "Cre\u{300}me Bru\u{302}le\u{301}e";
+        create_test_recipe(&desserts, decomposed, "Caramelize @sugar{}");
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+
+        // Keys are composed, names keep the original spelling
+        let dir_node = tree.children.get("Désserts").unwrap();
+        assert_eq!(dir_node.name, "De\u{301}sserts");
+        let recipe_node = dir_node.children.get("Crème Brûlée").unwrap();
+        assert_eq!(recipe_node.name, decomposed);
+
+        // Lookups ignore case and diacritics
+        let found = tree
+            .child("desserts")
+            .and_then(|node| node.child("CREME BRULEE"))
+            .unwrap();
+        assert_eq!(found.path, recipe_node.path);
+        assert!(tree.child("cakes").is_none());
+    }
+
+    #[test]
+    fn test_unreadable_recipe_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        create_test_recipe(&temp_dir_path, "pancakes", "Mix @flour{200%g}");
+        fs::write(temp_dir_path.join("broken.cook"), b"Mix @\xff\xfe{}").unwrap();
+
+        let report = build_tree_with_diagnostics(&temp_dir_path, &TreeOptions::default()).unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, temp_dir_path.join("broken.cook"));
+        assert_eq!(report.value.children.len(), 1);
+        assert!(report.value.children.contains_key("pancakes"));
+
+        // The plain variant returns the same partial tree
+        let tree = build_tree(&temp_dir_path).unwrap();
+        assert_eq!(tree.children.len(), 1);
+    }
+
+    #[test]
+    fn test_ignored_files_are_left_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        for dir in ["archive", "node_modules/pkg", "desserts"] {
+            fs::create_dir_all(temp_dir_path.join(dir)).unwrap();
+            create_test_recipe(&temp_dir_path.join(dir), "cake", "Bake @flour{}");
+        }
+        fs::write(temp_dir_path.join(".cookignore"), "archive/\n").unwrap();
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+        let mut names: Vec<_> = tree.children.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["desserts", "node_modules"]);
+
+        let options = TreeOptions {
+            walk: crate::WalkOptions {
+                exclude: vec!["node_modules/".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tree = build_tree_with_options(&temp_dir_path, &options).unwrap();
+        assert_eq!(tree.children.keys().collect::<Vec<_>>(), vec!["desserts"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_linked_nodes() {
+        use crate::walk::{SymlinkPolicy, WalkOptions};
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let shared_dir = temp_dir_path.join("shared");
+        fs::create_dir_all(&shared_dir).unwrap();
+        create_test_recipe(&shared_dir, "cake", "Bake a cake");
+        symlink(&shared_dir, temp_dir_path.join("desserts")).unwrap();
+        symlink(&temp_dir_path, shared_dir.join("loop")).unwrap();
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+        let desserts = tree.child("desserts").unwrap();
+        assert!(desserts.linked);
+        assert!(desserts.child("cake").unwrap().linked);
+        let shared = tree.child("shared").unwrap();
+        assert!(!shared.linked);
+        assert!(!shared.child("cake").unwrap().linked);
+        assert!(shared.child("loop").is_none());
+
+        let options = TreeOptions {
+            walk: WalkOptions {
+                symlinks: SymlinkPolicy::FollowOnce,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tree = build_tree_with_options(&temp_dir_path, &options).unwrap();
+        let names: Vec<_> = tree.children.keys().collect();
+        assert_eq!(names, vec!["shared"]);
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let breakfast_dir = temp_dir_path.join("breakfast");
+        fs::create_dir_all(&breakfast_dir).unwrap();
+        create_test_recipe(&breakfast_dir, "pancakes", "Make pancakes");
+        create_test_recipe(&temp_dir_path, "breakfast", "A breakfast recipe");
+        let soup_cook = create_test_recipe(&temp_dir_path, "Soup", "Boil");
+        let soup_menu = temp_dir_path.join("Soup.menu");
+        fs::write(&soup_menu, "A soup menu").unwrap();
+        let titled = indoc! {r#"
+            ---
+            title: Stew
+            ---
+            Simmer"#};
+        create_test_recipe(&temp_dir_path, "beef stew", titled);
+        create_test_recipe(&temp_dir_path, "lamb stew", titled);
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+        assert_eq!(
+            keys(&tree),
+            vec![
+                "beef stew",
+                "breakfast",
+                "breakfast.cook",
+                "lamb stew",
+                "Soup",
+                "Soup.menu"
+            ]
+        );
+        assert!(tree.children["breakfast"].recipe.is_none());
+        assert!(tree.children["breakfast.cook"].recipe.is_some());
+        assert_eq!(tree.children["Soup"].path, soup_cook);
+        assert_eq!(tree.children["Soup.menu"].path, soup_menu);
+        assert_eq!(tree.children["beef stew"].name, "Stew");
+        // Titles still find recipes when no key matches
+        assert_eq!(
+            tree.child("stew").unwrap().path,
+            tree.children["beef stew"].path
+        );
+
+        let collisions = tree.collisions();
+        let keys: Vec<_> = collisions.iter().map(|c| c.keys.clone()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                vec!["breakfast", "breakfast.cook"],
+                vec!["Soup", "Soup.menu"],
+                vec!["beef stew", "lamb stew"],
+            ]
+        );
+        assert_eq!(collisions[2].name, "Stew");
+        assert_eq!(collisions[1].paths, vec![soup_cook, soup_menu]);
+        assert!(collisions.iter().all(|c| c.parent == temp_dir_path));
+    }
+
+    fn sorted_tree(dir: &Utf8Path, sort: TreeSort, dirs_first: bool) -> Partial<RecipeTree> {
+        let options = TreeOptions {
+            sort,
+            dirs_first,
+            ..Default::default()
+        };
+        build_tree_with_diagnostics(dir, &options).unwrap()
+    }
+
+    fn keys(tree: &RecipeTree) -> Vec<&str> {
+        tree.children.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_sorted_children() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::create_dir_all(temp_dir_path.join("Soups")).unwrap();
+        create_test_recipe(&temp_dir_path, "10 Pie", "Bake");
+        create_test_recipe(&temp_dir_path, "2 Cake", "Bake");
+        create_test_recipe(&temp_dir_path, "éclair", "Bake");
+        create_test_recipe(&temp_dir_path.join("Soups"), "Leek", "Boil");
+        create_test_recipe(
+            &temp_dir_path,
+            "Apple",
+            indoc! {r#"
+                ---
+                title: Zesty Apple
+                ---
+                Bake"#},
+        );
+
+        let tree = sorted_tree(&temp_dir_path, TreeSort::Name, false).value;
+        assert_eq!(
+            keys(&tree),
+            vec!["10 Pie", "2 Cake", "Apple", "éclair", "Soups"]
+        );
+
+        let tree = sorted_tree(&temp_dir_path, TreeSort::Natural, true).value;
+        assert_eq!(
+            keys(&tree),
+            vec!["Soups", "2 Cake", "10 Pie", "Apple", "éclair"]
+        );
+
+        // Recipes are keyed by file name, but displayed with their title
+        let tree = sorted_tree(&temp_dir_path, TreeSort::Title, false).value;
+        assert_eq!(
+            keys(&tree),
+            vec!["10 Pie", "2 Cake", "éclair", "Soups", "Apple"]
+        );
+
+        // Serialized output follows the same order
+        let json = serde_json::to_string(&tree).unwrap();
+        let positions: Vec<_> = keys(&tree)
+            .iter()
+            .map(|key| json.find(&format!("\"{key}\":{{")).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_sorted_by_modification_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, age) in [("old", 300), ("new", 0), ("middle", 100)] {
+            let path = create_test_recipe(&temp_dir_path, name, "Cook");
+            let file = fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+
+        let tree = sorted_tree(&temp_dir_path, TreeSort::Modified, false).value;
+        assert_eq!(keys(&tree), vec!["new", "middle", "old"]);
+    }
+
+    #[test]
+    fn test_custom_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let soups = temp_dir_path.join("Soups");
+        fs::create_dir_all(&soups).unwrap();
+        for name in ["Pancakes", "Waffles", "Omelette", "Toast"] {
+            create_test_recipe(&temp_dir_path, name, "Cook");
+        }
+        create_test_recipe(&soups, "Leek", "Boil");
+        create_test_recipe(&soups, "Tomato", "Boil");
+        fs::write(
+            temp_dir_path.join(DIR_CONFIG_FILE),
+            "order:\n  - waffles.cook\n  - Soups\n  - missing\n  - Pancakes\n",
+        )
+        .unwrap();
+        fs::write(soups.join(DIR_CONFIG_FILE), "order: [\n").unwrap();
+
+        let report = sorted_tree(&temp_dir_path, TreeSort::Custom, false);
+        let tree = report.value;
+        assert_eq!(
+            keys(&tree),
+            vec!["Waffles", "Soups", "Pancakes", "Omelette", "Toast"]
+        );
+        // An invalid config falls back to natural order and is reported
+        assert_eq!(keys(&tree.children["Soups"]), vec!["Leek", "Tomato"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, soups.join(DIR_CONFIG_FILE));
+    }
+
+    #[test]
+    fn test_assets() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cards = temp_dir_path.join("cards");
+        let breakfast = temp_dir_path.join("breakfast");
+        fs::create_dir_all(&cards).unwrap();
+        fs::create_dir_all(&breakfast).unwrap();
+        fs::create_dir_all(temp_dir_path.join(".git")).unwrap();
+        fs::create_dir_all(temp_dir_path.join("empty")).unwrap();
+        create_test_recipe(&breakfast, "pancakes", "Mix @flour");
+        create_test_image(&breakfast, "pancakes", "jpg");
+        fs::write(breakfast.join("notes.md"), "Use buttermilk").unwrap();
+        fs::write(breakfast.join(DIR_CONFIG_FILE), "title: Breakfast").unwrap();
+        fs::write(cards.join("Grandma's Pie.PDF"), "%PDF").unwrap();
+        fs::write(cards.join("scan.heic"), "").unwrap();
+        fs::write(temp_dir_path.join(".DS_Store"), "").unwrap();
+        fs::write(temp_dir_path.join(".git").join("HEAD"), "ref").unwrap();
+        fs::write(temp_dir_path.join("README.txt"), "My recipes").unwrap();
+
+        // Assets are opt-in
+        let tree = build_tree(&temp_dir_path).unwrap();
+        assert_eq!(keys(&tree), vec!["breakfast"]);
+        assert!(tree.children["breakfast"].assets.is_empty());
+
+        let options = TreeOptions {
+            assets: true,
+            ..Default::default()
+        };
+        let tree = build_tree_with_options(&temp_dir_path, &options).unwrap();
+        assert_eq!(keys(&tree), vec!["breakfast", "cards", "empty"]);
+        let kinds = |node: &RecipeTree| {
+            node.assets
+                .iter()
+                .map(|asset| (asset.file_name().to_string(), asset.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(&tree),
+            vec![("README.txt".to_string(), AssetKind::Note)]
+        );
+        assert_eq!(
+            kinds(&tree.children["breakfast"]),
+            vec![
+                ("notes.md".to_string(), AssetKind::Note),
+                ("pancakes.jpg".to_string(), AssetKind::Image),
+            ]
+        );
+        assert_eq!(
+            kinds(&tree.children["cards"]),
+            vec![
+                ("Grandma's Pie.PDF".to_string(), AssetKind::Document),
+                ("scan.heic".to_string(), AssetKind::Unknown),
+            ]
+        );
+        assert_eq!(
+            tree.children["cards"].assets[0].path,
+            cards.join("Grandma's Pie.PDF")
+        );
+        assert!(tree.children["empty"].assets.is_empty());
+        // Recipes still find their images
+        let pancakes = tree.children["breakfast"].children["pancakes"]
+            .recipe
+            .as_ref()
+            .unwrap();
+        assert!(pancakes.title_image().is_some());
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let snapshot: RecipeTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            snapshot.children["cards"].assets,
+            tree.children["cards"].assets
+        );
+        let refresh = refresh_tree(&snapshot, &options).unwrap().into_value();
+        assert_eq!(keys(&refresh.tree), vec!["breakfast", "cards", "empty"]);
+    }
+
+    #[test]
+    fn test_lazy_tree_with_unreadable_recipe() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::write(temp_dir_path.join(DIR_CONFIG_FILE), "tags: [winter]").unwrap();
+        let soup = temp_dir_path.join("soup.cook");
+        fs::write(&soup, b"---\ntags: [hot\xff]\n---\nBoil").unwrap();
+
+        let options = TreeOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let report = build_tree_with_diagnostics(&temp_dir_path, &options).unwrap();
+        assert!(report.skipped.is_empty());
+
+        // The error is reported by try_metadata, and not kept once fixed
+        let recipe = report.value.children["soup"].recipe.as_ref().unwrap();
+        assert!(recipe.try_metadata().is_err());
+        assert_eq!(recipe.tags(), vec!["winter"]);
+        fs::write(&soup, "---\ntags: [hot]\n---\nBoil").unwrap();
+        assert_eq!(recipe.try_metadata().unwrap().tags(), vec!["hot", "winter"]);
+        assert_eq!(recipe.tags(), vec!["hot", "winter"]);
+    }
+
+    #[test]
+    fn test_lazy_depth_limited_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let breakfast = temp_dir_path.join("breakfast");
+        let eggs = breakfast.join("eggs");
+        for dir in [
+            &eggs,
+            &temp_dir_path.join("archive"),
+            &temp_dir_path.join(".git"),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(
+            temp_dir_path.join(".cookignore"),
+            "archive/\n*.draft.cook\n",
+        )
+        .unwrap();
+        fs::write(breakfast.join(DIR_CONFIG_FILE), "tags: [breakfast]").unwrap();
+        create_test_recipe(&temp_dir_path, "soup", "Boil @water");
+        create_test_recipe(&temp_dir_path.join("archive"), "cake", "Bake");
+        let pancakes = create_test_recipe(&breakfast, "pancakes", "Mix @flour");
+        create_test_image(&breakfast, "pancakes", "jpg");
+        create_test_recipe(&eggs, "omelette", "Whisk @eggs");
+        create_test_recipe(&eggs, "frittata.draft", "Bake @eggs");
+
+        let options = TreeOptions {
+            max_depth: Some(0),
+            lazy: true,
+            ..Default::default()
+        };
+        let report = build_tree_with_diagnostics(&temp_dir_path, &options).unwrap();
+        assert!(report.skipped.is_empty());
+        let mut tree = report.value;
+        assert_eq!(keys(&tree), vec!["breakfast", "soup"]);
+        assert!(tree.children["breakfast"].unexpanded);
+        assert!(tree.children["breakfast"].children.is_empty());
+        assert!(!tree.children["soup"].unexpanded);
+
+        // Snapshots keep unexpanded directories and unread recipes
+        let json = serde_json::to_string(&tree).unwrap();
+        let snapshot: RecipeTree = serde_json::from_str(&json).unwrap();
+        assert!(snapshot.children["breakfast"].unexpanded);
+        assert!(!json.contains("\"metadata\""));
+
+        let skipped = tree.expand(&breakfast, &options).unwrap();
+        assert!(skipped.is_empty());
+        let node = &tree.children["breakfast"];
+        assert!(!node.unexpanded);
+        assert_eq!(keys(node), vec!["eggs", "pancakes"]);
+        assert!(node.children["eggs"].unexpanded);
+        assert_eq!(node.children["pancakes"].name, "pancakes");
+
+        // Recipes are read when their metadata is first needed
+        fs::write(&pancakes, "---\ntitle: Fluffy Pancakes\n---\nMix @flour").unwrap();
+        let recipe = node.children["pancakes"].recipe.as_ref().unwrap();
+        assert_eq!(recipe.name().as_deref(), Some("Fluffy Pancakes"));
+        assert_eq!(recipe.tags(), vec!["breakfast"]);
+        assert!(recipe.title_image().is_some());
+
+        // Ignore files above the expanded directory still apply
+        tree.expand(&eggs, &options).unwrap();
+        let eggs_node = &tree.children["breakfast"].children["eggs"];
+        assert_eq!(keys(eggs_node), vec!["omelette"]);
+        assert!(tree
+            .expand(temp_dir_path.join("missing"), &options)
+            .is_err());
+        assert!(tree.expand(&pancakes, &options).is_err());
+
+        // Without lazy, recipes are read while listing
+        let options = TreeOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let tree = build_tree_with_options(&temp_dir_path, &options).unwrap();
+        let node = &tree.children["breakfast"];
+        assert_eq!(node.children["pancakes"].name, "Fluffy Pancakes");
+        assert!(node.children["eggs"].unexpanded);
+        assert!(node.children["eggs"].children.is_empty());
+    }
+
+    #[test]
+    fn test_dir_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let breakfast = temp_dir_path.join("breakfast");
+        let eggs = breakfast.join("eggs");
+        fs::create_dir_all(&eggs).unwrap();
+        fs::write(
+            breakfast.join(DIR_CONFIG_FILE),
+            indoc! {"
+                title: Breakfast & Brunch
+                icon: 🥞
+                description: For slow mornings
+                cover: cover.jpg
+                tags: [breakfast]
+                metadata:
+                  course: breakfast
+                  servings: 2
+            "},
+        )
+        .unwrap();
+        fs::write(
+            eggs.join(DIR_CONFIG_FILE),
+            "tags: [eggs]\nmetadata:\n  servings: 1\n",
+        )
+        .unwrap();
+        create_test_recipe(&breakfast, "pancakes", "---\ntags: sweet\n---\nMix @flour");
+        create_test_recipe(&eggs, "omelette", "---\ncourse: lunch\n---\nWhisk @eggs");
+        create_test_recipe(&temp_dir_path, "soup", "Boil @water");
+
+        let report = build_tree_with_diagnostics(&temp_dir_path, &TreeOptions::default()).unwrap();
+        assert!(report.skipped.is_empty());
+        let tree = report.value;
+        assert!(tree.config.is_none());
+
+        let node = &tree.children["breakfast"];
+        assert_eq!(node.name, "Breakfast & Brunch");
+        let config = node.config.as_ref().unwrap();
+        assert_eq!(config.icon.as_deref(), Some("🥞"));
+        assert_eq!(config.description.as_deref(), Some("For slow mornings"));
+        assert_eq!(
+            node.cover_image(),
+            Some(breakfast.join("cover.jpg").into_string())
+        );
+        assert_eq!(tree.child("breakfast & brunch").unwrap().path, breakfast);
+
+        // Tags add up, and nearer directories and the recipe itself win
+        let pancakes = node.children["pancakes"].recipe.as_ref().unwrap();
+        assert_eq!(pancakes.tags(), vec!["sweet", "breakfast"]);
+        assert_eq!(pancakes.metadata().servings(), Some(2));
+        let omelette = node.children["eggs"].children["omelette"]
+            .recipe
+            .as_ref()
+            .unwrap();
+        assert_eq!(omelette.tags(), vec!["eggs", "breakfast"]);
+        assert_eq!(omelette.metadata().servings(), Some(1));
+        assert_eq!(
+            omelette.metadata().get("course").and_then(|v| v.as_str()),
+            Some("lunch")
+        );
+        assert!(tree.children["soup"]
+            .recipe
+            .as_ref()
+            .unwrap()
+            .tags()
+            .is_empty());
+
+        let filtered = tree.filter_recipes(&RecipeFilter {
+            tags: vec!["breakfast".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            filtered.children.keys().collect::<Vec<_>>(),
+            vec!["breakfast"]
+        );
+
+        // Refreshing picks up changed configs for unchanged recipes
+        fs::write(eggs.join(DIR_CONFIG_FILE), "tags: [protein]\n").unwrap();
+        let refresh = refresh_tree(&tree, &TreeOptions::default())
+            .unwrap()
+            .into_value();
+        assert!(refresh.changes.is_empty());
+        let eggs_node = &refresh.tree.children["breakfast"].children["eggs"];
+        let omelette = eggs_node.children["omelette"].recipe.as_ref().unwrap();
+        assert_eq!(omelette.tags(), vec!["protein", "breakfast"]);
+        assert_eq!(omelette.metadata().servings(), Some(2));
+
+        fs::write(eggs.join(DIR_CONFIG_FILE), "tags: [\n").unwrap();
+        let report = build_tree_with_diagnostics(&temp_dir_path, &TreeOptions::default()).unwrap();
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, eggs.join(DIR_CONFIG_FILE));
+        assert!(report.value.children["breakfast"].children["eggs"]
+            .config
+            .is_none());
+    }
+
+    #[test]
+    fn test_apply_watch_events() {
+        use crate::watch::WatchEvent;
+
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let breakfast_dir = temp_dir_path.join("breakfast");
+        fs::create_dir_all(&breakfast_dir).unwrap();
+        let pancakes = create_test_recipe(&breakfast_dir, "pancakes", "Make pancakes");
+        let soup = create_test_recipe(&temp_dir_path, "soup", "Boil");
+        let options = TreeOptions {
+            sort: TreeSort::Natural,
+            ..Default::default()
+        };
+        let mut tree = build_tree_with_options(&temp_dir_path, &options).unwrap();
+
+        // Moving the only recipe out of a directory removes the directory
+        let moved = temp_dir_path.join("pancakes.cook");
+        fs::rename(&pancakes, &moved).unwrap();
+        let dinner_dir = temp_dir_path.join("dinner/mains");
+        fs::create_dir_all(&dinner_dir).unwrap();
+        let stew = create_test_recipe(&dinner_dir, "stew", "Simmer");
+        fs::write(
+            &soup,
+            indoc! {r#"
+                ---
+                title: Leek Soup
+                ---
+                Boil"#},
+        )
+        .unwrap();
+        let image = create_test_image(&temp_dir_path, "soup", "jpg");
+        for event in [
+            WatchEvent::RecipeRenamed {
+                from: pancakes,
+                to: moved,
+            },
+            WatchEvent::RecipeAdded(stew.clone()),
+            WatchEvent::RecipeModified(soup.clone()),
+            WatchEvent::ImageAdded(image.clone()),
+        ] {
+            tree.apply(&event, &options).unwrap();
+        }
+
+        let rebuilt = build_tree_with_options(&temp_dir_path, &options).unwrap();
+        assert_eq!(keys(&tree), keys(&rebuilt));
+        assert_eq!(keys(&tree), vec!["dinner", "pancakes", "soup"]);
+        assert_eq!(keys(&tree.children["dinner"]), vec!["mains"]);
+        let soup_node = &tree.children["soup"];
+        assert_eq!(soup_node.name, "Leek Soup");
+        let recipe = soup_node.recipe.as_ref().unwrap();
+        assert_eq!(recipe.title_image().as_deref(), Some(image.as_str()));
+
+        fs::remove_file(&stew).unwrap();
+        fs::remove_file(&image).unwrap();
+        tree.apply(&WatchEvent::RecipeRemoved(stew), &options)
+            .unwrap();
+        tree.apply(&WatchEvent::ImageRemoved(image), &options)
+            .unwrap();
+        assert_eq!(keys(&tree), vec!["pancakes", "soup"]);
+        assert!(tree.children["soup"]
+            .recipe
+            .as_ref()
+            .unwrap()
+            .title_image()
+            .is_none());
+
+        // Recipes that can no longer be read are dropped
+        fs::write(&soup, b"Boil @\xff\xfe{}").unwrap();
+        assert!(tree
+            .apply(&WatchEvent::RecipeModified(soup), &options)
+            .is_err());
+        assert_eq!(keys(&tree), vec!["pancakes"]);
+    }
+
+    #[test]
+    fn test_apply_skips_ignored_recipes() {
+        use crate::watch::WatchEvent;
+
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::write(temp_dir_path.join(".cookignore"), "archive/\n").unwrap();
+        let waffles = create_test_recipe(&temp_dir_path, "waffles", "Crispy waffles");
+        create_test_recipe(&temp_dir_path, "soup", "Boil");
+        let options = TreeOptions {
+            walk: crate::WalkOptions {
+                exclude: vec!["drafts/".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut tree = build_tree_with_options(&temp_dir_path, &options).unwrap();
+
+        fs::create_dir_all(temp_dir_path.join("archive")).unwrap();
+        fs::create_dir_all(temp_dir_path.join("drafts")).unwrap();
+        let moved = temp_dir_path.join("archive/waffles.cook");
+        fs::rename(&waffles, &moved).unwrap();
+        let archived = create_test_recipe(&temp_dir_path.join("archive"), "crepes", "Thin");
+        let draft = create_test_recipe(&temp_dir_path.join("drafts"), "blinis", "Small");
+        for event in [
+            WatchEvent::RecipeAdded(archived),
+            WatchEvent::RecipeModified(draft),
+            WatchEvent::RecipeRenamed {
+                from: waffles,
+                to: moved,
+            },
+        ] {
+            tree.apply(&event, &options).unwrap();
+        }
+
+        let rebuilt = build_tree_with_options(&temp_dir_path, &options).unwrap();
+        assert_eq!(keys(&tree), keys(&rebuilt));
+        assert_eq!(keys(&tree), vec!["soup"]);
+    }
+
+    #[test]
+    fn test_refresh_from_snapshot() {
+        use crate::watch::WatchEvent;
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let breakfast_dir = temp_dir_path.join("breakfast");
+        fs::create_dir_all(&breakfast_dir).unwrap();
+        let pancakes = create_test_recipe(&breakfast_dir, "pancakes", "Make pancakes");
+        let soup = create_test_recipe(&temp_dir_path, "soup", "Boil");
+        let stew = create_test_recipe(&temp_dir_path, "stew", "---\ntitle: Stew\n---\nSimmer");
+        let cake = create_test_recipe(&temp_dir_path, "cake", "Bake");
+        let tree = build_tree(&temp_dir_path).unwrap();
+        let json = serde_json::to_string(&tree).unwrap();
+        let snapshot: RecipeTree = serde_json::from_str(&json).unwrap();
+
+        // Rename, remove, add and modify recipes
+        let moved = temp_dir_path.join("pancakes.cook");
+        fs::rename(&pancakes, &moved).unwrap();
+        fs::remove_file(&cake).unwrap();
+        let tart = create_test_recipe(&temp_dir_path, "tart", "Bake a tart");
+        fs::write(
+            &soup,
+            indoc! {r#"
+                ---
+                title: Leek Soup
+                ---
+                Boil"#},
+        )
+        .unwrap();
+        let file = fs::File::options().write(true).open(&soup).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        create_test_image(&temp_dir_path, "stew", "jpg");
+
+        let report = refresh_tree(&snapshot, &TreeOptions::default()).unwrap();
+        assert!(report.is_complete());
+        let refresh = report.value;
+        assert_eq!(
+            refresh.changes,
+            vec![
+                WatchEvent::RecipeRenamed {
+                    from: pancakes,
+                    to: moved,
+                },
+                WatchEvent::RecipeRemoved(cake),
+                WatchEvent::RecipeAdded(tart),
+                WatchEvent::RecipeModified(soup),
+            ]
+        );
+        let rebuilt = build_tree(&temp_dir_path).unwrap();
+        assert_eq!(keys(&refresh.tree), keys(&rebuilt));
+        assert_eq!(refresh.tree.children["soup"].name, "Leek Soup");
+        // Images of unchanged recipes come from the walk
+        let stew_node = &refresh.tree.children["stew"];
+        assert!(stew_node.recipe.as_ref().unwrap().title_image().is_some());
+
+        // Files with an unchanged stamp are not read again
+        let time = fs::metadata(&stew).unwrap().modified().unwrap();
+        fs::write(&stew, "---\ntitle: Soup\n---\nSimmer").unwrap();
+        let file = fs::File::options().write(true).open(&stew).unwrap();
+        file.set_modified(time).unwrap();
+        let refresh = refresh_tree(&refresh.tree, &TreeOptions::default())
+            .unwrap()
+            .value;
+        assert!(refresh.changes.is_empty());
+        assert_eq!(refresh.tree.children["stew"].name, "Stew");
+    }
+
+    #[test]
+    fn test_diff_trees() {
+        let old_dir = TempDir::new().unwrap();
+        let new_dir = TempDir::new().unwrap();
+        let old_path = Utf8PathBuf::from_path_buf(old_dir.path().to_path_buf()).unwrap();
+        let new_path = Utf8PathBuf::from_path_buf(new_dir.path().to_path_buf()).unwrap();
+        for dir in [&old_path, &new_path] {
+            fs::create_dir_all(dir.join("breakfast")).unwrap();
+            create_test_recipe(dir, "soup", "Boil @water");
+        }
+        let tagged = indoc! {r#"
+            ---
+            tags: [quick]
+            servings: 2
+            ---
+            Mix @flour"#};
+        create_test_recipe(&old_path, "pancakes", tagged);
+        create_test_recipe(&old_path, "stew", "Simmer @beef");
+        create_test_recipe(&old_path, "cake", "Bake @flour");
+        create_test_recipe(&old_path, "tart", "Bake @apples");
+
+        // The same collection elsewhere, after syncing some changes
+        create_test_recipe(&new_path, "pancakes", &tagged.replace("quick", "sweet"));
+        create_test_recipe(&new_path, "stew", "Simmer @beef for hours");
+        create_test_recipe(&new_path.join("breakfast"), "cake", "Bake @flour");
+        create_test_recipe(&new_path, "apple tart", "Bake @apples");
+        create_test_recipe(&new_path, "toast", "Toast @bread");
+
+        let old = build_tree(&old_path).unwrap();
+        let new = build_tree(&new_path).unwrap();
+        let diff = diff_trees(&old, &new);
+        assert_eq!(diff.added, vec![Utf8PathBuf::from("toast.cook")]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.moved,
+            vec![
+                MovedRecipe {
+                    from: "cake.cook".into(),
+                    to: "breakfast/cake.cook".into(),
+                },
+                MovedRecipe {
+                    from: "tart.cook".into(),
+                    to: "apple tart.cook".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.modified,
+            vec![
+                ModifiedRecipe {
+                    path: "pancakes.cook".into(),
+                    metadata_keys: vec!["tags".to_string()],
+                },
+                ModifiedRecipe {
+                    path: "stew.cook".into(),
+                    metadata_keys: Vec::new(),
+                },
+            ]
+        );
+
+        let reversed = diff_trees(&new, &old);
+        assert_eq!(reversed.removed, vec![Utf8PathBuf::from("toast.cook")]);
+        assert_eq!(reversed.moved.len(), 2);
+
+        // Snapshots keep what is needed to compare them
+        let json = serde_json::to_string(&old).unwrap();
+        let snapshot: RecipeTree = serde_json::from_str(&json).unwrap();
+        assert!(diff_trees(&snapshot, &old).is_empty());
+        assert_eq!(diff_trees(&snapshot, &new), diff);
+    }
+
+    #[test]
+    fn test_filter_and_group_by() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let mains = temp_dir_path.join("mains");
+        let desserts = temp_dir_path.join("desserts");
+        fs::create_dir_all(&mains).unwrap();
+        fs::create_dir_all(&desserts).unwrap();
+        create_test_recipe(
+            &mains,
+            "curry",
+            indoc! {r#"
+                ---
+                course: Main
+                cuisine: Indian
+                tags: [vegan, spicy]
+                ---
+                Simmer @chickpeas"#},
+        );
+        create_test_recipe(
+            &mains,
+            "lasagne",
+            indoc! {r#"
+                ---
+                course: main
+                cuisine: [Italian, Comfort]
+                ---
+                Bake @pasta"#},
+        );
+        create_test_recipe(
+            &desserts,
+            "sorbet",
+            indoc! {r#"
+                ---
+                course: Dessert
+                tags: Vegan
+                ---
+                Freeze @lemons"#},
+        );
+        create_test_recipe(&temp_dir_path, "toast", "Toast @bread");
+        create_test_image(&desserts, "sorbet", "jpg");
+        fs::write(temp_dir_path.join("week.menu"), "@./mains/curry{}").unwrap();
+
+        let tree = build_tree(&temp_dir_path).unwrap();
+
+        // Directories left without recipes are pruned
+        let vegan = tree.filter_recipes(&RecipeFilter {
+            tags: vec!["VEGAN".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            vegan.children.keys().collect::<Vec<_>>(),
+            vec!["desserts", "mains"]
+        );
+        assert_eq!(
+            vegan.children["mains"].children.keys().collect::<Vec<_>>(),
+            vec!["curry"]
+        );
+
+        let with_image = tree.filter_recipes(&RecipeFilter {
+            has_title_image: Some(true),
+            metadata_keys: vec!["course".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            with_image.children.keys().collect::<Vec<_>>(),
+            vec!["desserts"]
+        );
+        let menus = tree.filter_recipes(&RecipeFilter {
+            is_menu: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(menus.children.keys().collect::<Vec<_>>(), vec!["week"]);
+        let all = tree.filter_recipes(&RecipeFilter::default());
+        assert!(diff_trees(&tree, &all).is_empty());
+        assert_eq!(
+            all.children.keys().collect::<Vec<_>>(),
+            tree.children.keys().collect::<Vec<_>>()
+        );
+
+        let none = tree.filter(|_| false);
+        assert_eq!(none.path, tree.path);
+        assert!(none.children.is_empty());
+
+        let pasta = tree.filter_query("@pasta").unwrap();
+        assert_eq!(pasta.children.keys().collect::<Vec<_>>(), vec!["mains"]);
+        assert_eq!(
+            pasta.children["mains"].children["lasagne"].path,
+            mains.join("lasagne.cook")
+        );
+        assert!(tree.filter_query("(pasta").is_err());
+
+        // Groups merge spellings and list recipes without the field next to them
+        let grouped = tree.group_by(&["course", "cuisine"]);
+        assert_eq!(
+            grouped.children.keys().collect::<Vec<_>>(),
+            vec!["Dessert", "Main", "toast", "week"]
+        );
+        let main = &grouped.children["Main"];
+        assert!(main.recipe.is_none());
+        assert_eq!(main.path, temp_dir_path.join("Main"));
+        assert_eq!(
+            main.children.keys().collect::<Vec<_>>(),
+            vec!["Comfort", "Indian", "Italian"]
+        );
+        assert_eq!(
+            main.children["Italian"].children["lasagne"].path,
+            mains.join("lasagne.cook")
+        );
+        assert!(main.children["Comfort"].children.contains_key("lasagne"));
+        assert_eq!(
+            grouped.children["Dessert"]
+                .children
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["sorbet"]
+        );
+
+        let by_tag = tree.group_by(&["tags"]);
+        assert_eq!(
+            by_tag.children.keys().collect::<Vec<_>>(),
+            vec!["spicy", "Vegan", "lasagne", "toast", "week"]
+        );
+        assert_eq!(
+            by_tag.children["Vegan"].children.keys().collect::<Vec<_>>(),
+            vec!["curry", "sorbet"]
+        );
+    }
+
     #[test]
     fn test_invalid_directory() {
         let result = build_tree(Utf8Path::new("/nonexistent/directory"));
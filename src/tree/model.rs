@@ -1,7 +1,12 @@
+use super::assets::Asset;
+use super::collision::{collect_collisions, NameCollision};
+use super::dir_config::DirConfig;
 use crate::model::RecipeEntry;
+use crate::normalize::{fold, nfc};
+use crate::walk::FileStamp;
 use camino::Utf8PathBuf;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 /// Represents a node in a hierarchical recipe directory tree.
 ///
@@ -14,20 +19,62 @@ use std::collections::HashMap;
 ///
 /// # Fields
 ///
-/// * `name` - The name of this node (directory or recipe name)
+/// * `name` - The displayed name of this node (directory title or name, or
+///   recipe title falling back to the file name)
 /// * `path` - The full filesystem path to this node
 /// * `recipe` - Optional recipe data if this node represents a recipe file
-/// * `children` - Child nodes indexed by their names
+/// * `children` - Child nodes keyed by their file names without extension, in
+///   composed (NFC) Unicode form and in the order chosen with
+///   [`TreeSort`](super::TreeSort)
+/// * `linked` - Whether this node was reached through a symbolic link
+/// * `config` - Settings of a directory from its
+///   [`DIR_CONFIG_FILE`](super::DIR_CONFIG_FILE), if it has one
+/// * `assets` - Other files in a directory, if requested with
+///   [`TreeOptions::assets`](super::TreeOptions::assets)
+/// * `unexpanded` - Whether a directory's content is yet to be listed
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RecipeTree {
-    /// Name of the current node (directory name or recipe name)
+    /// Displayed name of the current node (directory title from its config
+    /// falling back to the directory name, or recipe title falling back to
+    /// the file name without extension)
     pub name: String,
     /// Full path to this node
     pub path: Utf8PathBuf,
     /// If this is a recipe, contains the Recipe struct
     pub recipe: Option<RecipeEntry>,
-    /// Child directories and recipes
-    pub children: HashMap<String, RecipeTree>,
+    /// Child directories and recipes, keyed by their file names without
+    /// extension in composed (NFC) form, in sorted order
+    ///
+    /// Where siblings would share a key, later ones are keyed by their full
+    /// file name instead.
+    pub children: IndexMap<String, RecipeTree>,
+    /// True if this node is a symbolic link, or lies in a linked directory,
+    /// below the base directory of the tree
+    #[serde(default)]
+    pub linked: bool,
+    /// Settings of a directory from its [`DIR_CONFIG_FILE`](super::DIR_CONFIG_FILE),
+    /// such as its title, icon, description and cover image, or `None` if
+    /// it has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<DirConfig>,
+    /// Files in a directory other than recipes, sorted by path, if listed
+    /// with [`TreeOptions::assets`](super::TreeOptions::assets)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<Asset>,
+    /// True if this is a directory below
+    /// [`TreeOptions::max_depth`](super::TreeOptions::max_depth) whose
+    /// content has not been listed yet; see [`RecipeTree::expand`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unexpanded: bool,
+    /// Modification time and size of a recipe file when it was read, so
+    /// that [`refresh_tree`](super::refresh_tree) can tell whether it
+    /// changed since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stamp: Option<FileStamp>,
+    /// Hash of the content of a recipe file when it was read, so that
+    /// [`diff_trees`](super::diff_trees) can recognize moved recipes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) hash: Option<u64>,
 }
 
 impl RecipeTree {
@@ -36,7 +83,13 @@ impl RecipeTree {
             name,
             path,
             recipe: None,
-            children: HashMap::new(),
+            children: IndexMap::new(),
+            linked: false,
+            config: None,
+            assets: Vec::new(),
+            unexpanded: false,
+            stamp: None,
+            hash: None,
         }
     }
 
@@ -45,7 +98,83 @@ impl RecipeTree {
             name,
             path,
             recipe: Some(recipe),
-            children: HashMap::new(),
+            children: IndexMap::new(),
+            linked: false,
+            config: None,
+            assets: Vec::new(),
+            unexpanded: false,
+            stamp: None,
+            hash: None,
         }
     }
+
+    /// Creates a directory node, named after the title in its settings if
+    /// there is one.
+    pub(crate) fn new_dir(name: String, path: Utf8PathBuf, config: Option<DirConfig>) -> Self {
+        let title = config.as_ref().and_then(|config| config.title.clone());
+        let mut node = RecipeTree::new(title.unwrap_or(name), path);
+        node.config = config;
+        node
+    }
+
+    /// Returns the cover image of a directory from its settings, as a path
+    /// below the directory or, if it is given as one, a URL.
+    pub fn cover_image(&self) -> Option<String> {
+        let cover = self.config.as_ref()?.cover.as_deref()?.trim();
+        if cover.is_empty() {
+            None
+        } else if cover.contains("://") {
+            Some(cover.to_string())
+        } else {
+            Some(self.path.join(cover).into_string())
+        }
+    }
+
+    /// Returns the child node with the given name.
+    ///
+    /// The name is looked up as a key exactly first and then compared
+    /// ignoring case, Unicode normalization form and diacritics, so
+    /// "creme brulee" finds a child keyed "Crème Brûlée". Children are
+    /// keyed by file name, so a recipe can also be found by its displayed
+    /// title if no key matches.
+    pub fn child(&self, name: &str) -> Option<&RecipeTree> {
+        self.child_entry(name).map(|(_, child)| child)
+    }
+
+    /// Returns the key and node of the child with the given name, looked
+    /// up like [`RecipeTree::child`].
+    pub(crate) fn child_entry(&self, name: &str) -> Option<(&String, &RecipeTree)> {
+        if let Some(entry) = self
+            .children
+            .get_key_value(name)
+            .or_else(|| self.children.get_key_value(&nfc(name)))
+        {
+            return Some(entry);
+        }
+
+        let folded = fold(name);
+        let by_key = self
+            .children
+            .iter()
+            .filter(|(key, _)| fold(key) == folded)
+            .min_by_key(|(key, _)| *key);
+        by_key.or_else(|| {
+            self.children
+                .iter()
+                .filter(|(_, child)| fold(&child.name) == folded)
+                .min_by_key(|(key, _)| *key)
+        })
+    }
+
+    /// Returns the groups of siblings anywhere in the tree that share a
+    /// name, e.g. `Soup.cook` and `Soup.menu`, or two recipes with the same
+    /// title.
+    ///
+    /// Such siblings are kept apart under keys derived from their file
+    /// names; see [`NameCollision`].
+    pub fn collisions(&self) -> Vec<NameCollision> {
+        let mut collisions = Vec::new();
+        collect_collisions(self, &mut collisions);
+        collisions
+    }
 }
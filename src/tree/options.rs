@@ -0,0 +1,60 @@
+//! Options that control how recipe trees are built.
+
+use super::TreeSort;
+use crate::walk::WalkOptions;
+
+/// Options for [`build_tree_with_options`](super::build_tree_with_options)
+/// and [`build_tree_with_diagnostics`](super::build_tree_with_diagnostics).
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::{build_tree_with_options, TreeOptions, TreeSort, WalkOptions};
+///
+/// let options = TreeOptions {
+///     walk: WalkOptions {
+///         exclude: vec!["archive/".to_string()],
+///         ..Default::default()
+///     },
+///     sort: TreeSort::Natural,
+///     dirs_first: true,
+///     ..Default::default()
+/// };
+/// let tree = build_tree_with_options("./recipes", &options)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeOptions {
+    /// Files and directories left out of the tree, in addition to those
+    /// listed in `.cookignore` files
+    pub walk: WalkOptions,
+    /// How the children of every node are ordered
+    pub sort: TreeSort,
+    /// List directories before recipes
+    pub dirs_first: bool,
+    /// Also list the files other than recipes in the `assets` of their
+    /// directory's node, and include directories without recipes
+    ///
+    /// Hidden files and directories and the settings files read by this
+    /// library are left out.
+    pub assets: bool,
+    /// How many levels of subdirectories below the base directory are
+    /// listed, or `None` for all of them
+    ///
+    /// Directories one level deeper are included as nodes marked
+    /// [`unexpanded`](super::RecipeTree::unexpanded), whose content can be
+    /// listed later with [`RecipeTree::expand`](super::RecipeTree::expand).
+    /// `Some(0)` only lists the base directory itself. Hidden directories
+    /// are not included.
+    pub max_depth: Option<usize>,
+    /// Don't read recipe files while building the tree, but only when the
+    /// metadata of a recipe is first accessed
+    ///
+    /// Recipe nodes are then named after their files rather than their
+    /// titles, and recipes whose files cannot be read show only the
+    /// metadata they inherit instead of being left out. Such files are
+    /// not reported as skipped; use
+    /// [`RecipeEntry::try_metadata`](crate::RecipeEntry::try_metadata) to
+    /// find out why a recipe has no metadata.
+    pub lazy: bool,
+}
@@ -0,0 +1,174 @@
+//! Refreshing a recipe tree from a previous snapshot.
+
+use super::{
+    assemble, check_dir, load_recipe, scan_listing, DirConfig, ReadRecipe, RecipeTree, TreeError,
+    TreeOptions,
+};
+use crate::diagnostics::Partial;
+use crate::walk::FileStamp;
+use crate::watch::{changes, WatchEvent};
+use camino::{Utf8Path, Utf8PathBuf};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// An updated recipe tree and the changes since the snapshot it was
+/// refreshed from.
+#[derive(Debug)]
+pub struct TreeRefresh {
+    /// The tree as it is now
+    pub tree: RecipeTree,
+    /// The recipes added, removed, renamed and modified since the snapshot,
+    /// in the order [`RecipeWatcher`](crate::watch::RecipeWatcher) reports
+    /// them
+    ///
+    /// Image changes are not reported, as snapshots don't record images.
+    pub changes: Vec<WatchEvent>,
+}
+
+/// Brings a previously built recipe tree up to date with its directory.
+///
+/// The tree may come straight from [`build_tree`](super::build_tree) or be
+/// deserialized from a snapshot saved earlier, e.g. by an app between
+/// launches. The directory is walked again, but only recipe files whose
+/// modification time or size changed since the snapshot, or that are new,
+/// are read; all others are taken from the snapshot. Title and step images
+/// are taken from the same walk. Recipes in snapshots saved by versions of
+/// this library that did not record file stamps are read again.
+///
+/// The refreshed tree has the same keys and order as a tree built from
+/// scratch with the same options.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::{build_tree, refresh_tree, RecipeTree, TreeOptions};
+/// use std::fs;
+///
+/// let tree = build_tree("./recipes")?;
+/// fs::write("tree.json", serde_json::to_vec(&tree)?)?;
+///
+/// // On the next launch
+/// let snapshot: RecipeTree = serde_json::from_slice(&fs::read("tree.json")?)?;
+/// let refresh = refresh_tree(&snapshot, &TreeOptions::default())?.into_value();
+/// println!("{} changes", refresh.changes.len());
+/// let tree = refresh.tree;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `TreeError` if the directory of the tree no longer exists.
+pub fn refresh_tree(
+    previous: &RecipeTree,
+    options: &TreeOptions,
+) -> Result<Partial<TreeRefresh>, TreeError> {
+    let base_dir = previous.path.as_path();
+    check_dir(base_dir)?;
+
+    let mut before = BTreeMap::new();
+    let mut known = HashMap::new();
+    let mut configs = HashMap::new();
+    collect_nodes(previous, &mut before, &mut known, &mut configs);
+
+    let mut skipped = Vec::new();
+    let mut listing = scan_listing(base_dir, None, options, &mut skipped);
+    let paths = std::mem::take(&mut listing.recipes);
+
+    // Recipes carry the metadata they inherit from directory configs, so
+    // those below a changed config are read again
+    let changed_dirs = changed_configs(base_dir, &paths, &configs);
+    let inherits_changes =
+        |path: &Utf8Path| dirs_above(base_dir, path).any(|dir| changed_dirs.contains(dir));
+
+    let recipes: Vec<_> = paths
+        .par_iter()
+        .map(|path| {
+            let stamp = FileStamp::of(path).ok();
+            match known.get(path) {
+                Some(node) if stamp.is_some() && node.stamp == stamp && !inherits_changes(path) => {
+                    Ok(ReadRecipe {
+                        recipe: node.recipe.clone().unwrap(),
+                        stamp,
+                        hash: node.hash,
+                    })
+                }
+                _ => load_recipe(path, options),
+            }
+        })
+        .collect();
+
+    let after: BTreeMap<Utf8PathBuf, FileStamp> = paths
+        .iter()
+        .zip(&recipes)
+        .filter_map(|(path, recipe)| match recipe {
+            Ok(ReadRecipe {
+                stamp: Some(stamp), ..
+            }) => Some((path.clone(), *stamp)),
+            _ => None,
+        })
+        .collect();
+    let changes = changes(&before, &after);
+
+    let tree = assemble(
+        base_dir,
+        paths.into_iter().zip(recipes),
+        &listing,
+        options,
+        &mut skipped,
+    )?;
+    Ok(Partial::new(TreeRefresh { tree, changes }, skipped))
+}
+
+/// Collects the recipe nodes of a tree and their stamps, and the configs
+/// of its directories, by path.
+fn collect_nodes<'a>(
+    tree: &'a RecipeTree,
+    stamps: &mut BTreeMap<Utf8PathBuf, Option<FileStamp>>,
+    recipes: &mut HashMap<Utf8PathBuf, &'a RecipeTree>,
+    configs: &mut HashMap<Utf8PathBuf, Option<&'a DirConfig>>,
+) {
+    if tree.recipe.is_some() {
+        stamps.insert(tree.path.clone(), tree.stamp);
+        recipes.insert(tree.path.clone(), tree);
+    } else {
+        configs.insert(tree.path.clone(), tree.config.as_ref());
+    }
+    for child in tree.children.values() {
+        collect_nodes(child, stamps, recipes, configs);
+    }
+}
+
+/// Returns the directories holding recipe files whose config differs from
+/// the one in the snapshot.
+fn changed_configs(
+    base_dir: &Utf8Path,
+    paths: &[Utf8PathBuf],
+    previous: &HashMap<Utf8PathBuf, Option<&DirConfig>>,
+) -> HashSet<Utf8PathBuf> {
+    let mut checked = HashSet::new();
+    let mut changed = HashSet::new();
+    for path in paths {
+        for dir in dirs_above(base_dir, path) {
+            if !checked.insert(dir) {
+                break;
+            }
+            // Configs that cannot be read are reported when the tree is assembled
+            let config = DirConfig::read(dir).ok().flatten();
+            if previous.get(dir).copied().flatten() != config.as_ref() {
+                changed.insert(dir.to_path_buf());
+            }
+        }
+    }
+    changed
+}
+
+/// Returns the directories between a file and the base directory,
+/// including the latter.
+fn dirs_above<'a>(
+    base_dir: &'a Utf8Path,
+    path: &'a Utf8Path,
+) -> impl Iterator<Item = &'a Utf8Path> {
+    path.ancestors()
+        .skip(1)
+        .take_while(move |dir| dir.starts_with(base_dir))
+}
@@ -0,0 +1,166 @@
+//! Ordering of the children of recipe tree nodes.
+
+use super::model::RecipeTree;
+use crate::normalize::fold;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// How the children of every tree node are ordered.
+///
+/// Names are compared ignoring case and accents; ties keep a stable order
+/// by the exact key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeSort {
+    /// Alphabetically by file name
+    #[default]
+    Name,
+    /// By file name, comparing runs of digits by their value, so "2 Soup"
+    /// comes before "10 Soup"
+    Natural,
+    /// By the displayed name, i.e. the recipe title from metadata where
+    /// there is one
+    Title,
+    /// Most recently modified first
+    Modified,
+    /// In the order listed under `order` in each directory's
+    /// [`DIR_CONFIG_FILE`](super::DIR_CONFIG_FILE), followed by the unlisted children in natural
+    /// order
+    Custom,
+}
+
+/// Orders the children of a node and all nodes below it.
+///
+/// With [`TreeSort::Custom`], the children of directories without a
+/// config, e.g. because it could not be read, are ordered naturally.
+pub(crate) fn sort_tree(tree: &mut RecipeTree, sort: TreeSort, dirs_first: bool) {
+    sort_children(tree, sort, dirs_first);
+    for child in tree.children.values_mut() {
+        if !child.children.is_empty() {
+            sort_tree(child, sort, dirs_first);
+        }
+    }
+}
+
+/// Orders the children of a single node, like [`sort_tree`].
+pub(crate) fn sort_children(tree: &mut RecipeTree, sort: TreeSort, dirs_first: bool) {
+    let positions = match sort {
+        TreeSort::Custom => custom_positions(tree),
+        _ => HashMap::new(),
+    };
+    let modified: HashMap<String, Option<SystemTime>> = match sort {
+        TreeSort::Modified => tree
+            .children
+            .iter()
+            .map(|(key, child)| {
+                let modified = child.path.metadata().and_then(|m| m.modified()).ok();
+                (key.clone(), modified)
+            })
+            .collect(),
+        _ => HashMap::new(),
+    };
+
+    tree.children.sort_by(|a_key, a, b_key, b| {
+        let kind = if dirs_first {
+            a.recipe.is_some().cmp(&b.recipe.is_some())
+        } else {
+            Ordering::Equal
+        };
+        let ordering = match sort {
+            TreeSort::Name => Ordering::Equal,
+            TreeSort::Natural => natural_cmp(&fold(a_key), &fold(b_key)),
+            TreeSort::Title => fold(&a.name).cmp(&fold(&b.name)),
+            // Newest first, with unknown times last
+            TreeSort::Modified => modified[b_key].cmp(&modified[a_key]),
+            TreeSort::Custom => {
+                let position = |key: &String| positions.get(key).copied().unwrap_or(usize::MAX);
+                position(a_key)
+                    .cmp(&position(b_key))
+                    .then_with(|| natural_cmp(&fold(a_key), &fold(b_key)))
+            }
+        };
+        kind.then(ordering)
+            .then_with(|| fold(a_key).cmp(&fold(b_key)))
+            .then_with(|| a_key.cmp(b_key))
+    });
+}
+
+/// Returns the position of every child listed in the directory config of a
+/// node, by key.
+fn custom_positions(tree: &RecipeTree) -> HashMap<String, usize> {
+    let Some(config) = &tree.config else {
+        return HashMap::new();
+    };
+    let order: Vec<String> = config.order.iter().map(|name| fold(name.trim())).collect();
+
+    let mut positions = HashMap::new();
+    for (key, child) in &tree.children {
+        let names = [
+            Some(child.name.as_str()),
+            child.path.file_name(),
+            child.path.file_stem(),
+        ];
+        let position = names
+            .into_iter()
+            .flatten()
+            .filter_map(|name| order.iter().position(|entry| *entry == fold(name)))
+            .min();
+        if let Some(position) = position {
+            positions.insert(key.clone(), position);
+        }
+    }
+    positions
+}
+
+/// Compares strings with runs of ASCII digits compared by their value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        if a.is_empty() || b.is_empty() {
+            return a.is_empty().cmp(&b.is_empty()).reverse();
+        }
+        let (a_chunk, a_rest) = split_chunk(a);
+        let (b_chunk, b_rest) = split_chunk(b);
+        let is_number = |chunk: &str| chunk.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if is_number(a_chunk) && is_number(b_chunk) {
+            let a_digits = a_chunk.trim_start_matches('0');
+            let b_digits = b_chunk.trim_start_matches('0');
+            a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(b_digits))
+        } else {
+            a_chunk.cmp(b_chunk)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (a, b) = (a_rest, b_rest);
+    }
+}
+
+/// Splits off the leading run of digits or of other characters.
+fn split_chunk(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["10 soup", "2 soup", "soup", "1 soup", "02 stew", "soup 3"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["1 soup", "2 soup", "02 stew", "10 soup", "soup", "soup 3"]
+        );
+        assert_eq!(natural_cmp("a", "a"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+    }
+}
@@ -0,0 +1,228 @@
+//! Incremental updates of a recipe tree from watch events.
+
+use super::collision::{dir_entry, recipe_key};
+use super::dir_config::DirConfig;
+use super::model::RecipeTree;
+use super::sort::sort_children;
+use super::{load_recipe, recipe_name, ReadRecipe, TreeOptions};
+use crate::model::{Metadata, RecipeEntryError};
+use crate::normalize::nfc;
+use crate::walk::is_ignored;
+use crate::watch::WatchEvent;
+use camino::Utf8Path;
+
+impl RecipeTree {
+    /// Applies a change reported by a [`RecipeWatcher`](crate::watch::RecipeWatcher)
+    /// to this tree, which must be the root built for the watched directory.
+    ///
+    /// Only the affected nodes are touched: added recipes are read and
+    /// inserted, creating directory nodes as needed, removed recipes are
+    /// dropped together with directories left empty, and modified recipes
+    /// are re-read in place, keeping their keys. After an image event, the
+    /// title and step images of the recipes it may belong to are looked up
+    /// again. Siblings of a changed node are re-sorted with the sort order
+    /// of `options`; keys of other nodes stay as they are, even where a
+    /// fresh build would assign them differently after a collision went
+    /// away.
+    ///
+    /// Events for paths outside the tree or below an
+    /// [`unexpanded`](RecipeTree::unexpanded) directory are ignored, and
+    /// recipes left out by `.cookignore` files or `options.walk` are not
+    /// added. The
+    /// [`assets`](RecipeTree::assets) of directories are not updated; use
+    /// [`refresh_tree`](super::refresh_tree) to list them again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RecipeEntryError` if an added or modified recipe cannot be
+    /// read. The recipe is then left out of the tree, as it would be by
+    /// [`build_tree_with_diagnostics`](super::build_tree_with_diagnostics).
+    pub fn apply(
+        &mut self,
+        event: &WatchEvent,
+        options: &TreeOptions,
+    ) -> Result<(), RecipeEntryError> {
+        match event {
+            WatchEvent::RecipeAdded(path) | WatchEvent::RecipeModified(path) => {
+                self.put_recipe(path, options)
+            }
+            WatchEvent::RecipeRemoved(path) => {
+                self.remove_recipe(path);
+                Ok(())
+            }
+            WatchEvent::RecipeRenamed { from, to } => {
+                self.remove_recipe(from);
+                self.put_recipe(to, options)
+            }
+            WatchEvent::ImageAdded(path) | WatchEvent::ImageRemoved(path) => {
+                self.reset_images(path);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads a recipe file and inserts it into the tree, or replaces the
+    /// recipe of its existing node.
+    fn put_recipe(
+        &mut self,
+        path: &Utf8Path,
+        options: &TreeOptions,
+    ) -> Result<(), RecipeEntryError> {
+        let Some(dirs) = self.dirs_to(path) else {
+            return Ok(());
+        };
+        if self.is_unexpanded_along(&dirs) {
+            return Ok(());
+        }
+        if is_ignored(&self.path, path, &options.walk) {
+            self.remove_recipe(path);
+            return Ok(());
+        }
+        let ReadRecipe {
+            recipe,
+            stamp,
+            hash,
+        } = match load_recipe(path, options) {
+            Ok(read) => read,
+            Err(e) => {
+                self.remove_recipe(path);
+                return Err(e);
+            }
+        };
+
+        let base_dir = self.path.clone();
+        let mut inherited = match &self.config {
+            Some(config) => config.defaults(&Metadata::default()),
+            None => Metadata::default(),
+        };
+        let mut current = &mut *self;
+        for dir in &dirs {
+            let path = current.path.join(dir);
+            current = dir_entry(&mut current.children, dir, || {
+                // Directory configs that cannot be read are reported by full builds
+                let config = DirConfig::read(&path).ok().flatten();
+                let mut node = RecipeTree::new_dir(dir.clone(), path, config);
+                node.linked = is_linked(&base_dir, &node.path);
+                node
+            });
+            if let Some(config) = &current.config {
+                inherited = config.defaults(&inherited);
+            }
+        }
+        let recipe = recipe.inherit(&inherited);
+        let name = recipe_name(&recipe, path, options);
+
+        match current.children.values_mut().find(|node| node.path == path) {
+            Some(node) => {
+                node.name = name;
+                node.recipe = Some(recipe);
+                node.stamp = stamp;
+                node.hash = hash;
+            }
+            None => {
+                let key = recipe_key(&current.children, path);
+                let mut node = RecipeTree::new_with_recipe(name, path.to_path_buf(), recipe);
+                node.linked = is_linked(&base_dir, path);
+                node.stamp = stamp;
+                node.hash = hash;
+                current.children.insert(key, node);
+            }
+        }
+
+        self.sort_along(&dirs, options);
+        Ok(())
+    }
+
+    /// Checks whether one of the existing directory nodes on the way to a
+    /// file is unexpanded.
+    fn is_unexpanded_along(&self, dirs: &[String]) -> bool {
+        let mut current = self;
+        for dir in dirs {
+            match current.children.get(&nfc(dir)) {
+                Some(child) if child.unexpanded => return true,
+                Some(child) => current = child,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Removes the node of a recipe file and any directories left empty.
+    fn remove_recipe(&mut self, path: &Utf8Path) {
+        if let Some(dirs) = self.dirs_to(path) {
+            self.remove_below(&dirs, path);
+        }
+    }
+
+    /// Removes a recipe node below the given directories, returning whether
+    /// it was found.
+    fn remove_below(&mut self, dirs: &[String], path: &Utf8Path) -> bool {
+        let Some((dir, rest)) = dirs.split_first() else {
+            let index = self.children.values().position(|node| node.path == path);
+            return index
+                .and_then(|index| self.children.shift_remove_index(index))
+                .is_some();
+        };
+        let key = nfc(dir);
+        let Some(child) = self.children.get_mut(&key) else {
+            return false;
+        };
+        let removed = child.remove_below(rest, path);
+        if removed && child.recipe.is_none() && child.children.is_empty() && child.assets.is_empty()
+        {
+            self.children.shift_remove(&key);
+        }
+        removed
+    }
+
+    /// Makes the recipes an image may belong to look up their images again.
+    fn reset_images(&mut self, image: &Utf8Path) {
+        let (Some(dirs), Some(file_name)) = (self.dirs_to(image), image.file_name()) else {
+            return;
+        };
+        let mut current = &mut *self;
+        for dir in &dirs {
+            match current.children.get_mut(&nfc(dir)) {
+                Some(child) => current = child,
+                None => return,
+            }
+        }
+        for node in current.children.values_mut() {
+            let Some(stem) = node.path.file_stem() else {
+                continue;
+            };
+            if let Some(recipe) = &mut node.recipe {
+                if file_name.starts_with(&format!("{stem}.")) {
+                    recipe.reset_images();
+                }
+            }
+        }
+    }
+
+    /// Sorts the children of this node and of the directories leading to
+    /// a changed node.
+    fn sort_along(&mut self, dirs: &[String], options: &TreeOptions) {
+        sort_children(self, options.sort, options.dirs_first);
+        if let Some((dir, rest)) = dirs.split_first() {
+            if let Some(child) = self.children.get_mut(&nfc(dir)) {
+                child.sort_along(rest, options);
+            }
+        }
+    }
+
+    /// Returns the names of the directories between this node and a file
+    /// below it, or `None` if the file is not below it.
+    fn dirs_to(&self, path: &Utf8Path) -> Option<Vec<String>> {
+        let relative = path.strip_prefix(&self.path).ok()?;
+        let parent = relative.parent()?;
+        Some(parent.components().map(|c| c.to_string()).collect())
+    }
+}
+
+/// Checks whether a path, or a directory between the base directory and it,
+/// is a symbolic link.
+fn is_linked(base_dir: &Utf8Path, path: &Utf8Path) -> bool {
+    path.ancestors()
+        .take_while(|ancestor| *ancestor != base_dir)
+        .any(|ancestor| ancestor.is_symlink())
+}
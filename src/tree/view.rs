@@ -0,0 +1,243 @@
+//! Filtered and regrouped views of a recipe tree.
+
+use super::collision::{dir_entry, recipe_key};
+use super::model::RecipeTree;
+use super::sort::sort_tree;
+use super::TreeSort;
+use crate::model::{Metadata, RecipeEntry};
+use crate::normalize::fold;
+use crate::search::{matching_paths, IntoQuery, SearchError, SearchOptions};
+use indexmap::IndexMap;
+use serde_yaml::Value;
+
+/// Criteria for [`RecipeTree::filter_recipes`]; a recipe is kept if it
+/// meets all of them.
+///
+/// The default filter keeps every recipe.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::{build_tree, RecipeFilter};
+///
+/// let tree = build_tree("./recipes")?;
+/// let filter = RecipeFilter {
+///     tags: vec!["vegan".to_string()],
+///     is_menu: Some(false),
+///     ..Default::default()
+/// };
+/// let vegan = tree.filter_recipes(&filter);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecipeFilter {
+    /// Tags the recipe must all have, compared ignoring case and diacritics
+    pub tags: Vec<String>,
+    /// Metadata keys the recipe must all have
+    pub metadata_keys: Vec<String>,
+    /// Keep only menus if true, or only recipes if false
+    pub is_menu: Option<bool>,
+    /// Keep only recipes with a title image if true, or only those without
+    /// one if false
+    pub has_title_image: Option<bool>,
+}
+
+impl RecipeFilter {
+    /// Checks whether a recipe meets all criteria.
+    pub fn matches(&self, recipe: &RecipeEntry) -> bool {
+        let tags: Vec<String> = recipe.tags().iter().map(|tag| fold(tag)).collect();
+        self.tags.iter().all(|tag| tags.contains(&fold(tag)))
+            && self
+                .metadata_keys
+                .iter()
+                .all(|key| recipe.metadata().get(key).is_some())
+            && self
+                .is_menu
+                .is_none_or(|is_menu| recipe.is_menu() == is_menu)
+            && self
+                .has_title_image
+                .is_none_or(|has_image| recipe.title_image().is_some() == has_image)
+    }
+}
+
+impl RecipeTree {
+    /// Returns a copy of the tree with only the recipes for which
+    /// `predicate` returns true.
+    ///
+    /// Directories left without recipes are left out; the root is always
+    /// kept. Keys, order and all other fields of the remaining nodes are
+    /// unchanged.
+    pub fn filter(&self, predicate: impl Fn(&RecipeEntry) -> bool) -> RecipeTree {
+        self.filtered(&predicate)
+            .unwrap_or_else(|| self.copy_without_children())
+    }
+
+    /// Returns a copy of the tree with only the recipes that meet the
+    /// criteria of `filter`, like [`filter`](RecipeTree::filter).
+    pub fn filter_recipes(&self, filter: &RecipeFilter) -> RecipeTree {
+        self.filter(|recipe| filter.matches(recipe))
+    }
+
+    /// Returns a copy of the tree with only the recipes that
+    /// [`search`](crate::search::search) would return for a query, like
+    /// [`filter`](RecipeTree::filter).
+    ///
+    /// The recipe files are read again to match the query against their
+    /// content; files that can no longer be read are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SearchError` if the query cannot be parsed.
+    pub fn filter_query(&self, query: impl IntoQuery) -> Result<RecipeTree, SearchError> {
+        self.filter_query_with_options(query, &SearchOptions::default())
+    }
+
+    /// Returns a copy of the tree with only the recipes that
+    /// [`search_with_options`](crate::search::search_with_options) would
+    /// return for a query, like [`filter_query`](RecipeTree::filter_query).
+    ///
+    /// `offset` and `limit` are ignored, and only `.cookignore` files and
+    /// exclusions applied when the tree was built decide which files are in
+    /// it.
+    pub fn filter_query_with_options(
+        &self,
+        query: impl IntoQuery,
+        options: &SearchOptions,
+    ) -> Result<RecipeTree, SearchError> {
+        let mut paths = Vec::new();
+        self.visit_recipes(&mut |node| paths.push(node.path.clone()));
+        let matching = matching_paths(&self.path, paths, query, options)?;
+        Ok(self.filter(|recipe| recipe.path().is_some_and(|path| matching.contains(path))))
+    }
+
+    /// Regroups the recipes of the tree into a virtual tree keyed by
+    /// metadata fields instead of directories.
+    ///
+    /// The first field decides the groups below the root, the second one
+    /// the groups within each of those, and so on; `["course", "cuisine"]`
+    /// puts a recipe with `course: main` and `cuisine: italian` at
+    /// `main/italian`. A recipe with several values for a field, like a
+    /// list of tags, is listed in every group. Values are compared ignoring
+    /// case and diacritics; a group is named after the first spelling found.
+    /// Recipes without a field are listed at the level of that field,
+    /// next to its groups.
+    ///
+    /// Group nodes have no recipe, and a path made of the root path and the
+    /// group names, which does not exist on disk. Recipes keep their own
+    /// paths. Groups are listed before recipes, both in natural order.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cooklang_find::build_tree;
+    ///
+    /// let tree = build_tree("./recipes")?;
+    /// let by_course = tree.group_by(&["course", "cuisine"]);
+    /// for (course, node) in &by_course.children {
+    ///     println!("{course}: {} entries", node.children.len());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn group_by<S: AsRef<str>>(&self, fields: &[S]) -> RecipeTree {
+        let mut root = self.copy_without_children();
+        self.visit_recipes(&mut |node| {
+            let metadata = node.recipe.as_ref().unwrap().metadata();
+            add_grouped(&mut root, node, metadata, fields);
+        });
+        sort_tree(&mut root, TreeSort::Natural, true);
+        root
+    }
+
+    /// Copies the nodes of the tree that are, or lead to, recipes matching
+    /// `predicate`.
+    fn filtered(&self, predicate: &dyn Fn(&RecipeEntry) -> bool) -> Option<RecipeTree> {
+        if let Some(recipe) = &self.recipe {
+            return predicate(recipe).then(|| self.copy_without_children());
+        }
+        let children: IndexMap<String, RecipeTree> = self
+            .children
+            .iter()
+            .filter_map(|(key, child)| Some((key.clone(), child.filtered(predicate)?)))
+            .collect();
+        if children.is_empty() {
+            return None;
+        }
+        let mut node = self.copy_without_children();
+        node.children = children;
+        Some(node)
+    }
+
+    /// Copies a node without its children, keeping cached recipe data.
+    pub(crate) fn copy_without_children(&self) -> RecipeTree {
+        let mut node = RecipeTree::new(self.name.clone(), self.path.clone());
+        node.recipe = self.recipe.as_ref().map(RecipeEntry::clone_cached);
+        node.linked = self.linked;
+        node.config = self.config.clone();
+        node.assets = self.assets.clone();
+        node.unexpanded = self.unexpanded;
+        node.stamp = self.stamp;
+        node.hash = self.hash;
+        node
+    }
+
+    /// Calls `visit` for every recipe node in the tree, in order.
+    fn visit_recipes<'a>(&'a self, visit: &mut dyn FnMut(&'a RecipeTree)) {
+        if self.recipe.is_some() {
+            visit(self);
+        }
+        for child in self.children.values() {
+            child.visit_recipes(visit);
+        }
+    }
+}
+
+/// Adds a copy of a recipe node to every group it belongs to below `parent`.
+fn add_grouped<S: AsRef<str>>(
+    parent: &mut RecipeTree,
+    node: &RecipeTree,
+    metadata: &Metadata,
+    fields: &[S],
+) {
+    let Some((field, rest)) = fields.split_first() else {
+        let key = recipe_key(&parent.children, &node.path);
+        parent.children.insert(key, node.copy_without_children());
+        return;
+    };
+    let values = field_values(metadata, field.as_ref());
+    if values.is_empty() {
+        return add_grouped(parent, node, metadata, &[] as &[S]);
+    }
+    for value in values {
+        let folded = fold(&value);
+        let existing = parent
+            .children
+            .values()
+            .find(|child| child.recipe.is_none() && fold(&child.name) == folded)
+            .map(|child| child.name.clone());
+        let name = existing.unwrap_or(value);
+        let path = parent.path.join(&name);
+        let group = dir_entry(&mut parent.children, &name, || {
+            RecipeTree::new(name.clone(), path)
+        });
+        add_grouped(group, node, metadata, rest);
+    }
+}
+
+/// Returns the values of a metadata field to group by: every tag for
+/// `tags`, the items of a list, or a single value.
+fn field_values(metadata: &Metadata, field: &str) -> Vec<String> {
+    if field == "tags" || field == "tag" {
+        return metadata.tags();
+    }
+    let scalar = |value: &Value| match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    };
+    match metadata.get(field) {
+        Some(Value::Sequence(values)) => values.iter().filter_map(scalar).collect(),
+        Some(value) => scalar(value).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
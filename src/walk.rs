@@ -0,0 +1,750 @@
+//! Directory walks shared by searching, tree building and fetching.
+//!
+//! Every walk over a recipe directory honors gitignore-style `.cookignore`
+//! files, so archives, version control data and sync folders like
+//! Syncthing's `.stversions/` can be kept out of all results at once. A
+//! `.cookignore` applies to the directory it is in and everything below
+//! it; rules in deeper files take precedence, and `!pattern` re-includes
+//! files excluded further up.
+//!
+//! A recipe directory is walked once, with subdirectories read in parallel,
+//! and the same walk lists the images that belong to the recipes found, so
+//! they don't have to be looked up file by file afterwards.
+
+use crate::diagnostics::{lossy_path, SkippedFile};
+use crate::model::is_image_file;
+use camino::{Utf8Path, Utf8PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// Name of the ignore file honored in every recipe directory.
+pub const COOKIGNORE_FILE: &str = ".cookignore";
+
+/// Name of the git ignore file, honored when [`WalkOptions::gitignore`] is set.
+pub const GITIGNORE_FILE: &str = ".gitignore";
+
+/// Options controlling which files directory walks leave out.
+///
+/// `.cookignore` files are always honored. The default options add no
+/// further exclusions.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::search::{search_with_options, SearchOptions};
+/// use cooklang_find::WalkOptions;
+/// use camino::Utf8Path;
+///
+/// let options = SearchOptions {
+///     walk: WalkOptions {
+///         exclude: vec!["archive/".to_string(), "*.draft.cook".to_string()],
+///         gitignore: true,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let results = search_with_options(Utf8Path::new("./recipes"), "soup", &options)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalkOptions {
+    /// Gitignore-style patterns of files and directories to leave out,
+    /// relative to the base directory, e.g. `archive/` or `*.draft.cook`
+    ///
+    /// These take precedence over ignore files, so a `.cookignore` cannot
+    /// re-include what they exclude.
+    pub exclude: Vec<String>,
+    /// Also honor `.gitignore` files, with lower precedence than
+    /// `.cookignore` files in the same directory
+    pub gitignore: bool,
+    /// How symbolic links to files and directories are treated
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+}
+
+/// How directory walks treat symbolic links.
+///
+/// Whatever the policy, a link to a directory that is already being walked
+/// (one of the directories above the link) is never entered, so links
+/// can't send a walk into a loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Follow links to files and directories, listing the files they lead
+    /// to at the path of the link
+    #[default]
+    Follow,
+    /// Leave out links to files and directories
+    Skip,
+    /// Follow links like [`Follow`](SymlinkPolicy::Follow), but list every
+    /// file only once, even if links make it reachable through several
+    /// paths
+    ///
+    /// A file is listed at its own path if the walk reaches it without
+    /// links, and otherwise at the first such path in sorted order.
+    FollowOnce,
+}
+
+impl WalkOptions {
+    /// Returns the names of the ignore files honored, lowest precedence first.
+    fn ignore_files(&self) -> &'static [&'static str] {
+        if self.gitignore {
+            &[GITIGNORE_FILE, COOKIGNORE_FILE]
+        } else {
+            &[COOKIGNORE_FILE]
+        }
+    }
+
+    /// Builds the matcher for the exclude patterns of a base directory.
+    ///
+    /// Invalid patterns are recorded in `skipped` and otherwise ignored.
+    fn excludes(&self, base_dir: &Utf8Path, skipped: &mut Vec<SkippedFile>) -> Gitignore {
+        let mut builder = GitignoreBuilder::new(base_dir);
+        for pattern in &self.exclude {
+            if let Err(e) = builder.add_line(None, pattern) {
+                skipped.push(SkippedFile::from_walk_error(e));
+            }
+        }
+        builder.build().unwrap_or_else(|e| {
+            skipped.push(SkippedFile::from_walk_error(e));
+            Gitignore::empty()
+        })
+    }
+}
+
+/// The files found by a single walk over a recipe directory.
+#[derive(Debug, Default)]
+pub(crate) struct Listing {
+    /// All .cook and .menu files, sorted, with all .cook files before all
+    /// .menu files
+    pub(crate) recipes: Vec<Utf8PathBuf>,
+    /// The sorted image files of every directory that has any, by directory
+    pub(crate) images: HashMap<Utf8PathBuf, Vec<Utf8PathBuf>>,
+    /// The sorted files other than recipes and images of every directory
+    /// that has any, by directory; only listed with [`Scope::all_files`]
+    pub(crate) others: HashMap<Utf8PathBuf, Vec<Utf8PathBuf>>,
+    /// All directories below the base directory, sorted; only listed with
+    /// [`Scope::all_files`]
+    pub(crate) dirs: Vec<Utf8PathBuf>,
+    /// The directories at the depth limit of the walk, which were not
+    /// entered, sorted
+    pub(crate) unexpanded: Vec<Utf8PathBuf>,
+    /// The directory that was walked
+    base_dir: Utf8PathBuf,
+    /// Symbolic links that were followed, as found below the base directory
+    links: HashSet<Utf8PathBuf>,
+}
+
+impl Listing {
+    /// Returns the sorted image files in the directory of a recipe file.
+    pub(crate) fn images_for(&self, recipe: &Utf8Path) -> &[Utf8PathBuf] {
+        recipe
+            .parent()
+            .and_then(|dir| self.images.get(dir))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Checks whether a listed path was reached through a symbolic link,
+    /// either because it is one or because a directory between the base
+    /// directory and it is one.
+    pub(crate) fn is_linked(&self, path: &Utf8Path) -> bool {
+        !self.links.is_empty()
+            && path
+                .ancestors()
+                .take_while(|ancestor| *ancestor != self.base_dir)
+                .any(|ancestor| self.links.contains(ancestor))
+    }
+}
+
+/// Collects all .cook and .menu files below a directory that are not ignored.
+///
+/// Paths are sorted, with all .cook files before all .menu files. Entries
+/// that cannot be listed, whose path is not valid UTF-8, or ignore files
+/// that cannot be parsed are recorded in `skipped`.
+pub(crate) fn recipe_files(
+    base_dir: &Utf8Path,
+    options: &WalkOptions,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<Utf8PathBuf> {
+    scan(base_dir, options, skipped).recipes
+}
+
+/// Walks a directory once, listing the recipe and image files that are not
+/// ignored.
+///
+/// Directories are read in parallel across all cores. Entries that cannot
+/// be listed, whose path is not valid UTF-8, or ignore files that cannot be
+/// parsed are recorded in `skipped`.
+pub(crate) fn scan(
+    base_dir: &Utf8Path,
+    options: &WalkOptions,
+    skipped: &mut Vec<SkippedFile>,
+) -> Listing {
+    walk_dir(base_dir, options, Scope::default(), skipped)
+}
+
+/// Which part of a directory a walk lists, and how much of it.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Scope<'a> {
+    /// Only list what is below this directory, which must lie below the
+    /// base directory; the directories on the way are walked, so their
+    /// ignore files apply
+    pub(crate) below: Option<&'a Utf8Path>,
+    /// How many levels of subdirectories below the base directory, or
+    /// below `below`, are entered; those one level deeper are only listed
+    /// in [`Listing::unexpanded`]
+    pub(crate) max_depth: Option<usize>,
+    /// Also list all other files and all directories that are not ignored
+    pub(crate) all_files: bool,
+}
+
+/// Walks part of a directory like [`scan`], as limited by `scope`.
+pub(crate) fn scan_scoped(
+    base_dir: &Utf8Path,
+    options: &WalkOptions,
+    scope: Scope,
+    skipped: &mut Vec<SkippedFile>,
+) -> Listing {
+    walk_dir(base_dir, options, scope, skipped)
+}
+
+/// Walks the part of a directory in `scope` for [`scan`] and [`scan_scoped`].
+fn walk_dir(
+    base_dir: &Utf8Path,
+    options: &WalkOptions,
+    scope: Scope,
+    skipped: &mut Vec<SkippedFile>,
+) -> Listing {
+    let excludes = options.excludes(base_dir, skipped);
+    let below = scope.below.map(|dir| dir.as_std_path().to_path_buf());
+    let below_depth = scope
+        .below
+        .and_then(|dir| dir.strip_prefix(base_dir).ok())
+        .map_or(0, |relative| relative.components().count());
+    // Entries at this depth are listed, but directories there not entered
+    let limit = scope.max_depth.map(|depth| below_depth + depth + 1);
+    let all_files = scope.all_files;
+
+    let mut builder = WalkBuilder::new(base_dir);
+    builder
+        .standard_filters(false)
+        .follow_links(options.symlinks != SymlinkPolicy::Skip)
+        .max_depth(limit)
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            let path = entry.path();
+            let in_scope = below
+                .as_ref()
+                .is_none_or(|dir| path.starts_with(dir) || dir.starts_with(path));
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            in_scope && !excludes.matched(path, is_dir).is_ignore()
+        });
+    for name in options.ignore_files() {
+        builder.add_custom_ignore_filename(name);
+    }
+
+    let found = Mutex::new(Found::default());
+    builder.build_parallel().run(|| {
+        Box::new(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // Loops are expected with links and simply not walked again
+                Err(e) if is_loop(&e) => return WalkState::Continue,
+                Err(e) => {
+                    let skipped = SkippedFile::from_walk_error(e);
+                    found
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .skipped
+                        .push(skipped);
+                    return WalkState::Continue;
+                }
+            };
+            let link = (entry.depth() > 0 && entry.path_is_symlink())
+                .then(|| Utf8Path::from_path(entry.path()).map(Utf8Path::to_path_buf))
+                .flatten();
+            let is_dir = entry.depth() > 0 && entry.file_type().is_some_and(|t| t.is_dir());
+            let at_limit = is_dir && Some(entry.depth()) == limit;
+            let found_file = found_file(entry, all_files || at_limit);
+            if link.is_some() || found_file.is_some() {
+                let mut found = found.lock().unwrap_or_else(PoisonError::into_inner);
+                found.links.extend(link);
+                match found_file {
+                    Some(Ok(path)) if is_dir => {
+                        if at_limit {
+                            found.unexpanded.push(path.clone());
+                        }
+                        if all_files {
+                            found.dirs.push(path);
+                        }
+                    }
+                    Some(Ok(path)) => found.paths.push(path),
+                    Some(Err(skipped)) => found.skipped.push(skipped),
+                    None => {}
+                }
+            }
+            WalkState::Continue
+        })
+    });
+    let found = found.into_inner().unwrap_or_else(PoisonError::into_inner);
+    skipped.extend(found.skipped);
+
+    let mut listing = Listing {
+        base_dir: base_dir.to_path_buf(),
+        links: found.links,
+        dirs: found.dirs,
+        unexpanded: found.unexpanded,
+        ..Default::default()
+    };
+    for path in found.paths {
+        if is_recipe_file(path.as_std_path()) {
+            listing.recipes.push(path);
+        } else if let Some(dir) = path.parent() {
+            let files = if is_image_file(path.as_std_path()) {
+                &mut listing.images
+            } else {
+                &mut listing.others
+            };
+            files.entry(dir.to_path_buf()).or_default().push(path);
+        }
+    }
+
+    // Directories are visited in no particular order, so sort afterwards,
+    // keeping .cook files ahead of .menu files of the same name
+    listing.recipes.sort();
+    listing
+        .recipes
+        .sort_by_key(|path| path.extension() == Some("menu"));
+    for files in listing
+        .images
+        .values_mut()
+        .chain(listing.others.values_mut())
+    {
+        files.sort();
+    }
+    listing.dirs.sort();
+    listing.unexpanded.sort();
+    if options.symlinks == SymlinkPolicy::FollowOnce {
+        dedup_linked(&mut listing);
+    }
+    listing
+}
+
+/// What the threads of a parallel walk found.
+#[derive(Default)]
+struct Found {
+    paths: Vec<Utf8PathBuf>,
+    dirs: Vec<Utf8PathBuf>,
+    unexpanded: Vec<Utf8PathBuf>,
+    links: HashSet<Utf8PathBuf>,
+    skipped: Vec<SkippedFile>,
+}
+
+/// Checks whether a walk error reports a file system loop.
+fn is_loop(error: &ignore::Error) -> bool {
+    match error {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// Removes recipes reached through links whose target is listed already,
+/// comparing canonical paths.
+///
+/// Recipes reached without links are kept, and otherwise the first path in
+/// sorted order. Recipes whose link target cannot be resolved are kept.
+fn dedup_linked(listing: &mut Listing) {
+    if listing.links.is_empty() {
+        return;
+    }
+    // Paths reached without links only need the base directory resolved
+    let canonical_base = listing.base_dir.canonicalize_utf8().ok();
+    let canonical = |path: &Utf8Path| match &canonical_base {
+        Some(base) if !listing.is_linked(path) => path
+            .strip_prefix(&listing.base_dir)
+            .ok()
+            .map(|relative| base.join(relative)),
+        _ => path.canonicalize_utf8().ok(),
+    };
+
+    let mut seen = HashSet::new();
+    let (direct, linked): (Vec<_>, Vec<_>) = listing
+        .recipes
+        .iter()
+        .partition(|path| !listing.is_linked(path));
+    for path in &direct {
+        seen.extend(canonical(path));
+    }
+    let duplicates: HashSet<Utf8PathBuf> = linked
+        .into_iter()
+        .filter(|path| canonical(path).is_some_and(|target| !seen.insert(target)))
+        .cloned()
+        .collect();
+    listing.recipes.retain(|path| !duplicates.contains(path));
+}
+
+/// Returns the path of a walked recipe or image file, or with `all_files`
+/// of any file or directory below the base directory, an error for such an
+/// entry whose path is not valid UTF-8, or `None` for any other entry.
+fn found_file(entry: DirEntry, all_files: bool) -> Option<Result<Utf8PathBuf, SkippedFile>> {
+    let path = entry.path();
+    let wanted = match entry.file_type() {
+        Some(t) if t.is_file() => all_files || is_recipe_file(path) || is_image_file(path),
+        Some(t) if t.is_dir() => all_files && entry.depth() > 0,
+        _ => false,
+    };
+    if !wanted {
+        return None;
+    }
+    Some(
+        Utf8PathBuf::from_path_buf(entry.into_path()).map_err(|path| {
+            SkippedFile::new(
+                lossy_path(&path),
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Path contains invalid UTF-8",
+                ),
+            )
+        }),
+    )
+}
+
+/// Modification time and size of a file, used to detect changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    pub(crate) fn of(path: &Utf8Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Hashes file content with 64-bit FNV-1a, which unlike the standard
+/// library's hasher is the same across releases, so hashes can be kept in
+/// snapshots.
+pub(crate) fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Checks whether a file below a base directory is left out by the walk options
+/// or the ignore files between the base directory and the file.
+///
+/// Paths outside the base directory are never ignored.
+pub(crate) fn is_ignored(base_dir: &Utf8Path, path: &Utf8Path, options: &WalkOptions) -> bool {
+    let Ok(relative) = path.strip_prefix(base_dir) else {
+        return false;
+    };
+    let excludes = options.excludes(base_dir, &mut Vec::new());
+
+    // Check every directory on the way down, as a walk would
+    let mut matchers = vec![ignore_matcher(base_dir, options)];
+    let mut current = base_dir.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        let is_dir = current.is_dir();
+        if excludes.matched(&current, is_dir).is_ignore() {
+            return true;
+        }
+        // The deepest ignore file with a matching rule decides
+        let decision = matchers
+            .iter()
+            .rev()
+            .map(|matcher| matcher.matched(&current, is_dir))
+            .find(|m| !m.is_none());
+        if decision.is_some_and(|m| m.is_ignore()) {
+            return true;
+        }
+        if is_dir {
+            matchers.push(ignore_matcher(&current, options));
+        }
+    }
+    false
+}
+
+/// Builds the matcher for the ignore files of a single directory.
+fn ignore_matcher(dir: &Utf8Path, options: &WalkOptions) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    for name in options.ignore_files() {
+        let path = dir.join(name);
+        if path.is_file() {
+            // Unparsable lines are skipped, like during a walk
+            let _ = builder.add(path);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Checks whether a path has a recipe extension.
+fn is_recipe_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "cook" || extension == "menu")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Utf8PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        for path in [
+            "soup.cook",
+            "dinner.menu",
+            "notes.txt",
+            "archive/old soup.cook",
+            "desserts/cake.cook",
+            "desserts/cake.draft.cook",
+            "desserts/drafts/tart.cook",
+            "desserts/drafts/keep.cook",
+            ".stversions/soup~20240101.cook",
+        ] {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "Some recipe").unwrap();
+        }
+        (temp_dir, dir)
+    }
+
+    fn relative(dir: &Utf8Path, paths: Vec<Utf8PathBuf>) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| path.strip_prefix(dir).unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_recipe_files() {
+        let (_temp_dir, dir) = setup();
+        let mut skipped = Vec::new();
+        let paths = recipe_files(&dir, &WalkOptions::default(), &mut skipped);
+        assert_eq!(
+            relative(&dir, paths),
+            vec![
+                ".stversions/soup~20240101.cook",
+                "archive/old soup.cook",
+                "desserts/cake.cook",
+                "desserts/cake.draft.cook",
+                "desserts/drafts/keep.cook",
+                "desserts/drafts/tart.cook",
+                "soup.cook",
+                "dinner.menu",
+            ]
+        );
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn test_scan_lists_images() {
+        let (_temp_dir, dir) = setup();
+        for path in [
+            "soup.jpg",
+            "soup.1.png",
+            "desserts/cake.webp",
+            "desserts/cake.gif",
+        ] {
+            fs::write(dir.join(path), "dummy image content").unwrap();
+        }
+        fs::write(dir.join(COOKIGNORE_FILE), "archive/\n.stversions/\n").unwrap();
+
+        let mut skipped = Vec::new();
+        let listing = scan(&dir, &WalkOptions::default(), &mut skipped);
+        assert_eq!(
+            relative(&dir, listing.recipes.clone()),
+            vec![
+                "desserts/cake.cook",
+                "desserts/cake.draft.cook",
+                "desserts/drafts/keep.cook",
+                "desserts/drafts/tart.cook",
+                "soup.cook",
+                "dinner.menu",
+            ]
+        );
+        assert_eq!(
+            relative(&dir, listing.images_for(&dir.join("soup.cook")).to_vec()),
+            vec!["soup.1.png", "soup.jpg"]
+        );
+        assert_eq!(
+            relative(
+                &dir,
+                listing.images_for(&dir.join("desserts/cake.cook")).to_vec()
+            ),
+            vec!["desserts/cake.webp"]
+        );
+        assert!(listing
+            .images_for(&dir.join("desserts/drafts/tart.cook"))
+            .is_empty());
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn test_cookignore() {
+        let (_temp_dir, dir) = setup();
+        fs::write(
+            dir.join(COOKIGNORE_FILE),
+            "archive/\n.stversions/\n*.draft.cook\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("desserts/drafts").join(COOKIGNORE_FILE),
+            "*\n!keep.cook\n",
+        )
+        .unwrap();
+
+        let options = WalkOptions::default();
+        let paths = recipe_files(&dir, &options, &mut Vec::new());
+        assert_eq!(
+            relative(&dir, paths),
+            vec![
+                "desserts/cake.cook",
+                "desserts/drafts/keep.cook",
+                "soup.cook",
+                "dinner.menu"
+            ]
+        );
+
+        assert!(is_ignored(
+            &dir,
+            &dir.join("archive/old soup.cook"),
+            &options
+        ));
+        assert!(is_ignored(
+            &dir,
+            &dir.join("desserts/cake.draft.cook"),
+            &options
+        ));
+        assert!(is_ignored(
+            &dir,
+            &dir.join("desserts/drafts/tart.cook"),
+            &options
+        ));
+        assert!(!is_ignored(
+            &dir,
+            &dir.join("desserts/drafts/keep.cook"),
+            &options
+        ));
+        assert!(!is_ignored(&dir, &dir.join("soup.cook"), &options));
+    }
+
+    #[test]
+    fn test_exclude_and_gitignore() {
+        let (_temp_dir, dir) = setup();
+        fs::write(dir.join(GITIGNORE_FILE), "desserts/\n").unwrap();
+        // A .cookignore can't re-include what the options exclude
+        fs::write(dir.join(COOKIGNORE_FILE), "!archive/\n").unwrap();
+
+        let options = WalkOptions {
+            exclude: vec!["archive".to_string(), ".*/".to_string()],
+            ..Default::default()
+        };
+        let paths = recipe_files(&dir, &options, &mut Vec::new());
+        assert_eq!(
+            relative(&dir, paths),
+            vec![
+                "desserts/cake.cook",
+                "desserts/cake.draft.cook",
+                "desserts/drafts/keep.cook",
+                "desserts/drafts/tart.cook",
+                "soup.cook",
+                "dinner.menu"
+            ]
+        );
+        assert!(is_ignored(
+            &dir,
+            &dir.join("archive/old soup.cook"),
+            &options
+        ));
+
+        let options = WalkOptions {
+            gitignore: true,
+            ..options
+        };
+        let paths = recipe_files(&dir, &options, &mut Vec::new());
+        assert_eq!(relative(&dir, paths), vec!["soup.cook", "dinner.menu"]);
+        assert!(is_ignored(&dir, &dir.join("desserts/cake.cook"), &options));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let dir = root.join("recipes");
+        let elsewhere = root.join("elsewhere");
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::create_dir_all(&elsewhere).unwrap();
+        fs::write(dir.join("soup.cook"), "Some recipe").unwrap();
+        fs::write(dir.join("shared/cake.cook"), "Some recipe").unwrap();
+        fs::write(elsewhere.join("pie.cook"), "Some recipe").unwrap();
+        symlink(dir.join("soup.cook"), dir.join("favorite.cook")).unwrap();
+        symlink(dir.join("shared"), dir.join("desserts")).unwrap();
+        symlink(&dir, dir.join("shared/loop")).unwrap();
+        symlink(&elsewhere, dir.join("pies")).unwrap();
+        symlink(&elsewhere, dir.join("more pies")).unwrap();
+
+        let walk = |symlinks| {
+            let options = WalkOptions {
+                symlinks,
+                ..Default::default()
+            };
+            let mut skipped = Vec::new();
+            let listing = scan(&dir, &options, &mut skipped);
+            assert!(skipped.is_empty(), "{skipped:?}");
+            listing
+        };
+
+        let listing = walk(SymlinkPolicy::Follow);
+        assert_eq!(
+            relative(&dir, listing.recipes.clone()),
+            vec![
+                "desserts/cake.cook",
+                "favorite.cook",
+                "more pies/pie.cook",
+                "pies/pie.cook",
+                "shared/cake.cook",
+                "soup.cook",
+            ]
+        );
+        assert!(listing.is_linked(&dir.join("desserts")));
+        assert!(listing.is_linked(&dir.join("desserts/cake.cook")));
+        assert!(listing.is_linked(&dir.join("favorite.cook")));
+        assert!(!listing.is_linked(&dir.join("shared/cake.cook")));
+        assert!(!listing.is_linked(&dir.join("soup.cook")));
+
+        let listing = walk(SymlinkPolicy::Skip);
+        assert_eq!(
+            relative(&dir, listing.recipes),
+            vec!["shared/cake.cook", "soup.cook"]
+        );
+
+        let listing = walk(SymlinkPolicy::FollowOnce);
+        assert_eq!(
+            relative(&dir, listing.recipes),
+            vec!["more pies/pie.cook", "shared/cake.cook", "soup.cook"]
+        );
+    }
+}
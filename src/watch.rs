@@ -0,0 +1,546 @@
+//! Watching recipe directories for changes.
+//!
+//! A [`RecipeWatcher`] reports changes below a recipe directory as typed
+//! [`WatchEvent`]s, which can be applied to a [`RecipeTree`](crate::RecipeTree)
+//! with [`RecipeTree::apply`](crate::RecipeTree::apply) and to a
+//! [`SearchIndex`](crate::search::SearchIndex) with
+//! [`SearchIndex::apply`](crate::search::SearchIndex::apply), keeping both
+//! up to date without rebuilding them.
+//!
+//! The watcher uses the native notification mechanism of the platform
+//! (inotify on Linux and Android, FSEvents on macOS, kqueue on iOS) and
+//! falls back to polling where that is unavailable, e.g. on some network
+//! file systems. Raw notifications are only used as hints: the files they
+//! name are compared against what the watcher saw before, so events are
+//! reported once per change, honor `.cookignore` files and
+//! [`WalkOptions`], and are the same whichever backend is in use.
+
+use crate::model::is_image_file;
+use crate::walk::{is_ignored, scan, FileStamp, WalkOptions};
+use camino::{Utf8Path, Utf8PathBuf};
+use notify::{Config, PollWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors that can occur while watching a recipe directory.
+#[derive(Error, Debug)]
+pub enum WatchError {
+    #[error("Directory does not exist: {0}")]
+    DirectoryNotFound(String),
+
+    #[error("Failed to watch directory: {0}")]
+    Notify(#[from] notify::Error),
+
+    #[error("Failed to resolve directory: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("The watcher stopped delivering events")]
+    Disconnected,
+}
+
+/// A change to the recipes or images below a watched directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A recipe file was created, or moved into the watched directory
+    RecipeAdded(Utf8PathBuf),
+    /// A recipe file was deleted, or moved out of the watched directory
+    RecipeRemoved(Utf8PathBuf),
+    /// A recipe file was moved or renamed within the watched directory
+    RecipeRenamed { from: Utf8PathBuf, to: Utf8PathBuf },
+    /// The content of a recipe file changed
+    RecipeModified(Utf8PathBuf),
+    /// An image file was created, or moved into the watched directory
+    ImageAdded(Utf8PathBuf),
+    /// An image file was deleted, or moved out of the watched directory
+    ImageRemoved(Utf8PathBuf),
+}
+
+/// How a [`RecipeWatcher`] learns about changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchBackend {
+    /// Notifications from the operating system
+    Native,
+    /// Periodic scans of the directory
+    Polling,
+}
+
+/// Options for [`RecipeWatcher::new`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// Files and directories to leave out, in addition to those listed in
+    /// `.cookignore` files
+    pub walk: WalkOptions,
+    /// Poll even if native notifications are available
+    pub force_polling: bool,
+    /// How often the directory is scanned when polling
+    pub poll_interval: Duration,
+    /// How long to wait for further notifications before reporting a
+    /// batch of changes, so that a file written in several steps is
+    /// reported once
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            walk: WalkOptions::default(),
+            force_polling: false,
+            poll_interval: Duration::from_secs(2),
+            debounce: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Watches a recipe directory and reports changes to its recipes and images.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cooklang_find::watch::{RecipeWatcher, WatchOptions};
+/// use cooklang_find::{build_tree_with_options, TreeOptions};
+/// use std::time::Duration;
+///
+/// let options = TreeOptions::default();
+/// let mut tree = build_tree_with_options("./recipes", &options)?;
+/// let mut watcher = RecipeWatcher::new("./recipes", WatchOptions::default())?;
+/// loop {
+///     for event in watcher.next_events(Duration::from_secs(1))? {
+///         if let Err(e) = tree.apply(&event, &options) {
+///             eprintln!("Failed to apply {event:?}: {e}");
+///         }
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct RecipeWatcher {
+    base_dir: Utf8PathBuf,
+    options: WatchOptions,
+    backend: WatchBackend,
+    /// Absolute forms of the base directory that notifications may use
+    roots: Vec<Utf8PathBuf>,
+    /// Delivers notifications for as long as it is kept
+    _watcher: Box<dyn Watcher + Send>,
+    receiver: Receiver<notify::Result<notify::Event>>,
+    /// Recipe and image files as last seen, by path
+    known: BTreeMap<Utf8PathBuf, FileStamp>,
+}
+
+impl RecipeWatcher {
+    /// Starts watching a directory and everything below it.
+    ///
+    /// The files present when the watcher starts are not reported; only
+    /// changes made afterwards are.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WatchError` if the directory does not exist or cannot be
+    /// watched with either backend.
+    pub fn new<P: AsRef<Utf8Path>>(base_dir: P, options: WatchOptions) -> Result<Self, WatchError> {
+        let base_dir = base_dir.as_ref().to_path_buf();
+        if !base_dir.is_dir() {
+            return Err(WatchError::DirectoryNotFound(base_dir.to_string()));
+        }
+        let absolute = if base_dir.is_absolute() {
+            base_dir.clone()
+        } else {
+            let current = std::env::current_dir()?;
+            Utf8PathBuf::try_from(current)
+                .map_err(|e| e.into_io_error())?
+                .join(&base_dir)
+        };
+        let mut roots = vec![absolute.clone()];
+        if let Ok(canonical) = absolute.canonicalize_utf8() {
+            if canonical != absolute {
+                roots.push(canonical);
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let native = if options.force_polling {
+            None
+        } else {
+            notify::recommended_watcher(sender.clone())
+                .and_then(|mut watcher| {
+                    watcher.watch(absolute.as_std_path(), RecursiveMode::Recursive)?;
+                    Ok(watcher)
+                })
+                .ok()
+        };
+        let (watcher, backend): (Box<dyn Watcher + Send>, _) = match native {
+            Some(watcher) => (Box::new(watcher), WatchBackend::Native),
+            None => {
+                let config = Config::default().with_poll_interval(options.poll_interval);
+                let mut watcher = PollWatcher::new(sender, config)?;
+                watcher.watch(absolute.as_std_path(), RecursiveMode::Recursive)?;
+                (Box::new(watcher), WatchBackend::Polling)
+            }
+        };
+
+        let mut watcher = RecipeWatcher {
+            base_dir,
+            options,
+            backend,
+            roots,
+            _watcher: watcher,
+            receiver,
+            known: BTreeMap::new(),
+        };
+        watcher.known = watcher.stamps_below(&watcher.base_dir.clone());
+        Ok(watcher)
+    }
+
+    /// Returns the directory being watched.
+    pub fn base_dir(&self) -> &Utf8Path {
+        &self.base_dir
+    }
+
+    /// Returns how the watcher learns about changes.
+    pub fn backend(&self) -> WatchBackend {
+        self.backend
+    }
+
+    /// Waits up to `timeout` for changes and returns them.
+    ///
+    /// Once a first notification arrives, further ones are collected until
+    /// none has arrived for [`WatchOptions::debounce`]. Returns an empty
+    /// list if nothing changed within `timeout`, or if the notifications
+    /// did not amount to a change, e.g. a file saved with the same content.
+    ///
+    /// Recipe renames are recognized by a recipe disappearing and another
+    /// one with the same modification time and size appearing in the same
+    /// batch. Events are ordered renames first, then removals, additions
+    /// and modifications of recipes, then removed and added images.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WatchError::Disconnected`] if the backend stopped.
+    pub fn next_events(&mut self, timeout: Duration) -> Result<Vec<WatchEvent>, WatchError> {
+        let first = match self.receiver.recv_timeout(timeout) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => return Err(WatchError::Disconnected),
+        };
+        let mut batch = vec![first];
+        while let Ok(event) = self.receiver.recv_timeout(self.options.debounce) {
+            batch.push(event);
+        }
+
+        let mut touched = BTreeSet::new();
+        let mut rescan = false;
+        for event in batch {
+            match event {
+                Ok(event) if !event.need_rescan() => {
+                    touched.extend(event.paths.into_iter().filter_map(|p| self.local_path(p)));
+                }
+                // Missed notifications leave nothing to go by
+                _ => rescan = true,
+            }
+        }
+        if rescan {
+            touched = BTreeSet::from([self.base_dir.clone()]);
+        }
+        Ok(self.reconcile(touched))
+    }
+
+    /// Maps a path from a notification to the same path below the base
+    /// directory as it was given, or `None` for paths outside of it.
+    fn local_path(&self, path: PathBuf) -> Option<Utf8PathBuf> {
+        let path = Utf8PathBuf::from_path_buf(path).ok()?;
+        self.roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .map(|relative| match relative.as_str() {
+                "" => self.base_dir.clone(),
+                _ => self.base_dir.join(relative),
+            })
+    }
+
+    /// Compares the files at or below the touched paths with what was seen
+    /// before, updates the known files and returns the changes.
+    fn reconcile(&mut self, touched: BTreeSet<Utf8PathBuf>) -> Vec<WatchEvent> {
+        // A change to an ignore file may change everything in its directory
+        let ignore_files = [crate::walk::COOKIGNORE_FILE, crate::walk::GITIGNORE_FILE];
+        let touched: BTreeSet<Utf8PathBuf> = touched
+            .into_iter()
+            .map(|path| match (path.file_name(), path.parent()) {
+                (Some(name), Some(dir)) if ignore_files.contains(&name) => dir.to_path_buf(),
+                _ => path,
+            })
+            .collect();
+
+        let mut before = BTreeMap::new();
+        let mut after = BTreeMap::new();
+        let mut last: Option<&Utf8PathBuf> = None;
+        for root in &touched {
+            // Paths below a root already looked at are covered by it
+            if last.is_some_and(|last| root.starts_with(last)) {
+                continue;
+            }
+            last = Some(root);
+            before.extend(
+                self.known
+                    .range(root.clone()..)
+                    .take_while(|(path, _)| path.starts_with(root))
+                    .map(|(path, stamp)| (path.clone(), Some(*stamp))),
+            );
+            after.extend(self.stamps_below(root));
+        }
+
+        for path in before.keys() {
+            self.known.remove(path);
+        }
+        self.known
+            .extend(after.iter().map(|(p, s)| (p.clone(), *s)));
+        changes(&before, &after)
+    }
+
+    /// Returns the recipe and image files at or below a path that are not
+    /// ignored, with their stamps.
+    fn stamps_below(&self, path: &Utf8Path) -> BTreeMap<Utf8PathBuf, FileStamp> {
+        let walk = &self.options.walk;
+        let is_base = path == self.base_dir;
+        if !is_base && (!path.starts_with(&self.base_dir) || is_ignored(&self.base_dir, path, walk))
+        {
+            return BTreeMap::new();
+        }
+
+        let files: Vec<Utf8PathBuf> = if path.is_dir() {
+            // Exclude patterns are relative to the base directory, so below
+            // it they are checked file by file instead
+            let options = if is_base {
+                walk.clone()
+            } else {
+                WalkOptions {
+                    exclude: Vec::new(),
+                    ..walk.clone()
+                }
+            };
+            let listing = scan(path, &options, &mut Vec::new());
+            let images = listing.images.into_values().flatten();
+            listing
+                .recipes
+                .into_iter()
+                .chain(images)
+                .filter(|file| is_base || !is_ignored(&self.base_dir, file, walk))
+                .collect()
+        } else if path.is_file() && (is_recipe_path(path) || is_image_file(path.as_std_path())) {
+            vec![path.to_path_buf()]
+        } else {
+            Vec::new()
+        };
+
+        files
+            .into_iter()
+            .filter_map(|file| FileStamp::of(&file).ok().map(|stamp| (file, stamp)))
+            .collect()
+    }
+}
+
+/// Returns the changes between the recipe and image files seen before and
+/// after, with their stamps.
+///
+/// Files seen before without a stamp are reported as modified if they are
+/// still there. A removed recipe and an added one with the same stamp are
+/// reported as a rename, preferring one with the same file name. Events
+/// are ordered renames first, then removals, additions and modifications
+/// of recipes, then removed and added images, each sorted by path.
+pub(crate) fn changes(
+    before: &BTreeMap<Utf8PathBuf, Option<FileStamp>>,
+    after: &BTreeMap<Utf8PathBuf, FileStamp>,
+) -> Vec<WatchEvent> {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    for (path, stamp) in before {
+        match after.get(path) {
+            None => removed.push(path.clone()),
+            Some(now) if *stamp != Some(*now) && !is_image_file(path.as_std_path()) => {
+                modified.push(path.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    for path in after.keys() {
+        if !before.contains_key(path) {
+            added.push(path.clone());
+        }
+    }
+
+    let (removed_images, removed): (Vec<_>, Vec<_>) = removed
+        .into_iter()
+        .partition(|path| is_image_file(path.as_std_path()));
+    let (added_images, mut added): (Vec<_>, Vec<_>) = added
+        .into_iter()
+        .partition(|path| is_image_file(path.as_std_path()));
+
+    let mut events = Vec::new();
+    let mut gone = Vec::new();
+    for from in removed {
+        let stamp = before.get(&from).copied().flatten();
+        let same_stamp = |to: &&Utf8PathBuf| stamp.is_some() && after.get(*to) == stamp.as_ref();
+        let found = added
+            .iter()
+            .filter(same_stamp)
+            .find(|to| to.file_name() == from.file_name())
+            .or_else(|| added.iter().find(same_stamp))
+            .cloned();
+        match found {
+            Some(to) => {
+                added.retain(|path| *path != to);
+                events.push(WatchEvent::RecipeRenamed { from, to });
+            }
+            None => gone.push(from),
+        }
+    }
+    events.extend(gone.into_iter().map(WatchEvent::RecipeRemoved));
+    events.extend(added.into_iter().map(WatchEvent::RecipeAdded));
+    events.extend(modified.into_iter().map(WatchEvent::RecipeModified));
+    events.extend(removed_images.into_iter().map(WatchEvent::ImageRemoved));
+    events.extend(added_images.into_iter().map(WatchEvent::ImageAdded));
+    events
+}
+
+/// Checks whether a path has a recipe extension.
+fn is_recipe_path(path: &Utf8Path) -> bool {
+    matches!(path.extension(), Some("cook" | "menu"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    fn create_test_recipe(dir: &Utf8Path, name: &str, content: &str) -> Utf8PathBuf {
+        let path = dir.join(format!("{name}.cook"));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn setup() -> (TempDir, Utf8PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::create_dir_all(dir.join("breakfast")).unwrap();
+        create_test_recipe(&dir, "soup", "Boil @water");
+        create_test_recipe(&dir.join("breakfast"), "pancakes", "Mix @flour");
+        (temp_dir, dir)
+    }
+
+    /// Collects events until `expected` of them arrived or a few seconds passed.
+    fn wait_for(watcher: &mut RecipeWatcher, expected: usize) -> Vec<WatchEvent> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut events = Vec::new();
+        while events.len() < expected && Instant::now() < deadline {
+            events.extend(watcher.next_events(Duration::from_millis(200)).unwrap());
+        }
+        events
+    }
+
+    fn options(force_polling: bool) -> WatchOptions {
+        WatchOptions {
+            force_polling,
+            poll_interval: Duration::from_millis(50),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reconcile_reports_typed_events() {
+        let (_temp_dir, dir) = setup();
+        fs::write(dir.join(".cookignore"), "*.draft.cook\n").unwrap();
+        let mut watcher = RecipeWatcher::new(&dir, options(true)).unwrap();
+
+        fs::rename(
+            dir.join("breakfast/pancakes.cook"),
+            dir.join("pancakes.cook"),
+        )
+        .unwrap();
+        fs::remove_file(dir.join("soup.cook")).unwrap();
+        create_test_recipe(&dir, "stew", "Simmer @beef");
+        create_test_recipe(&dir, "stew.draft", "Simmer @lamb");
+        fs::write(dir.join("stew.jpg"), "dummy image content").unwrap();
+
+        let events = watcher.reconcile(BTreeSet::from([dir.clone()]));
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::RecipeRenamed {
+                    from: dir.join("breakfast/pancakes.cook"),
+                    to: dir.join("pancakes.cook"),
+                },
+                WatchEvent::RecipeRemoved(dir.join("soup.cook")),
+                WatchEvent::RecipeAdded(dir.join("stew.cook")),
+                WatchEvent::ImageAdded(dir.join("stew.jpg")),
+            ]
+        );
+
+        // Nothing is reported twice
+        assert!(watcher.reconcile(BTreeSet::from([dir.clone()])).is_empty());
+
+        fs::write(dir.join("stew.cook"), "Simmer @beef for a long time").unwrap();
+        fs::remove_file(dir.join("stew.jpg")).unwrap();
+        let events = watcher.reconcile(BTreeSet::from([
+            dir.join("stew.cook"),
+            dir.join("stew.jpg"),
+        ]));
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::RecipeModified(dir.join("stew.cook")),
+                WatchEvent::ImageRemoved(dir.join("stew.jpg")),
+            ]
+        );
+
+        // Un-ignoring files adds them
+        fs::write(dir.join(".cookignore"), "").unwrap();
+        let events = watcher.reconcile(BTreeSet::from([dir.join(".cookignore")]));
+        assert_eq!(
+            events,
+            vec![WatchEvent::RecipeAdded(dir.join("stew.draft.cook"))]
+        );
+    }
+
+    #[test]
+    fn test_native_watcher() {
+        let (_temp_dir, dir) = setup();
+        let mut watcher = RecipeWatcher::new(&dir, options(false)).unwrap();
+
+        let stew = create_test_recipe(&dir.join("breakfast"), "stew", "Simmer @beef");
+        assert_eq!(
+            wait_for(&mut watcher, 1),
+            vec![WatchEvent::RecipeAdded(stew)]
+        );
+
+        fs::remove_file(dir.join("soup.cook")).unwrap();
+        assert_eq!(
+            wait_for(&mut watcher, 1),
+            vec![WatchEvent::RecipeRemoved(dir.join("soup.cook"))]
+        );
+    }
+
+    #[test]
+    fn test_polling_watcher() {
+        let (_temp_dir, dir) = setup();
+        let mut watcher = RecipeWatcher::new(&dir, options(true)).unwrap();
+        assert_eq!(watcher.backend(), WatchBackend::Polling);
+
+        fs::create_dir_all(dir.join("dinner")).unwrap();
+        let stew = create_test_recipe(&dir.join("dinner"), "stew", "Simmer @beef");
+        assert_eq!(
+            wait_for(&mut watcher, 1),
+            vec![WatchEvent::RecipeAdded(stew)]
+        );
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        assert!(matches!(
+            RecipeWatcher::new("/nonexistent/directory", WatchOptions::default()),
+            Err(WatchError::DirectoryNotFound(_))
+        ));
+    }
+}